@@ -0,0 +1,142 @@
+//! Right-to-left and mixed-direction text: which way a label reads, and
+//! joining it with the hotkey hints so that neither reorders the other.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use unicode_bidi::{Direction, get_base_direction};
+
+/// First-strong isolate: the enclosed text takes the direction of its first
+/// letter without affecting what is around it.
+const FSI: char = '\u{2068}';
+/// Left-to-right isolate.
+const LRI: char = '\u{2066}';
+/// Pop directional isolate, closing `FSI` or `LRI`.
+const PDI: char = '\u{2069}';
+
+/// Reading direction of the overlay text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TextDirection {
+    /// From the first letter of the label: right-to-left for Hebrew or
+    /// Arabic, left-to-right otherwise.
+    #[default]
+    Auto,
+    Ltr,
+    Rtl,
+}
+
+impl TextDirection {
+    /// Whether `text` reads right-to-left.
+    pub fn is_rtl(self, text: &str) -> bool {
+        match self {
+            TextDirection::Auto => get_base_direction(text) == Direction::Rtl,
+            TextDirection::Ltr => false,
+            TextDirection::Rtl => true,
+        }
+    }
+}
+
+/// Where lines narrower than the overlay go, relative to the reading
+/// direction: `start` is left for English and right for Hebrew.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAlign {
+    Start,
+    #[default]
+    Center,
+    End,
+}
+
+impl TextAlign {
+    /// Where a line's left edge goes in the space it does not fill: 0.0 at
+    /// the left, 1.0 at the right.
+    pub fn ratio(self, rtl: bool) -> f32 {
+        match (self, rtl) {
+            (TextAlign::Center, _) => 0.5,
+            (TextAlign::Start, false) | (TextAlign::End, true) => 0.0,
+            (TextAlign::Start, true) | (TextAlign::End, false) => 1.0,
+        }
+    }
+}
+
+/// A label and its hints on one line. Offsets are in UTF-16 code units, as
+/// DirectWrite counts them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Joined {
+    pub text: String,
+    /// Where the label starts.
+    pub label_start: u32,
+    pub hints_start: u32,
+    pub hints_len: u32,
+}
+
+/// `label` followed by `hints`, each isolated: a Hebrew label keeps the
+/// English hints from being reordered into it, and the hints' parentheses
+/// stay the right way round on a right-to-left line.
+pub fn join_hints(label: &str, hints: &str) -> Joined {
+    if hints.is_empty() {
+        return Joined {
+            text: label.to_string(),
+            label_start: 0,
+            hints_start: utf16_len(label),
+            hints_len: 0,
+        };
+    }
+    let head = format!("{FSI}{label}{PDI} {LRI}");
+    Joined {
+        text: format!("{head}{hints}{PDI}"),
+        label_start: FSI.len_utf16() as u32,
+        hints_start: utf16_len(&head),
+        hints_len: utf16_len(hints),
+    }
+}
+
+fn utf16_len(s: &str) -> u32 {
+    s.encode_utf16().count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slice(s: &str, start: u32, len: u32) -> String {
+        let units: Vec<u16> = s.encode_utf16().collect();
+        String::from_utf16(&units[start as usize..(start + len) as usize]).unwrap()
+    }
+
+    #[test]
+    fn auto_direction_follows_the_first_letter() {
+        assert!(TextDirection::Auto.is_rtl("שחרור ABC-123"));
+        assert!(TextDirection::Auto.is_rtl("12: مرحبا"));
+        assert!(!TextDirection::Auto.is_rtl("ABC-123 שחרור"));
+        assert!(!TextDirection::Auto.is_rtl(""));
+        assert!(TextDirection::Rtl.is_rtl("Work"));
+        assert!(!TextDirection::Ltr.is_rtl("עבודה"));
+    }
+
+    #[test]
+    fn alignment_flips_with_the_direction() {
+        assert_eq!(TextAlign::Start.ratio(false), 0.0);
+        assert_eq!(TextAlign::Start.ratio(true), 1.0);
+        assert_eq!(TextAlign::End.ratio(true), 0.0);
+        assert_eq!(TextAlign::Center.ratio(true), 0.5);
+    }
+
+    #[test]
+    fn hints_are_isolated_from_the_label() {
+        let label = "באג 🐛 ABC-12";
+        let j = join_hints(label, "(Ctrl+Alt+T)");
+        assert_eq!(
+            j.text,
+            "\u{2068}באג 🐛 ABC-12\u{2069} \u{2066}(Ctrl+Alt+T)\u{2069}"
+        );
+        assert_eq!(slice(&j.text, j.label_start, utf16_len(label)), label);
+        assert_eq!(slice(&j.text, j.hints_start, j.hints_len), "(Ctrl+Alt+T)");
+
+        let j = join_hints("Work", "");
+        assert_eq!(
+            (j.text.as_str(), j.label_start, j.hints_len),
+            ("Work", 0, 0)
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Human-readable hotkeys, built from the configured chords so hints never
+//! disagree with what is actually registered.
+
+use crate::config::{Hotkeys, KeyChord};
+use crate::i18n::tr;
+
+/// What each hotkey does, in menu order and the current language.
+pub fn bindings(hk: &Hotkeys) -> [(&'static str, &KeyChord); 7] {
+    [
+        (tr("action.edit_title"), &hk.edit_title),
+        (tr("action.edit_description"), &hk.edit_description),
+        (tr("action.toggle_overlay"), &hk.toggle_overlay),
+        (tr("action.snap_position"), &hk.snap_position),
+        (tr("action.cycle_profile"), &hk.cycle_profile),
+        (tr("action.undo_label"), &hk.undo_label),
+        (tr("action.redo_label"), &hk.redo_label),
+    ]
+}
+
+fn modifiers(c: &KeyChord) -> String {
+    let mut out = String::new();
+    for (on, name) in [(c.ctrl, "Ctrl+"), (c.alt, "Alt+"), (c.shift, "Shift+")] {
+        if on {
+            out.push_str(name);
+        }
+    }
+    out
+}
+
+fn key(c: &KeyChord) -> String {
+    match c.key.trim() {
+        "" => "?".into(),
+        k => k.to_uppercase(),
+    }
+}
+
+/// `Ctrl+Alt+T`.
+pub fn long(c: &KeyChord) -> String {
+    format!("{}{}", modifiers(c), key(c))
+}
+
+/// Chords with the same modifiers share them: `Ctrl+Alt+T,D,O`. Runs with
+/// different modifiers are separated by " / ".
+pub fn compact(chords: &[&KeyChord]) -> String {
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    for c in chords {
+        let mods = modifiers(c);
+        match groups.last_mut() {
+            Some((m, keys)) if *m == mods => keys.push(key(c)),
+            _ => groups.push((mods, vec![key(c)])),
+        }
+    }
+    groups
+        .iter()
+        .map(|(mods, keys)| format!("{mods}{}", keys.join(",")))
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// The overlay's hint: the chords for editing, toggling, snapping and
+/// switching profiles, e.g. `(Ctrl+Alt+T,D,O,L,P)`.
+pub fn overlay_hint(hk: &Hotkeys) -> String {
+    let chords = [
+        &hk.edit_title,
+        &hk.edit_description,
+        &hk.toggle_overlay,
+        &hk.snap_position,
+        &hk.cycle_profile,
+    ];
+    format!("({})", compact(&chords))
+}
+
+/// One line per hotkey, chord first: `Ctrl+Alt+T  Edit Title`.
+pub fn help_lines(hk: &Hotkeys) -> Vec<String> {
+    bindings(hk)
+        .iter()
+        .map(|(action, c)| format!("{}  {action}", long(c)))
+        .collect()
+}
+
+/// Tray tooltip: the app name and the hotkeys used most. Windows shows at
+/// most 127 characters.
+pub fn tooltip(app_name: &str, hk: &Hotkeys) -> String {
+    let mut tip = app_name.to_string();
+    for (action, c) in &bindings(hk)[..3] {
+        tip.push_str(&format!("\n{}  {action}", long(c)));
+    }
+    tip
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(ctrl: bool, alt: bool, shift: bool, key: &str) -> KeyChord {
+        KeyChord {
+            ctrl,
+            alt,
+            shift,
+            key: key.into(),
+            extra: Default::default(),
+        }
+    }
+
+    #[test]
+    fn long_form_lists_modifiers_in_order() {
+        assert_eq!(long(&chord(true, true, false, "t")), "Ctrl+Alt+T");
+        assert_eq!(long(&chord(false, true, true, " 7 ")), "Alt+Shift+7");
+        assert_eq!(long(&chord(false, false, false, "")), "?");
+    }
+
+    #[test]
+    fn compact_form_shares_modifiers() {
+        let a = chord(true, true, false, "T");
+        let b = chord(true, true, false, "D");
+        let c = chord(true, false, true, "O");
+        assert_eq!(compact(&[&a, &b]), "Ctrl+Alt+T,D");
+        assert_eq!(
+            compact(&[&a, &c, &b]),
+            "Ctrl+Alt+T / Ctrl+Shift+O / Ctrl+Alt+D"
+        );
+        assert_eq!(compact(&[]), "");
+    }
+
+    #[test]
+    fn hints_follow_the_configuration() {
+        let mut hk = Hotkeys::default();
+        assert_eq!(overlay_hint(&hk), "(Ctrl+Alt+T,D,O,L,P)");
+        hk.edit_title.key = "E".into();
+        hk.toggle_overlay.shift = true;
+        assert_eq!(
+            overlay_hint(&hk),
+            "(Ctrl+Alt+E,D / Ctrl+Alt+Shift+O / Ctrl+Alt+L,P)"
+        );
+        assert_eq!(help_lines(&hk)[0], "Ctrl+Alt+E  Edit Title");
+        assert_eq!(help_lines(&hk).len(), 7);
+        let tip = tooltip("Desktop Labeler", &hk);
+        assert!(tip.starts_with("Desktop Labeler\nCtrl+Alt+E  Edit Title\n"));
+        assert!(tip.chars().count() <= 127);
+    }
+}
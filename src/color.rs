@@ -0,0 +1,255 @@
+//! Colors written as `#rgb`, `#rrggbb` or a name like `orange`; `Rgba` also
+//! takes `#rgba`, `#rrggbbaa` and `transparent`. Also the overlay's theme
+//! presets.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// An `Rgb` with alpha, 0 being fully transparent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("expected a color like \"#f80\", \"#ff8800cc\" or \"orange\", got {0:?}")]
+pub struct BadColor(pub String);
+
+/// Color names, as in CSS.
+const NAMED: [(&str, u32); 17] = [
+    ("black", 0x000000),
+    ("white", 0xffffff),
+    ("red", 0xff0000),
+    ("green", 0x008000),
+    ("lime", 0x00ff00),
+    ("blue", 0x0000ff),
+    ("yellow", 0xffff00),
+    ("cyan", 0x00ffff),
+    ("magenta", 0xff00ff),
+    ("orange", 0xffa500),
+    ("gray", 0x808080),
+    ("grey", 0x808080),
+    ("silver", 0xc0c0c0),
+    ("maroon", 0x800000),
+    ("navy", 0x000080),
+    ("teal", 0x008080),
+    ("purple", 0x800080),
+];
+
+impl Rgb {
+    pub const WHITE: Rgb = Rgb {
+        r: 255,
+        g: 255,
+        b: 255,
+    };
+
+    /// `0xRRGGBB`.
+    pub const fn from_u32(v: u32) -> Rgb {
+        Rgb {
+            r: (v >> 16) as u8,
+            g: (v >> 8) as u8,
+            b: v as u8,
+        }
+    }
+
+    pub const fn with_alpha(self, a: u8) -> Rgba {
+        Rgba {
+            r: self.r,
+            g: self.g,
+            b: self.b,
+            a,
+        }
+    }
+
+    /// Channels scaled to 0.0..=1.0, as Direct2D wants them.
+    pub fn to_f32(self) -> [f32; 3] {
+        [self.r, self.g, self.b].map(|c| f32::from(c) / 255.0)
+    }
+
+    /// As a GDI `COLORREF` value (0x00BBGGRR).
+    pub fn to_colorref(self) -> u32 {
+        u32::from(self.r) | u32::from(self.g) << 8 | u32::from(self.b) << 16
+    }
+}
+
+impl Rgba {
+    /// `0xRRGGBBAA`.
+    pub const fn from_u32(v: u32) -> Rgba {
+        Rgb::from_u32(v >> 8).with_alpha(v as u8)
+    }
+
+    pub fn rgb(self) -> Rgb {
+        Rgb {
+            r: self.r,
+            g: self.g,
+            b: self.b,
+        }
+    }
+
+    /// Channels scaled to 0.0..=1.0, alpha last.
+    pub fn to_f32(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a].map(|c| f32::from(c) / 255.0)
+    }
+}
+
+/// Parse any color form; alpha only when `alpha` is set.
+fn parse(s: &str, alpha: bool) -> Option<Rgba> {
+    let s = s.trim();
+    if alpha && s.eq_ignore_ascii_case("transparent") {
+        return Some(Rgba::from_u32(0));
+    }
+    if let Some((_, v)) = NAMED.iter().find(|(name, _)| s.eq_ignore_ascii_case(name)) {
+        return Some(Rgb::from_u32(*v).with_alpha(255));
+    }
+    let hex = s.strip_prefix('#')?;
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let (len, channels) = match (hex.len(), alpha) {
+        (3, _) => (1, 3),
+        (6, _) => (2, 3),
+        (4, true) => (1, 4),
+        (8, true) => (2, 4),
+        _ => return None,
+    };
+    let channel = |i: usize| {
+        let v = u8::from_str_radix(&hex[i * len..(i + 1) * len], 16).expect("hex digits");
+        if len == 1 { v * 17 } else { v }
+    };
+    let a = if channels == 4 { channel(3) } else { 255 };
+    Some(Rgba {
+        r: channel(0),
+        g: channel(1),
+        b: channel(2),
+        a,
+    })
+}
+
+impl FromStr for Rgb {
+    type Err = BadColor;
+
+    fn from_str(s: &str) -> Result<Self, BadColor> {
+        parse(s, false)
+            .map(Rgba::rgb)
+            .ok_or_else(|| BadColor(s.to_string()))
+    }
+}
+
+impl FromStr for Rgba {
+    type Err = BadColor;
+
+    fn from_str(s: &str) -> Result<Self, BadColor> {
+        parse(s, true).ok_or_else(|| BadColor(s.to_string()))
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl fmt::Display for Rgba {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{:02x}", self.rgb(), self.a)
+    }
+}
+
+/// Named color sets for the overlay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemePreset {
+    /// White text on translucent black.
+    #[default]
+    Dark,
+    /// Dark text on translucent white.
+    Light,
+    /// Solarized dark.
+    Solarized,
+    /// Yellow on opaque black with a thick border.
+    HighVisibility,
+}
+
+/// The overlay's colors and border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub foreground: Rgba,
+    /// Its alpha is the opacity of the background alone.
+    pub background: Rgba,
+    pub border: Rgba,
+    pub border_px: u32,
+}
+
+impl ThemePreset {
+    pub fn theme(self) -> Theme {
+        let (foreground, background, border, border_px) = match self {
+            ThemePreset::Dark => (0xffffffff, 0x00000059, 0x00000000, 0),
+            ThemePreset::Light => (0x1e1e1eff, 0xffffffd9, 0x00000033, 1),
+            ThemePreset::Solarized => (0x93a1a1ff, 0x002b36e6, 0x586e75ff, 1),
+            ThemePreset::HighVisibility => (0xffff00ff, 0x000000ff, 0xffff00ff, 2),
+        };
+        Theme {
+            foreground: Rgba::from_u32(foreground),
+            background: Rgba::from_u32(background),
+            border: Rgba::from_u32(border),
+            border_px,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_short_and_long_forms() {
+        let orange = Rgb {
+            r: 0xff,
+            g: 0x88,
+            b: 0x00,
+        };
+        assert_eq!("#f80".parse(), Ok(orange));
+        assert_eq!(" #FF8800 ".parse(), Ok(orange));
+        assert_eq!(orange.to_string(), "#ff8800");
+        for bad in ["f80", "#ff88", "#gg8800", "#", "#ff88000", "#ff8800cc"] {
+            assert!(bad.parse::<Rgb>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn parses_alpha_and_names() {
+        assert_eq!("#ff880080".parse(), Ok(Rgba::from_u32(0xff880080)));
+        assert_eq!("#f808".parse(), Ok(Rgba::from_u32(0xff880088)));
+        assert_eq!("#f80".parse(), Ok(Rgba::from_u32(0xff8800ff)));
+        assert_eq!("Orange".parse(), Ok(Rgba::from_u32(0xffa500ff)));
+        assert_eq!("transparent".parse(), Ok(Rgba::from_u32(0)));
+        assert_eq!("navy".parse(), Ok(Rgb::from_u32(0x000080)));
+        assert!("transparent".parse::<Rgb>().is_err());
+        assert!("chartreuse-ish".parse::<Rgba>().is_err());
+        assert_eq!(Rgba::from_u32(0x11223344).to_string(), "#11223344");
+        assert_eq!(
+            "#ff88008".parse::<Rgba>().unwrap_err().to_string(),
+            r##"expected a color like "#f80", "#ff8800cc" or "orange", got "#ff88008""##
+        );
+    }
+
+    #[test]
+    fn converts_for_rendering() {
+        let c: Rgb = "#ff8000".parse().unwrap();
+        assert_eq!(c.to_colorref(), 0x0000_80ff);
+        assert_eq!(Rgb::WHITE.to_f32(), [1.0, 1.0, 1.0]);
+    }
+}
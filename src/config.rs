@@ -1,47 +1,301 @@
+use crate::color::{Rgba, Theme};
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, io::Write, path::PathBuf};
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+pub mod archive;
+pub mod backup;
+pub mod desktop_id;
+pub mod exchange;
+pub mod format;
+pub mod history;
+pub mod identity;
+pub mod jsonc;
+pub mod layers;
+pub mod location;
+pub mod migrate;
+pub mod profile;
+pub mod schema;
+pub mod source;
+pub mod validate;
+
+pub use crate::bidi::{TextAlign, TextDirection};
+pub use crate::color::ThemePreset;
+pub use crate::core::Ellipsis;
+pub use archive::Archive;
+pub use desktop_id::DesktopId;
+pub use format::Format;
+pub use identity::{DesktopIdentity, MatchPolicy};
+pub use layers::{Layer, LayerSources, Layers};
+pub use profile::{AppearanceOverrides, Profile};
+pub use validate::{ConfigError, validate};
+
+/// Contents of `labels.json` or `labels.toml`. Settings left out of the
+/// file take their defaults (see `layers`).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(default)]
 pub struct Config {
-    pub desktops: HashMap<String, DesktopLabel>,
+    /// Editor hint pointing at the JSON Schema for this file.
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// Labels per virtual desktop, keyed by desktop GUID. These are the
+    /// default profile's labels.
+    #[schemars(schema_with = "schema::desktops")]
+    pub desktops: HashMap<DesktopId, DesktopLabel>,
+    /// Named alternative label sets, see `profile`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// The profile in use; unset for the top-level `desktops`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+    /// Labels of deleted desktops, see `archive`. Maintained by the app.
+    #[serde(default, skip_serializing_if = "Archive::is_empty")]
+    pub archived: Archive,
+    /// Position and name of each labelled desktop, used to recognize it
+    /// after its GUID changes. Maintained by the app.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[schemars(schema_with = "schema::identities")]
+    pub identities: HashMap<DesktopId, DesktopIdentity>,
+    /// What to do when a new desktop looks like a labelled one whose GUID
+    /// changed: `off`, `offer` (ask first) or `auto`.
+    #[serde(default)]
+    pub desktop_matching: MatchPolicy,
+    /// Language of the menus, dialogs and messages, e.g. `de` or `fr-CA`;
+    /// unset or `auto` follows the Windows display language. Languages
+    /// without a translation fall back to English.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
     pub hotkeys: Hotkeys,
     pub appearance: Appearance,
+    /// Format version; maintained by the app.
     #[serde(default)]
     pub version: Option<u32>,
+    /// Fields this build does not know about, kept so they survive a save.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(default)]
 pub struct DesktopLabel {
+    /// First line of the overlay.
     pub title: String,
+    /// Shown after the title.
     pub description: String,
+    /// Overlay line for this desktop, replacing `appearance.line_template`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Local date and time for `{countdown}`, e.g. `2024-05-31 18:00`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<String>,
+    /// Appearance while this desktop is shown, over the profile's.
+    #[serde(default, skip_serializing_if = "AppearanceOverrides::is_empty")]
+    pub appearance: AppearanceOverrides,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A label with only a title, for tests.
+#[cfg(test)]
+pub(crate) fn label(title: &str) -> DesktopLabel {
+    DesktopLabel {
+        title: title.into(),
+        ..Default::default()
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(default)]
 pub struct Hotkeys {
     pub edit_title: KeyChord,
     pub edit_description: KeyChord,
     pub toggle_overlay: KeyChord,
+    /// Cycles the overlay between 1/4, 1/2 and 3/4 of the screen width.
     #[serde(default = "default_snap_key")]
     pub snap_position: KeyChord,
+    /// Switches to the next label profile.
+    #[serde(default = "default_profile_key")]
+    pub cycle_profile: KeyChord,
+    /// Restores the current desktop's label from before the last edit.
+    #[serde(default = "default_undo_key")]
+    pub undo_label: KeyChord,
+    /// Reapplies the label the last undo replaced.
+    #[serde(default = "default_redo_key")]
+    pub redo_label: KeyChord,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct KeyChord {
     pub ctrl: bool,
     pub alt: bool,
     pub shift: bool,
+    /// A single letter or digit.
+    #[schemars(regex(pattern = r"^\s*[A-Za-z0-9]\s*$"))]
     pub key: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(default)]
 pub struct Appearance {
     pub font_family: String,
+    /// Font size in device-independent pixels.
+    #[schemars(range(min = 1))]
     pub font_size_dip: u32,
+    /// Distance from the top of the screen in pixels.
+    #[schemars(range(min = 0))]
     pub margin_px: i32,
+    /// Hide the overlay while a fullscreen window covers the primary monitor.
     #[serde(default)]
     pub hide_on_fullscreen: bool,
+    /// The overlay line; see the README for placeholders and sections.
+    #[serde(default = "default_line_template")]
+    pub line_template: String,
+    #[serde(default)]
+    pub layout: OverlayLayout,
+    /// Two-line layout: description size as a percentage of `font_size_dip`.
+    #[serde(default = "default_description_size")]
+    #[schemars(range(min = 1, max = 100))]
+    pub description_size_percent: u32,
+    /// Two-line layout: description opacity in percent.
+    #[serde(default = "default_description_opacity")]
+    #[schemars(range(min = 0, max = 100))]
+    pub description_opacity_percent: u32,
+    /// Widest the overlay may get, in pixels; 0 for the width of the screen.
+    /// Longer text is cut with an ellipsis.
+    #[serde(default)]
+    pub max_width_px: u32,
+    /// Most characters shown per line of text, counting an emoji or an
+    /// accented letter as one; 0 for no limit.
+    #[serde(default)]
+    pub max_chars: u32,
+    /// Where text cut for `max_width_px` or `max_chars` loses characters.
+    #[serde(default)]
+    pub ellipsis: Ellipsis,
+    /// Two-line layout: lines each for the title and the description before
+    /// the rest is cut off.
+    #[serde(default = "default_max_lines")]
+    #[schemars(range(min = 1, max = 100))]
+    pub max_lines: u32,
+    /// Colors the overlay starts from; the fields below override parts of it.
+    #[serde(default)]
+    pub theme: ThemePreset,
+    /// Text color: `#rgb`, `#rrggbb`, either with an alpha digit or pair, or
+    /// a name like `orange`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<String>,
+    /// Background color; its alpha is the background's opacity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_color: Option<String>,
+    /// Shorthand for a colored border: the border color, with a 3px border
+    /// unless `border_px` is set. `border_color` wins over it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0, max = 20))]
+    pub border_px: Option<u32>,
+    /// Opacity of the whole overlay, text included, in percent.
+    #[serde(default = "default_opacity")]
+    #[schemars(range(min = 0, max = 100))]
+    pub opacity_percent: u32,
+    /// Unset for a radius that follows the font size.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0, max = 100))]
+    pub corner_radius_px: Option<u32>,
+    /// Space between the text and the overlay's edge; unset to use
+    /// `margin_px`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0, max = 200))]
+    pub padding_px: Option<u32>,
+    /// Where the overlay sits; unset to follow the snap hotkey.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<OverlayPosition>,
+    /// Show the hotkeys, as configured in `hotkeys`, after the text.
+    #[serde(default = "default_true")]
+    pub show_hints: bool,
+    /// Reading direction; `auto` reads right-to-left when the label starts
+    /// with Hebrew or Arabic.
+    #[serde(default)]
+    pub direction: TextDirection,
+    /// Where lines narrower than the overlay go: `start`, `center` or `end`
+    /// of the reading direction.
+    #[serde(default)]
+    pub align: TextAlign,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Appearance {
+    /// The theme preset with the color and border fields applied. Colors that
+    /// do not parse keep the preset's.
+    pub fn theme_colors(&self) -> Theme {
+        let mut theme = self.theme.theme();
+        let color = |v: &Option<String>| v.as_deref().and_then(|s| s.parse::<Rgba>().ok());
+        if let Some(c) = color(&self.accent) {
+            theme.border = c;
+            theme.border_px = 3;
+        }
+        if let Some(c) = color(&self.foreground) {
+            theme.foreground = c;
+        }
+        if let Some(c) = color(&self.background) {
+            theme.background = c;
+        }
+        if let Some(c) = color(&self.border_color) {
+            theme.border = c;
+        }
+        if let Some(px) = self.border_px {
+            theme.border_px = px;
+        }
+        theme
+    }
+}
+
+/// Horizontal position of the overlay at the top of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayPosition {
+    /// Centred on the first quarter of the screen.
+    Left,
+    Center,
+    /// Centred on the last quarter of the screen.
+    Right,
+}
+
+impl OverlayPosition {
+    /// Where the overlay's centre goes, as a fraction of the screen width.
+    pub fn anchor_ratio(self) -> f32 {
+        match self {
+            OverlayPosition::Left => 0.25,
+            OverlayPosition::Center => 0.5,
+            OverlayPosition::Right => 0.75,
+        }
+    }
+}
+
+/// How the overlay arranges title and description.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayLayout {
+    /// One line rendered from `line_template`.
+    #[default]
+    SingleLine,
+    /// The template's output on top, the description below in a smaller,
+    /// fainter style.
+    TwoLines,
 }
 
 #[derive(Debug, Clone)]
@@ -54,58 +308,162 @@ pub struct Paths {
 impl Default for Config {
     fn default() -> Self {
         Self {
+            schema: None,
             desktops: HashMap::new(),
-            hotkeys: Hotkeys {
-                edit_title: KeyChord {
-                    ctrl: true,
-                    alt: true,
-                    shift: false,
-                    key: "T".into(),
-                },
-                edit_description: KeyChord {
-                    ctrl: true,
-                    alt: true,
-                    shift: false,
-                    key: "D".into(),
-                },
-                toggle_overlay: KeyChord {
-                    ctrl: true,
-                    alt: true,
-                    shift: false,
-                    key: "O".into(),
-                },
-                snap_position: KeyChord {
-                    ctrl: true,
-                    alt: true,
-                    shift: false,
-                    key: "L".into(),
-                },
+            profiles: BTreeMap::new(),
+            active_profile: None,
+            archived: Archive::default(),
+            identities: HashMap::new(),
+            desktop_matching: MatchPolicy::default(),
+            language: None,
+            hotkeys: Hotkeys::default(),
+            appearance: Appearance::default(),
+            version: None,
+            extra: Map::new(),
+        }
+    }
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        Self {
+            edit_title: KeyChord {
+                ctrl: true,
+                alt: true,
+                shift: false,
+                key: "T".into(),
+                extra: Map::new(),
             },
-            appearance: Appearance {
-                font_family: "Segoe UI".into(),
-                font_size_dip: 16,
-                margin_px: 8,
-                hide_on_fullscreen: false,
+            edit_description: KeyChord {
+                ctrl: true,
+                alt: true,
+                shift: false,
+                key: "D".into(),
+                extra: Map::new(),
             },
-            version: None,
+            toggle_overlay: KeyChord {
+                ctrl: true,
+                alt: true,
+                shift: false,
+                key: "O".into(),
+                extra: Map::new(),
+            },
+            snap_position: default_snap_key(),
+            cycle_profile: default_profile_key(),
+            undo_label: default_undo_key(),
+            redo_label: default_redo_key(),
+            extra: Map::new(),
         }
     }
 }
 
+impl Default for Appearance {
+    fn default() -> Self {
+        Self {
+            font_family: "Segoe UI".into(),
+            font_size_dip: 16,
+            margin_px: 8,
+            hide_on_fullscreen: false,
+            line_template: default_line_template(),
+            layout: OverlayLayout::default(),
+            description_size_percent: default_description_size(),
+            description_opacity_percent: default_description_opacity(),
+            max_width_px: 0,
+            max_chars: 0,
+            ellipsis: Ellipsis::default(),
+            max_lines: default_max_lines(),
+            theme: ThemePreset::default(),
+            foreground: None,
+            background: None,
+            border_color: None,
+            accent: None,
+            border_px: None,
+            opacity_percent: default_opacity(),
+            corner_radius_px: None,
+            padding_px: None,
+            position: None,
+            show_hints: true,
+            direction: TextDirection::default(),
+            align: TextAlign::default(),
+            extra: Map::new(),
+        }
+    }
+}
+
+fn default_line_template() -> String {
+    crate::template::DEFAULT_TEMPLATE.into()
+}
+
+fn default_description_size() -> u32 {
+    75
+}
+
+fn default_description_opacity() -> u32 {
+    70
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_opacity() -> u32 {
+    100
+}
+
+fn default_max_lines() -> u32 {
+    2
+}
+
 fn default_snap_key() -> KeyChord {
     KeyChord {
         ctrl: true,
         alt: true,
         shift: false,
         key: "L".into(),
+        extra: Map::new(),
+    }
+}
+
+fn default_profile_key() -> KeyChord {
+    KeyChord {
+        ctrl: true,
+        alt: true,
+        shift: false,
+        key: "P".into(),
+        extra: Map::new(),
+    }
+}
+
+fn default_undo_key() -> KeyChord {
+    KeyChord {
+        ctrl: true,
+        alt: true,
+        shift: false,
+        key: "Z".into(),
+        extra: Map::new(),
+    }
+}
+
+fn default_redo_key() -> KeyChord {
+    KeyChord {
+        ctrl: true,
+        alt: true,
+        shift: false,
+        key: "Y".into(),
+        extra: Map::new(),
     }
 }
 
+/// Config and log locations for this process; see `location` for the
+/// override chain.
 pub fn project_paths() -> Result<Paths> {
+    if let Some((_, paths)) = location::resolve_current() {
+        return Ok(paths);
+    }
     let dirs = ProjectDirs::from("com", "Acme", "DesktopLabeler")
         .context("Failed to determine project directories")?;
     let cfg_dir = dirs.config_dir().to_path_buf();
-    let cfg_file = cfg_dir.join("labels.json");
+    let cfg_file = format::config_file(&cfg_dir);
     let log_dir = dirs.data_local_dir().join("logs");
     Ok(Paths {
         cfg_file,
@@ -115,6 +473,13 @@ pub fn project_paths() -> Result<Paths> {
 }
 
 pub fn load_or_default() -> Result<(Config, Paths)> {
+    let (cfg, paths, _) = load_with_problems()?;
+    Ok((cfg, paths))
+}
+
+/// Like `load_or_default`, but also returns the problems found in the file
+/// so the caller can surface them to the user.
+pub fn load_with_problems() -> Result<(Config, Paths, Vec<ConfigError>)> {
     let paths = project_paths()?;
     if let Err(e) = fs::create_dir_all(&paths.cfg_dir) {
         tracing::warn!("Failed to create config directory: {}", e);
@@ -122,64 +487,205 @@ pub fn load_or_default() -> Result<(Config, Paths)> {
     if let Err(e) = fs::create_dir_all(&paths.log_dir) {
         tracing::warn!("Failed to create log directory: {}", e);
     }
-    let mut cfg = match fs::read_to_string(&paths.cfg_file) {
-        Ok(s) => match serde_json::from_str(&s) {
-            Ok(cfg) => cfg,
-            Err(e) => {
-                tracing::warn!("Failed to parse config JSON: {}, using defaults", e);
-                Config::default()
-            }
+    let (cfg, problems) = load_from(&paths);
+    Ok((cfg, paths, problems))
+}
+
+/// Load the config at `paths` layered with this machine's defaults file and
+/// environment overrides (see `layers`), running pending migrations.
+pub fn load_from(paths: &Paths) -> (Config, Vec<ConfigError>) {
+    load_layered(paths, &LayerSources::current().read())
+}
+
+/// Load the user config at `paths` between the given layers.
+///
+/// The file is only rewritten when it did not exist yet or a migration
+/// actually changed the document, and never while it has problems. A file
+/// that cannot be read as a `Config` yields the newest valid backup (or
+/// defaults); invalid values are reset to their defaults. Either way the
+/// problems are returned.
+pub fn load_layered(paths: &Paths, layers: &Layers) -> (Config, Vec<ConfigError>) {
+    let format = Format::of(&paths.cfg_file);
+    let text = match read_config_text(&paths.cfg_file) {
+        Ok(text) => text,
+        Err(e) => return (recover_unreadable(paths, &e), vec![e]),
+    };
+    let (mut doc, source, mut needs_save) = match text {
+        Some(s) => match format.parse(&s) {
+            Ok((doc, plain)) => (doc, Some(plain), false),
+            Err(e) => return (recover_unreadable(paths, &e), vec![e]),
         },
-        Err(_) => {
+        None => match read_legacy_document() {
             // Migrate from old app name if present
-            if let Some(old_dirs) = ProjectDirs::from("com", "Acme", "DesktopOverlay") {
-                let old_file = old_dirs.config_dir().join("labels.json");
-                if let Ok(s) = fs::read_to_string(&old_file) {
-                    match serde_json::from_str(&s) {
-                        Ok(parsed) => {
-                            // Save to new location
-                            let _ = save_atomic(&parsed, &paths);
-                            parsed
-                        }
-                        Err(e) => {
-                            tracing::warn!(
-                                "Failed to parse migration config JSON: {}, using defaults",
-                                e
-                            );
-                            Config::default()
-                        }
-                    }
-                } else {
-                    Config::default()
+            Some(doc) => (doc, None, true),
+            None => (Value::Object(Map::new()), None, true),
+        },
+    };
+
+    needs_save |= migrate::migrate(&mut doc);
+
+    // A machine default or environment value that does not fit its setting
+    // is dropped on its own; only a failure of the user's file falls back.
+    let mut layers = layers.clone();
+    let mut problems = Vec::new();
+    let mut cfg = loop {
+        let (effective, _) = layers.apply(Some(&doc));
+        match from_document(effective, source.as_deref()) {
+            Ok(cfg) => break cfg,
+            Err(e) => match layers.drop_value(e.path(), &doc) {
+                Some(layer) => {
+                    let message = match &e {
+                        ConfigError::Type { message, .. } => message.clone(),
+                        other => other.to_string(),
+                    };
+                    problems.push(ConfigError::Override {
+                        layer,
+                        path: e.path().to_string(),
+                        message,
+                    });
                 }
-            } else {
-                Config::default()
-            }
+                None => return (recover_unreadable(paths, &e), vec![e]),
+            },
         }
     };
+    problems.extend(validate(&cfg, source.as_deref()));
+    if problems.is_empty() {
+        if needs_save && let Err(e) = save_layered(&cfg, paths, &layers) {
+            tracing::warn!(error=%e, "Failed to save migrated config");
+        }
+    } else {
+        for p in &problems {
+            tracing::warn!(problem=%p, "Invalid config value, using default");
+        }
+        validate::sanitize(&mut cfg);
+    }
+    (cfg, problems)
+}
+
+/// The text of the config file, or `None` if there is no file. Any other
+/// failure to read it is an error, so a locked or non-UTF-8 file is never
+/// mistaken for a missing one and replaced.
+fn read_config_text(path: &Path) -> std::result::Result<Option<String>, ConfigError> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ConfigError::Unreadable {
+            message: e.to_string(),
+        }),
+    }
+}
 
-    // Migration: Change snap_position hotkey from "S" to "L" for version 0 or None
-    if cfg.version.is_none() || cfg.version == Some(0) {
-        if cfg.hotkeys.snap_position.key.eq_ignore_ascii_case("S") {
-            cfg.hotkeys.snap_position.key = "L".into();
+/// Fall back to the newest usable backup, or defaults if there is none.
+fn recover_unreadable(paths: &Paths, e: &ConfigError) -> Config {
+    match backup::newest_valid_backup(paths) {
+        Some((path, cfg)) => {
+            tracing::warn!(error=%e, backup=?path, "Failed to read config, using newest backup");
+            cfg
+        }
+        None => {
+            tracing::warn!(error=%e, "Failed to read config, using defaults");
+            Config::default()
         }
-        cfg.version = Some(1);
-        let _ = save_atomic(&cfg, &paths);
     }
+}
 
-    Ok((cfg, paths))
+fn parse_document(s: &str) -> std::result::Result<Value, ConfigError> {
+    Format::Json.parse(s).map(|(doc, _)| doc)
+}
+
+fn from_document(doc: Value, source: Option<&str>) -> std::result::Result<Config, ConfigError> {
+    serde_path_to_error::deserialize(doc).map_err(|e| ConfigError::from_typed(e, source))
+}
+
+/// Parse, migrate and deserialize JSON config text without touching the
+/// disk. Settings the text leaves out take their built-in defaults.
+pub fn parse_config(s: &str) -> std::result::Result<Config, ConfigError> {
+    parse_config_as(Format::Json, s)
+}
+
+/// `parse_config` for text in the given format.
+pub fn parse_config_as(format: Format, s: &str) -> std::result::Result<Config, ConfigError> {
+    let (mut doc, plain) = format.parse(s)?;
+    migrate::migrate(&mut doc);
+    let (doc, _) = Layers::default().apply(Some(&doc));
+    from_document(doc, Some(&plain))
 }
 
+fn read_legacy_document() -> Option<Value> {
+    // Only the per-user location inherits the old app's labels; a portable or
+    // explicitly chosen config starts fresh.
+    if location::resolve_current().is_some() {
+        return None;
+    }
+    let old_dirs = ProjectDirs::from("com", "Acme", "DesktopOverlay")?;
+    let s = fs::read_to_string(old_dirs.config_dir().join("labels.json")).ok()?;
+    match parse_document(&s) {
+        Ok(doc) => Some(doc),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to parse migration config JSON: {}, using defaults",
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Write `cfg` to `paths.cfg_file`, in that file's format, via a temp file
+/// and rename, keeping a rotating backup of the previous file (see
+/// `backup`). Values that come from the machine defaults or environment
+/// overrides are not written, and comments in the existing file are kept.
+///
+/// Refuses to replace an existing file that cannot be read as a `Config`, so
+/// a hand-edited file with a typo is never overwritten with defaults.
 pub fn save_atomic(cfg: &Config, paths: &Paths) -> Result<()> {
+    save_layered(cfg, paths, &LayerSources::current().read())
+}
+
+/// `save_atomic` relative to the given layers.
+pub fn save_layered(cfg: &Config, paths: &Paths, layers: &Layers) -> Result<()> {
+    let format = Format::of(&paths.cfg_file);
+    let existing_text = read_config_text(&paths.cfg_file)
+        .map_err(|e| anyhow::Error::new(e).context("refusing to overwrite unreadable config"))?;
+    let mut existing = None;
+    if let Some(text) = &existing_text {
+        if let Err(e) = parse_config_as(format, text) {
+            return Err(anyhow::Error::new(e).context("refusing to overwrite unreadable config"));
+        }
+        existing = format.parse(text).ok().map(|(mut doc, _)| {
+            migrate::migrate(&mut doc);
+            doc
+        });
+    }
     fs::create_dir_all(&paths.cfg_dir).ok();
-    let tmp = paths.cfg_file.with_extension("json.tmp");
-    let data = serde_json::to_vec_pretty(cfg)?;
+    if let Err(e) = backup::create_backup(paths) {
+        tracing::warn!(error=%e, "Failed to back up config before saving");
+    }
+    let mut doc = layers.user_document(serde_json::to_value(cfg)?, existing.as_ref());
+    if format == Format::Json {
+        if cfg.schema.is_none()
+            && let Some(obj) = doc.as_object_mut()
+        {
+            obj.insert("$schema".into(), Value::from(schema::SCHEMA_REF));
+        }
+        if let Err(e) = schema::write_schema(paths) {
+            tracing::warn!(error=%e, "Failed to write config schema");
+        }
+    }
+    let data = format.render(&doc, existing_text.as_deref())?;
+    write_atomic(&paths.cfg_file, data.as_bytes())
+}
+
+/// Write `data` to `path` via a temp file next to it and a rename.
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("cfg");
+    let tmp = path.with_extension(format!("{ext}.tmp"));
     {
         let mut f = fs::File::create(&tmp).context("create temp cfg")?;
-        f.write_all(&data).context("write temp cfg")?;
+        f.write_all(data).context("write temp cfg")?;
         f.sync_all().ok();
     }
     // Best-effort atomic replace.
-    fs::rename(&tmp, &paths.cfg_file).context("rename temp to final")?;
+    fs::rename(&tmp, path).context("rename temp to final")?;
     Ok(())
 }
@@ -0,0 +1,277 @@
+//! Labels of desktops that no longer exist.
+//!
+//! `Config::reconcile` compares the labelled desktops with the live desktop
+//! list. A label whose desktop is gone is first marked orphaned, so a
+//! desktop that only disappears briefly (Explorer restart, remote session)
+//! keeps its label. Once a desktop has been missing for the grace period, its
+//! labels move from `desktops` (and every profile) to `archived.labels`,
+//! where they can be re-attached to another desktop.
+
+use super::{Config, DesktopId, DesktopLabel, schema};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use thiserror::Error;
+
+/// How long a desktop may be missing before its labels are archived.
+pub const GRACE_PERIOD_SECS: u64 = 14 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Archive {
+    /// Labelled desktops that were missing from the desktop list, with the
+    /// time that was first noticed (seconds since the Unix epoch).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[schemars(schema_with = "schema::desktop_times")]
+    pub orphaned: HashMap<DesktopId, u64>,
+    /// Labels of desktops that stayed missing, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<ArchivedLabel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ArchivedLabel {
+    /// The desktop the label belonged to.
+    #[schemars(schema_with = "schema::guid")]
+    pub desktop_id: DesktopId,
+    /// The profile the label came from; unset for the top-level `desktops`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(flatten)]
+    pub label: DesktopLabel,
+    /// When the label was archived, in seconds since the Unix epoch.
+    pub archived_at: u64,
+}
+
+/// What a `reconcile` pass changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reconciled {
+    /// Desktops newly marked orphaned.
+    pub orphaned: usize,
+    /// Orphaned desktops that exist again.
+    pub restored: usize,
+    /// Labels moved to the archive.
+    pub archived: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ReattachError {
+    #[error("no archived label number {0}")]
+    NoSuchLabel(usize),
+    #[error("desktop {0} already has a label")]
+    Labelled(DesktopId),
+}
+
+impl ReattachError {
+    /// Key of the text shown for this error in the message catalogs (see
+    /// `i18n`).
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            Self::NoSuchLabel(_) => "reattach.no_such_label",
+            Self::Labelled(_) => "reattach.labelled",
+        }
+    }
+}
+
+impl Archive {
+    pub fn is_empty(&self) -> bool {
+        self.orphaned.is_empty() && self.labels.is_empty()
+    }
+}
+
+impl Reconciled {
+    pub fn changed(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+impl Config {
+    /// Every desktop that has a label in any profile.
+    pub(crate) fn labelled_desktops(&self) -> BTreeSet<DesktopId> {
+        let profiles = self.profiles.values().map(|p| &p.desktops);
+        std::iter::once(&self.desktops)
+            .chain(profiles)
+            .flat_map(|d| d.keys().copied())
+            .collect()
+    }
+
+    /// Whether `desktop` is labelled but missing from the desktop list.
+    pub fn is_orphaned(&self, desktop: DesktopId) -> bool {
+        self.archived.orphaned.contains_key(&desktop)
+    }
+
+    /// Mark labels of desktops missing from `live` as orphaned, unmark those
+    /// that are back, and archive those missing for longer than `grace`
+    /// seconds. An empty `live` list means the desktop API failed and
+    /// changes nothing.
+    pub fn reconcile(&mut self, live: &[DesktopId], now: u64, grace: u64) -> Reconciled {
+        let mut out = Reconciled::default();
+        if live.is_empty() {
+            return out;
+        }
+        let labelled = self.labelled_desktops();
+        let orphaned = &mut self.archived.orphaned;
+        orphaned.retain(|id, _| {
+            let back = live.contains(id);
+            out.restored += back as usize;
+            !back && labelled.contains(id)
+        });
+        for id in labelled.iter().filter(|id| !live.contains(id)) {
+            if !orphaned.contains_key(id) {
+                orphaned.insert(*id, now);
+                out.orphaned += 1;
+            }
+        }
+        let expired: Vec<DesktopId> = orphaned
+            .iter()
+            .filter(|(_, since)| now.saturating_sub(**since) >= grace)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in expired {
+            self.archived.orphaned.remove(&id);
+            out.archived += self.archive(id, now);
+        }
+        out
+    }
+
+    /// Move every label of `desktop` to the archive; returns how many moved.
+    fn archive(&mut self, desktop: DesktopId, now: u64) -> usize {
+        let profiles = self
+            .profiles
+            .iter_mut()
+            .map(|(name, p)| (Some(name.clone()), &mut p.desktops));
+        let mut moved = Vec::new();
+        for (profile, desktops) in std::iter::once((None, &mut self.desktops)).chain(profiles) {
+            if let Some(label) = desktops.remove(&desktop) {
+                moved.push(ArchivedLabel {
+                    desktop_id: desktop,
+                    profile,
+                    label,
+                    archived_at: now,
+                });
+            }
+        }
+        let n = moved.len();
+        self.archived.labels.extend(moved);
+        n
+    }
+
+    /// Give archived label `index` to `desktop` and remove it from the
+    /// archive. The label returns to its profile, or to the top-level labels
+    /// if that profile no longer exists.
+    pub fn reattach(
+        &mut self,
+        index: usize,
+        desktop: DesktopId,
+    ) -> Result<ArchivedLabel, ReattachError> {
+        let entry = self
+            .archived
+            .labels
+            .get(index)
+            .ok_or(ReattachError::NoSuchLabel(index))?;
+        let target = match entry
+            .profile
+            .as_ref()
+            .and_then(|p| self.profiles.get_mut(p))
+        {
+            Some(p) => &mut p.desktops,
+            None => &mut self.desktops,
+        };
+        if target.contains_key(&desktop) {
+            return Err(ReattachError::Labelled(desktop));
+        }
+        target.insert(desktop, entry.label.clone());
+        self.archived.orphaned.remove(&desktop);
+        Ok(self.archived.labels.remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::label;
+
+    const GONE: DesktopId = DesktopId::from_u128(1);
+    const LIVE: DesktopId = DesktopId::from_u128(2);
+    const NEW: DesktopId = DesktopId::from_u128(3);
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn config() -> Config {
+        let mut cfg = Config::default();
+        cfg.desktops.insert(GONE, label("Old"));
+        cfg.desktops.insert(LIVE, label("Mail"));
+        let mut home = super::super::Profile::default();
+        home.desktops.insert(GONE, label("Games"));
+        cfg.profiles.insert("Home".into(), home);
+        cfg
+    }
+
+    #[test]
+    fn missing_desktops_are_archived_after_the_grace_period() {
+        let mut cfg = config();
+        let r = cfg.reconcile(&[LIVE], 100, 7 * DAY);
+        assert_eq!(
+            r,
+            Reconciled {
+                orphaned: 1,
+                restored: 0,
+                archived: 0
+            }
+        );
+        assert!(cfg.is_orphaned(GONE) && !cfg.is_orphaned(LIVE));
+        assert!(!cfg.reconcile(&[LIVE], 100 + DAY, 7 * DAY).changed());
+        assert_eq!(cfg.archived.orphaned[&GONE], 100);
+
+        let r = cfg.reconcile(&[LIVE], 100 + 7 * DAY, 7 * DAY);
+        assert_eq!(r.archived, 2);
+        assert!(!cfg.desktops.contains_key(&GONE));
+        assert!(cfg.profiles["Home"].desktops.is_empty());
+        assert!(cfg.archived.orphaned.is_empty());
+        let archived: Vec<_> = cfg
+            .archived
+            .labels
+            .iter()
+            .map(|a| (a.profile.as_deref(), a.label.title.as_str()))
+            .collect();
+        assert_eq!(archived, vec![(None, "Old"), (Some("Home"), "Games")]);
+    }
+
+    #[test]
+    fn returning_desktops_keep_their_labels() {
+        let mut cfg = config();
+        cfg.reconcile(&[LIVE], 100, DAY);
+        let r = cfg.reconcile(&[LIVE, GONE], 100 + 2 * DAY, DAY);
+        assert_eq!(
+            r,
+            Reconciled {
+                orphaned: 0,
+                restored: 1,
+                archived: 0
+            }
+        );
+        assert!(cfg.archived.is_empty());
+        assert_eq!(cfg.desktops[&GONE].title, "Old");
+    }
+
+    #[test]
+    fn empty_desktop_list_changes_nothing() {
+        let mut cfg = config();
+        assert!(!cfg.reconcile(&[], 100, 0).changed());
+        assert!(cfg.archived.is_empty());
+    }
+
+    #[test]
+    fn reattach_restores_into_the_original_profile() {
+        let mut cfg = config();
+        cfg.reconcile(&[LIVE], 0, 0);
+        assert_eq!(cfg.reattach(1, NEW).unwrap().label.title, "Games");
+        assert_eq!(cfg.profiles["Home"].desktops[&NEW].title, "Games");
+        assert_eq!(cfg.reattach(0, LIVE), Err(ReattachError::Labelled(LIVE)));
+        assert_eq!(cfg.reattach(5, NEW), Err(ReattachError::NoSuchLabel(5)));
+
+        // Without its profile, a label goes to the top-level labels.
+        cfg.archived.labels[0].profile = Some("Gone".into());
+        cfg.reattach(0, NEW).unwrap();
+        assert_eq!(cfg.desktops[&NEW].title, "Old");
+        assert!(cfg.archived.is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+//! Rotating timestamped copies of the config file, taken before every save.
+//!
+//! Backups live in `<cfg_dir>/backups` and are named
+//! `labels-YYYYMMDD-HHMMSS-mmm.json` (UTC; `.toml` for a TOML config), so
+//! lexical order is chronological. The copy taken just before a restore gets
+//! a `-restore` mark, so restoring again goes further back instead of
+//! returning to it.
+
+use super::{Config, Format, Paths, parse_config_as, write_atomic};
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Number of backups kept; older ones are deleted after each new backup.
+pub const KEEP_BACKUPS: usize = 10;
+
+const PREFIX: &str = "labels-";
+/// Ends the file stem of a backup taken by `restore_backup`.
+const RESTORE_MARK: &str = "-restore";
+
+/// Backups of `paths.cfg_file` share its extension.
+fn suffix(paths: &Paths) -> String {
+    format!(".{}", Format::of(&paths.cfg_file).extension())
+}
+
+pub fn backup_dir(paths: &Paths) -> PathBuf {
+    paths.cfg_dir.join("backups")
+}
+
+/// Existing backups in the format of the current config file, newest first.
+pub fn list_backups(paths: &Paths) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(backup_dir(paths)) else {
+        return Vec::new();
+    };
+    let suffix = suffix(paths);
+    let mut found: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(PREFIX) && n.ends_with(&suffix))
+        })
+        .collect();
+    found.sort();
+    found.reverse();
+    found
+}
+
+/// Copy the current config file into the backup directory and prune old
+/// backups. Does nothing if there is no config file yet or it is identical to
+/// the newest backup. Returns the path of the new backup, if one was made.
+pub fn create_backup(paths: &Paths) -> Result<Option<PathBuf>> {
+    backup_marked(paths, "")
+}
+
+fn backup_marked(paths: &Paths, mark: &str) -> Result<Option<PathBuf>> {
+    let Ok(current) = fs::read(&paths.cfg_file) else {
+        return Ok(None);
+    };
+    if let Some(newest) = list_backups(paths).first()
+        && fs::read(newest).is_ok_and(|b| b == current)
+    {
+        return Ok(None);
+    }
+    let dir = backup_dir(paths);
+    fs::create_dir_all(&dir).context("create backup dir")?;
+    // Two saves within the same millisecond must not overwrite each other's
+    // backup, so step forward until the name is free.
+    let suffix = suffix(paths);
+    let mut t = SystemTime::now();
+    let mut target = dir.join(format!("{PREFIX}{}{mark}{suffix}", stamp(t)));
+    while target.exists() {
+        t += Duration::from_millis(1);
+        target = dir.join(format!("{PREFIX}{}{mark}{suffix}", stamp(t)));
+    }
+    fs::write(&target, &current).context("write backup")?;
+    prune(paths);
+    Ok(Some(target))
+}
+
+fn prune(paths: &Paths) {
+    for old in list_backups(paths).into_iter().skip(KEEP_BACKUPS) {
+        if let Err(e) = fs::remove_file(&old) {
+            tracing::warn!(path=?old, error=%e, "Failed to remove old config backup");
+        }
+    }
+}
+
+/// The newest backup that still parses as a `Config`.
+pub fn newest_valid_backup(paths: &Paths) -> Option<(PathBuf, Config)> {
+    list_backups(paths).into_iter().find_map(|p| {
+        let text = fs::read_to_string(&p).ok()?;
+        parse_config_as(Format::of(&p), &text)
+            .ok()
+            .map(|cfg| (p, cfg))
+    })
+}
+
+/// Replace the config file with `backup`. The current file, even if it is
+/// unreadable, is backed up first so the restore itself can be undone.
+pub fn restore_backup(paths: &Paths, backup: &Path) -> Result<Config> {
+    let text = fs::read_to_string(backup).context("read backup")?;
+    let cfg = parse_config_as(Format::of(backup), &text)
+        .with_context(|| format!("backup {} is invalid", backup.display()))?;
+    backup_marked(paths, RESTORE_MARK)?;
+    write_atomic(&paths.cfg_file, text.as_bytes())?;
+    tracing::info!(backup=?backup, "Restored config from backup");
+    Ok(cfg)
+}
+
+fn made_by_restore(backup: &Path) -> bool {
+    backup
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .is_some_and(|s| s.ends_with(RESTORE_MARK))
+}
+
+/// Restore the newest valid backup that differs from the current file and
+/// is older than it: when the current file is itself a restored backup, the
+/// search continues from there, so repeated restores walk back through the
+/// history. Backups taken by a restore are skipped. Returns the restored
+/// backup's path and config.
+pub fn restore_latest(paths: &Paths) -> Result<(PathBuf, Config)> {
+    let current = fs::read(&paths.cfg_file).ok();
+    let backups: Vec<PathBuf> = list_backups(paths)
+        .into_iter()
+        .filter(|p| !made_by_restore(p))
+        .collect();
+    let start = backups
+        .iter()
+        .position(|p| current.is_some() && fs::read(p).ok() == current)
+        .map_or(0, |i| i + 1);
+    let backup = backups
+        .into_iter()
+        .skip(start)
+        .find(|p| {
+            fs::read(p).is_ok_and(|b| {
+                Some(&b) != current.as_ref()
+                    && std::str::from_utf8(&b)
+                        .is_ok_and(|t| parse_config_as(Format::of(p), t).is_ok())
+            })
+        })
+        .ok_or_else(|| anyhow!("no usable backup found"))?;
+    let cfg = restore_backup(paths, &backup)?;
+    Ok((backup, cfg))
+}
+
+/// `YYYYMMDD-HHMMSS-mmm` in UTC.
+fn stamp(t: SystemTime) -> String {
+    let d = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = d.as_secs();
+    let (y, m, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{y:04}{m:02}{day:02}-{:02}{:02}{:02}-{:03}",
+        rem / 3600,
+        (rem / 60) % 60,
+        rem % 60,
+        d.subsec_millis()
+    )
+}
+
+/// Days since 1970-01-01 to (year, month, day) in the proleptic Gregorian calendar.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamp_formats_utc() {
+        assert_eq!(stamp(UNIX_EPOCH), "19700101-000000-000");
+        let t = UNIX_EPOCH + Duration::from_millis(1_760_704_212_345);
+        assert_eq!(stamp(t), "20251017-123012-345");
+    }
+
+    #[test]
+    fn civil_handles_leap_days() {
+        // 2024-02-29 is day 19782.
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(19_783), (2024, 3, 1));
+    }
+}
@@ -0,0 +1,142 @@
+//! Virtual desktop identifiers as stored in `labels.json`.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A virtual desktop GUID.
+///
+/// Parses GUIDs with or without braces in any case and always formats in the
+/// canonical form `D178F97B-2525-4ED7-B219-6BA2AA6BE296`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DesktopId(u128);
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid desktop id {0:?}; expected a GUID like D178F97B-2525-4ED7-B219-6BA2AA6BE296")]
+pub struct ParseDesktopIdError(pub String);
+
+impl DesktopId {
+    /// Used when the virtual desktop API is unavailable, so labels still have
+    /// a stable key.
+    pub const FALLBACK: DesktopId = DesktopId(0);
+
+    pub const fn from_u128(v: u128) -> Self {
+        Self(v)
+    }
+
+    pub const fn as_u128(self) -> u128 {
+        self.0
+    }
+}
+
+impl FromStr for DesktopId {
+    type Err = ParseDesktopIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseDesktopIdError(s.to_string());
+        let t = s.trim();
+        let t = match (t.strip_prefix('{'), t.strip_suffix('}')) {
+            (Some(_), Some(_)) => &t[1..t.len() - 1],
+            (None, None) => t,
+            _ => return Err(err()),
+        };
+        let groups: Vec<&str> = t.split('-').collect();
+        if groups.iter().map(|g| g.len()).collect::<Vec<_>>() != [8, 4, 4, 4, 12] {
+            return Err(err());
+        }
+        let hex: String = groups.concat();
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(err());
+        }
+        u128::from_str_radix(&hex, 16).map(Self).map_err(|_| err())
+    }
+}
+
+impl fmt::Display for DesktopId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = self.0;
+        write!(
+            f,
+            "{:08X}-{:04X}-{:04X}-{:04X}-{:012X}",
+            (v >> 96) as u32,
+            (v >> 80) as u16,
+            (v >> 64) as u16,
+            (v >> 48) as u16,
+            v & 0xFFFF_FFFF_FFFF
+        )
+    }
+}
+
+impl Serialize for DesktopId {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DesktopId {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CANONICAL: &str = "D178F97B-2525-4ED7-B219-6BA2AA6BE296";
+
+    #[test]
+    fn parses_canonical_braced_and_lowercase() {
+        let id: DesktopId = CANONICAL.parse().unwrap();
+        assert_eq!(id.to_string(), CANONICAL);
+        assert_eq!(
+            "{d178f97b-2525-4ed7-b219-6ba2aa6be296}"
+                .parse::<DesktopId>()
+                .unwrap(),
+            id
+        );
+        assert_eq!(
+            format!(" {{{CANONICAL}}} ").parse::<DesktopId>().unwrap(),
+            id
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_ids() {
+        for bad in [
+            "",
+            "default",
+            "{D178F97B-2525-4ED7-B219-6BA2AA6BE296",
+            "D178F97B25254ED7B2196BA2AA6BE296",
+            "D178F97B-2525-4ED7-B219-6BA2AA6BE29",
+            "G178F97B-2525-4ED7-B219-6BA2AA6BE296",
+            "+178F97B-2525-4ED7-B219-6BA2AA6BE296",
+            "Desktop(Guid(D178F97B-2525-4ED7-B219-6BA2AA6BE296))",
+        ] {
+            assert!(bad.parse::<DesktopId>().is_err(), "{bad:?} parsed");
+        }
+    }
+
+    #[test]
+    fn u128_layout_matches_guid_text() {
+        let id = DesktopId::from_u128(0xD178F97B_2525_4ED7_B219_6BA2AA6BE296);
+        assert_eq!(id.to_string(), CANONICAL);
+        assert_eq!(
+            DesktopId::FALLBACK.to_string(),
+            "00000000-0000-0000-0000-000000000000"
+        );
+    }
+
+    #[test]
+    fn serde_uses_canonical_string() {
+        let id: DesktopId =
+            serde_json::from_str("\"{d178f97b-2525-4ed7-b219-6ba2aa6be296}\"").unwrap();
+        assert_eq!(
+            serde_json::to_string(&id).unwrap(),
+            format!("\"{CANONICAL}\"")
+        );
+        assert!(serde_json::from_str::<DesktopId>("\"nope\"").is_err());
+    }
+}
@@ -0,0 +1,536 @@
+//! Import and export of desktop labels.
+//!
+//! Two formats: a CSV table (`desktop_id,index,title,description`) for
+//! spreadsheets, and a JSON bundle that names its format and version and can
+//! carry hotkeys and appearance as well. `index` is the desktop's position on
+//! the exporting machine (1 = the first desktop), which lets
+//! `MergeStrategy::ByIndex` map labels onto a machine whose desktop GUIDs
+//! differ. Labels are imported into the active profile; hotkeys and
+//! appearance are the top-level settings on both ends, so profile overrides
+//! are not exchanged.
+
+use super::{
+    Appearance, Config, DesktopId, DesktopLabel, Hotkeys, Paths, load_from, save_atomic, validate,
+};
+use crate::i18n::{tr, tr_with};
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Value of `format` in a bundle.
+pub const BUNDLE_FORMAT: &str = "mddsklbl-labels";
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// One exported label.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub desktop_id: DesktopId,
+    /// 1-based desktop position on the exporting machine, if known.
+    #[serde(default)]
+    pub index: Option<usize>,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    pub format: String,
+    pub version: u32,
+    pub desktops: Vec<Entry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hotkeys: Option<Hotkeys>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appearance: Option<Appearance>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// Imported labels replace existing ones for the same desktop.
+    #[default]
+    Overwrite,
+    /// Only desktops without a label are filled in; settings are not touched.
+    KeepExisting,
+    /// Labels go to the local desktop at the same position, whatever its GUID.
+    ByIndex,
+}
+
+/// An export or import requested on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Export {
+        path: PathBuf,
+        with_settings: bool,
+    },
+    Import {
+        path: PathBuf,
+        strategy: MergeStrategy,
+    },
+}
+
+/// What an import changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Labels not applied because the desktop already had one.
+    pub kept: usize,
+    /// `ByIndex` entries with no local desktop at their position.
+    pub unmatched: usize,
+    /// Whether hotkeys or appearance were imported.
+    pub settings: bool,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "overwrite" => Ok(Self::Overwrite),
+            "keep-existing" => Ok(Self::KeepExisting),
+            "by-index" => Ok(Self::ByIndex),
+            _ => bail!("unknown merge strategy {s:?}; use overwrite, keep-existing or by-index"),
+        }
+    }
+}
+
+impl fmt::Display for MergeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Overwrite => "overwrite",
+            Self::KeepExisting => "keep-existing",
+            Self::ByIndex => "by-index",
+        })
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&tr_with(
+            "exchange.report",
+            &[
+                ("added", &self.added.to_string()),
+                ("updated", &self.updated.to_string()),
+                ("unchanged", &self.unchanged.to_string()),
+                ("kept", &self.kept.to_string()),
+                ("unmatched", &self.unmatched.to_string()),
+            ],
+        ))?;
+        if self.settings {
+            f.write_str(tr("exchange.report_settings"))?;
+        }
+        Ok(())
+    }
+}
+
+/// The active profile's labels, ordered by desktop position (`order`, as
+/// reported by the OS) and then by GUID for desktops that no longer exist.
+pub fn bundle(cfg: &Config, order: &[DesktopId], with_settings: bool) -> Bundle {
+    let mut desktops: Vec<Entry> = cfg
+        .labels()
+        .iter()
+        .map(|(id, label)| Entry {
+            desktop_id: *id,
+            index: order.iter().position(|o| o == id).map(|i| i + 1),
+            title: label.title.clone(),
+            description: label.description.clone(),
+        })
+        .collect();
+    desktops.sort_by_key(|e| (e.index.unwrap_or(usize::MAX), e.desktop_id));
+    Bundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        desktops,
+        hotkeys: with_settings.then(|| cfg.hotkeys.clone()),
+        appearance: with_settings.then(|| cfg.appearance.clone()),
+    }
+}
+
+pub fn to_json(bundle: &Bundle) -> String {
+    let mut s = serde_json::to_string_pretty(bundle).expect("serialize bundle");
+    s.push('\n');
+    s
+}
+
+pub fn from_json(text: &str) -> Result<Bundle> {
+    let bundle: Bundle = serde_json::from_str(text).context("not a label bundle")?;
+    if bundle.format != BUNDLE_FORMAT {
+        bail!("not a label bundle (format {:?})", bundle.format);
+    }
+    if bundle.version > BUNDLE_VERSION {
+        bail!(
+            "bundle version {} is newer than this app supports ({BUNDLE_VERSION})",
+            bundle.version
+        );
+    }
+    Ok(bundle)
+}
+
+pub fn to_csv(entries: &[Entry]) -> Result<String> {
+    let mut w = csv::Writer::from_writer(Vec::new());
+    for e in entries {
+        w.serialize(e)?;
+    }
+    Ok(String::from_utf8(w.into_inner()?)?)
+}
+
+pub fn from_csv(text: &str) -> Result<Vec<Entry>> {
+    let mut r = csv::ReaderBuilder::new()
+        .trim(csv::Trim::Headers)
+        .from_reader(text.trim_start_matches('\u{feff}').as_bytes());
+    r.deserialize()
+        .enumerate()
+        .map(|(i, row)| row.with_context(|| format!("CSV record {}", i + 1)))
+        .collect()
+}
+
+/// Write the active profile's labels to `path`: CSV for `.csv`, a JSON
+/// bundle otherwise. Settings only fit in a bundle.
+pub fn export_file(
+    cfg: &Config,
+    path: &Path,
+    order: &[DesktopId],
+    with_settings: bool,
+) -> Result<usize> {
+    let bundle = bundle(cfg, order, with_settings);
+    let text = if is_csv(path) {
+        if with_settings {
+            bail!("CSV files hold labels only; export settings to a .json bundle");
+        }
+        to_csv(&bundle.desktops)?
+    } else {
+        to_json(&bundle)
+    };
+    fs::write(path, text).with_context(|| format!("write {}", path.display()))?;
+    Ok(bundle.desktops.len())
+}
+
+/// Read a CSV file or JSON bundle written by `export_file`.
+pub fn read_file(path: &Path) -> Result<Bundle> {
+    let text = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    if is_csv(path) {
+        Ok(Bundle {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            desktops: from_csv(&text)?,
+            hotkeys: None,
+            appearance: None,
+        })
+    } else {
+        from_json(&text)
+    }
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+}
+
+/// Merge `bundle` into the active profile of `cfg`. `order` is this
+/// machine's desktops by position and is only used for `ByIndex`.
+pub fn import(
+    cfg: &mut Config,
+    bundle: &Bundle,
+    strategy: MergeStrategy,
+    order: &[DesktopId],
+) -> ImportReport {
+    let mut report = ImportReport::default();
+    let labels = cfg.labels_mut();
+    for e in &bundle.desktops {
+        let target = match strategy {
+            MergeStrategy::ByIndex => {
+                match e
+                    .index
+                    .and_then(|i| i.checked_sub(1))
+                    .and_then(|i| order.get(i))
+                {
+                    Some(id) => *id,
+                    None => {
+                        report.unmatched += 1;
+                        continue;
+                    }
+                }
+            }
+            _ => e.desktop_id,
+        };
+        let label = DesktopLabel {
+            title: e.title.clone(),
+            description: e.description.clone(),
+            ..Default::default()
+        };
+        match labels.get_mut(&target) {
+            None => {
+                labels.insert(target, label);
+                report.added += 1;
+            }
+            Some(_) if strategy == MergeStrategy::KeepExisting => report.kept += 1,
+            Some(cur) if cur.title == label.title && cur.description == label.description => {
+                report.unchanged += 1
+            }
+            Some(cur) => {
+                // Only the text is exchanged; keep the rest of the label.
+                cur.title = label.title;
+                cur.description = label.description;
+                report.updated += 1;
+            }
+        }
+    }
+    if strategy != MergeStrategy::KeepExisting {
+        if let Some(hk) = &bundle.hotkeys {
+            cfg.hotkeys = hk.clone();
+            report.settings = true;
+        }
+        if let Some(a) = &bundle.appearance {
+            cfg.appearance = a.clone();
+            report.settings = true;
+        }
+    }
+    report
+}
+
+/// The command in `args` (program name excluded), if any:
+/// `--export <file> [--with-settings]` or `--import <file> [--merge <strategy>]`.
+pub fn command_arg<I: IntoIterator<Item = OsString>>(args: I) -> Result<Option<Command>> {
+    let (mut export, mut import, mut merge, mut with_settings) = (None, None, None, false);
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let Some(arg) = arg.to_str() else { continue };
+        let (name, inline) = match arg.split_once('=') {
+            Some((n, v)) => (n, Some(OsString::from(v))),
+            None => (arg, None),
+        };
+        let slot = match name {
+            "--export" => &mut export,
+            "--import" => &mut import,
+            "--merge" => &mut merge,
+            "--with-settings" => {
+                with_settings = true;
+                continue;
+            }
+            _ => continue,
+        };
+        let value = inline
+            .or_else(|| args.next())
+            .ok_or_else(|| anyhow!("{name} needs a value"))?;
+        *slot = Some(value);
+    }
+    let strategy = match merge {
+        Some(m) => m.to_string_lossy().parse()?,
+        None => MergeStrategy::default(),
+    };
+    Ok(match (export, import) {
+        (Some(_), Some(_)) => bail!("use either --export or --import, not both"),
+        (Some(path), None) => Some(Command::Export {
+            path: path.into(),
+            with_settings,
+        }),
+        (None, Some(path)) => Some(Command::Import {
+            path: path.into(),
+            strategy,
+        }),
+        (None, None) => None,
+    })
+}
+
+/// Run `cmd` against the config at `paths` and describe what happened.
+/// An import refuses to touch a config with problems, since saving would
+/// replace the offending values with defaults, and refuses settings the app
+/// could not use.
+pub fn run(cmd: &Command, paths: &Paths, order: &[DesktopId]) -> Result<String> {
+    let (mut cfg, problems) = load_from(paths);
+    match cmd {
+        Command::Export {
+            path,
+            with_settings,
+        } => {
+            let n = export_file(&cfg, path, order, *with_settings)?;
+            Ok(tr_with(
+                "exchange.exported",
+                &[
+                    ("count", &n.to_string()),
+                    ("file", &path.display().to_string()),
+                ],
+            ))
+        }
+        Command::Import { path, strategy } => {
+            if let Some(p) = problems.first() {
+                bail!(tr_with(
+                    "exchange.config_problems",
+                    &[("problem", &p.to_string())]
+                ));
+            }
+            let bundle = read_file(path)?;
+            let report = import(&mut cfg, &bundle, *strategy, order);
+            if let Some(p) = validate(&cfg, None).first() {
+                bail!(tr_with(
+                    "exchange.bad_settings",
+                    &[
+                        ("file", &path.display().to_string()),
+                        ("problem", &p.to_string())
+                    ]
+                ));
+            }
+            save_atomic(&cfg, paths)?;
+            Ok(tr_with(
+                "exchange.imported",
+                &[
+                    ("file", &path.display().to_string()),
+                    ("profile", cfg.active_profile_display_name()),
+                    ("strategy", &strategy.to_string()),
+                    ("report", &report.to_string()),
+                ],
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::label;
+
+    const A: DesktopId = DesktopId::from_u128(0xA);
+    const B: DesktopId = DesktopId::from_u128(0xB);
+
+    fn sample() -> Config {
+        let mut cfg = Config::default();
+        cfg.desktops.insert(A, label("Mail"));
+        cfg.desktops.insert(B, label("Build, \"release\""));
+        cfg
+    }
+
+    #[test]
+    fn bundle_orders_by_desktop_position() {
+        let b = bundle(&sample(), &[B, A], false);
+        let ids: Vec<_> = b.desktops.iter().map(|e| (e.desktop_id, e.index)).collect();
+        assert_eq!(ids, vec![(B, Some(1)), (A, Some(2))]);
+        assert!(b.hotkeys.is_none());
+
+        let b = bundle(&sample(), &[A], true);
+        assert_eq!(b.desktops[1].index, None);
+        assert!(b.hotkeys.is_some() && b.appearance.is_some());
+    }
+
+    #[test]
+    fn csv_round_trips_with_quoting() {
+        let entries = bundle(&sample(), &[A], false).desktops;
+        let text = to_csv(&entries).unwrap();
+        assert!(
+            text.starts_with("desktop_id,index,title,description\n"),
+            "{text}"
+        );
+        assert!(text.contains("\"Build, \"\"release\"\"\""), "{text}");
+        assert_eq!(from_csv(&text).unwrap(), entries);
+    }
+
+    #[test]
+    fn csv_accepts_bom_and_missing_columns() {
+        let text = "\u{feff}desktop_id,title\n{00000000-0000-0000-0000-00000000000A},Mail\n";
+        let entries = from_csv(text).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].desktop_id, A);
+        assert_eq!(entries[0].index, None);
+        assert_eq!(entries[0].description, "");
+        assert!(from_csv("desktop_id,title\nnope,Mail\n").is_err());
+    }
+
+    #[test]
+    fn bundles_identify_themselves() {
+        let json = to_json(&bundle(&sample(), &[], true));
+        let back = from_json(&json).unwrap();
+        assert_eq!(back.desktops.len(), 2);
+        assert!(from_json(r#"{"format":"other","version":1,"desktops":[]}"#).is_err());
+        assert!(from_json(r#"{"format":"mddsklbl-labels","version":99,"desktops":[]}"#).is_err());
+    }
+
+    #[test]
+    fn overwrite_and_keep_existing() {
+        let mut incoming = bundle(&sample(), &[], true);
+        incoming.desktops[0].title = "Inbox".into();
+        incoming.appearance.as_mut().unwrap().font_size_dip = 30;
+
+        let mut cfg = Config::default();
+        cfg.desktops.insert(A, label("Local"));
+        let mut keep = cfg.clone();
+
+        let r = import(&mut cfg, &incoming, MergeStrategy::Overwrite, &[]);
+        assert_eq!((r.added, r.updated, r.kept), (1, 1, 0));
+        assert!(r.settings);
+        assert_eq!(cfg.desktops[&A].title, "Inbox");
+        assert_eq!(cfg.appearance.font_size_dip, 30);
+
+        let r = import(&mut keep, &incoming, MergeStrategy::KeepExisting, &[]);
+        assert_eq!((r.added, r.updated, r.kept), (1, 0, 1));
+        assert!(!r.settings);
+        assert_eq!(keep.desktops[&A].title, "Local");
+        assert_eq!(keep.appearance.font_size_dip, 16);
+    }
+
+    #[test]
+    fn by_index_maps_onto_local_desktops() {
+        let incoming = bundle(&sample(), &[A, B], false);
+        let (x, y) = (DesktopId::from_u128(0x1), DesktopId::from_u128(0x2));
+        let mut cfg = Config::default();
+        let r = import(&mut cfg, &incoming, MergeStrategy::ByIndex, &[x]);
+        assert_eq!((r.added, r.unmatched), (1, 1));
+        assert_eq!(cfg.desktops[&x].title, "Mail");
+        assert!(!cfg.desktops.contains_key(&y));
+        assert!(!cfg.desktops.contains_key(&A));
+    }
+
+    #[test]
+    fn imports_go_to_the_active_profile() {
+        let mut cfg = Config::default();
+        cfg.profiles.insert("Home".into(), Default::default());
+        cfg.set_active_profile(Some("Home")).unwrap();
+        import(
+            &mut cfg,
+            &bundle(&sample(), &[], false),
+            MergeStrategy::Overwrite,
+            &[],
+        );
+        assert!(cfg.desktops.is_empty());
+        assert_eq!(cfg.profiles["Home"].desktops.len(), 2);
+    }
+
+    #[test]
+    fn strategies_parse() {
+        for s in [
+            MergeStrategy::Overwrite,
+            MergeStrategy::KeepExisting,
+            MergeStrategy::ByIndex,
+        ] {
+            assert_eq!(s.to_string().parse::<MergeStrategy>().unwrap(), s);
+        }
+        assert!("merge".parse::<MergeStrategy>().is_err());
+    }
+
+    #[test]
+    fn parses_command_flags() {
+        let args = |l: &[&str]| l.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(command_arg(args(&["--config", "x"])).unwrap(), None);
+        assert_eq!(
+            command_arg(args(&["--export", "out.json", "--with-settings"])).unwrap(),
+            Some(Command::Export {
+                path: "out.json".into(),
+                with_settings: true
+            })
+        );
+        assert_eq!(
+            command_arg(args(&["--merge=by-index", "--import=in.csv"])).unwrap(),
+            Some(Command::Import {
+                path: "in.csv".into(),
+                strategy: MergeStrategy::ByIndex
+            })
+        );
+        assert!(command_arg(args(&["--import"])).is_err());
+        assert!(command_arg(args(&["--import", "a", "--merge", "x"])).is_err());
+        assert!(command_arg(args(&["--import", "a", "--export", "b"])).is_err());
+    }
+}
@@ -0,0 +1,381 @@
+//! On-disk formats of the user config.
+//!
+//! The config is either `labels.json` (JSON with comments, see `jsonc`) or
+//! `labels.toml`; when both exist the TOML file wins. A file's format follows
+//! from its extension. Both are read into the same JSON document, so
+//! migrations, layering and validation work the same for either.
+
+use super::source::Location;
+use super::{ConfigError, Paths, backup, jsonc, migrate, schema};
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml_edit::{DocumentMut, ImDocument, Item, TableLike};
+
+pub const JSON_FILE: &str = "labels.json";
+pub const TOML_FILE: &str = "labels.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// The format of `path`, by extension. Anything but `.toml` is JSON.
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Format::Toml,
+            _ => Format::Json,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Toml => "toml",
+        }
+    }
+
+    /// Parse config text into a document. Also returns the text that value
+    /// locations refer to (for JSON, the text with comments blanked out).
+    pub(crate) fn parse(self, text: &str) -> Result<(Value, String), ConfigError> {
+        match self {
+            Format::Json => {
+                let plain = jsonc::strip(text);
+                let doc = serde_json::from_str(&plain).map_err(|e| ConfigError::from_json(&e))?;
+                Ok((doc, plain))
+            }
+            Format::Toml => Ok((parse_toml(text)?, text.to_string())),
+        }
+    }
+
+    /// Render a user document, keeping the comments and layout of `existing`
+    /// where the format allows it.
+    pub(crate) fn render(self, doc: &Value, existing: Option<&str>) -> Result<String> {
+        match self {
+            Format::Json => {
+                // Hand-annotated files keep their comments: only changed
+                // values are rewritten in place.
+                let patched = existing
+                    .filter(|t| jsonc::has_comments(t))
+                    .and_then(|t| jsonc::patch(t, doc));
+                match patched {
+                    Some(text) => Ok(text),
+                    None => Ok(serde_json::to_string_pretty(doc)?),
+                }
+            }
+            Format::Toml => {
+                let doc = without_nulls(doc);
+                if let Some(text) = existing.and_then(|t| patch_toml(t, &doc)) {
+                    return Ok(text);
+                }
+                toml::to_string_pretty(&doc).context("serialize TOML config")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "toml" => Ok(Format::Toml),
+            _ => Err(anyhow!("unknown config format {s:?}; use json or toml")),
+        }
+    }
+}
+
+/// The config file to use in `dir`: `labels.toml` if it exists, otherwise
+/// `labels.json`.
+pub fn config_file(dir: &Path) -> PathBuf {
+    let toml = dir.join(TOML_FILE);
+    if toml.is_file() {
+        toml
+    } else {
+        dir.join(JSON_FILE)
+    }
+}
+
+/// The value of `--convert-config <format>` in `args` (program name
+/// excluded), if present.
+pub fn convert_arg<I: IntoIterator<Item = OsString>>(args: I) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--convert-config" {
+            return Some(args.next()?.to_string_lossy().into_owned());
+        }
+        if let Some(v) = arg
+            .to_str()
+            .and_then(|s| s.strip_prefix("--convert-config="))
+        {
+            return Some(v.to_string());
+        }
+    }
+    None
+}
+
+/// Rewrite the config at `paths` in format `to`, next to the original. The
+/// original (and any file already at the target) is moved into the backups
+/// so the conversion can be undone. Returns the paths of the new file.
+pub fn convert(paths: &Paths, to: Format) -> Result<Paths> {
+    let from = Format::of(&paths.cfg_file);
+    if from == to {
+        bail!("{} is already {to}", paths.cfg_file.display());
+    }
+    let text = fs::read_to_string(&paths.cfg_file)
+        .with_context(|| format!("read {}", paths.cfg_file.display()))?;
+    super::parse_config_as(from, &text).context("refusing to convert unreadable config")?;
+    let (mut doc, _) = from.parse(&text)?;
+    migrate::migrate(&mut doc);
+    if let Some(obj) = doc.as_object_mut() {
+        match to {
+            Format::Toml => {
+                obj.remove("$schema");
+            }
+            Format::Json => {
+                obj.entry("$schema")
+                    .or_insert_with(|| Value::from(schema::SCHEMA_REF));
+            }
+        }
+    }
+
+    let target = Paths {
+        cfg_file: paths.cfg_file.with_extension(to.extension()),
+        cfg_dir: paths.cfg_dir.clone(),
+        log_dir: paths.log_dir.clone(),
+    };
+    backup::create_backup(&target)?;
+    backup::create_backup(paths)?;
+    if to == Format::Json
+        && let Err(e) = schema::write_schema(&target)
+    {
+        tracing::warn!(error=%e, "Failed to write config schema");
+    }
+    super::write_atomic(&target.cfg_file, to.render(&doc, None)?.as_bytes())?;
+    fs::remove_file(&paths.cfg_file).context("remove converted config")?;
+    tracing::info!(from=?paths.cfg_file, to=?target.cfg_file, "Converted config");
+    Ok(target)
+}
+
+fn parse_toml(text: &str) -> Result<Value, ConfigError> {
+    toml::from_str(text).map_err(|e| {
+        let at = Location::of_offset(text, e.span().map_or(0, |s| s.start));
+        ConfigError::Syntax {
+            line: at.line,
+            column: at.column,
+            message: e.message().to_string(),
+        }
+    })
+}
+
+/// TOML has no null; leave such members out.
+fn without_nulls(v: &Value) -> Value {
+    match v {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), without_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(without_nulls).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Update `original` in place to hold `new`, keeping comments, key order and
+/// formatting of everything that did not change. `None` if the text cannot
+/// be edited that way.
+fn patch_toml(original: &str, new: &Value) -> Option<String> {
+    let old = parse_toml(original).ok()?;
+    let mut doc: DocumentMut = original.parse().ok()?;
+    let (Value::Object(old), Value::Object(new_map)) = (&old, new) else {
+        return None;
+    };
+    sync_table(doc.as_table_mut(), false, old, new_map)?;
+    let out = doc.to_string();
+    (parse_toml(&out).ok()? == *new).then_some(out)
+}
+
+fn sync_table(
+    table: &mut dyn TableLike,
+    inline: bool,
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+) -> Option<()> {
+    for k in old.keys().filter(|k| !new.contains_key(*k)) {
+        table.remove(k);
+    }
+    for (k, v) in new {
+        let before = old.get(k);
+        if before == Some(v) {
+            continue;
+        }
+        let Some(item) = table.get_mut(k) else {
+            table.insert(k, to_item(v, inline)?);
+            continue;
+        };
+        if let (Some(Value::Object(b)), Value::Object(n)) = (before, v)
+            && item.is_table_like()
+        {
+            let child_inline = !item.is_table();
+            sync_table(item.as_table_like_mut()?, child_inline, b, n)?;
+            if let Some(t) = item.as_table_mut()
+                && t.is_empty()
+            {
+                t.set_implicit(false);
+            }
+            continue;
+        }
+        let mut replacement = to_item(v, inline || item.is_value())?;
+        if let (Some(old), Some(new)) = (item.as_value(), replacement.as_value_mut()) {
+            *new.decor_mut() = old.decor().clone();
+        }
+        *item = replacement;
+    }
+    Some(())
+}
+
+/// A TOML item for `v`: tables become `[sections]` unless `inline`.
+fn to_item(v: &Value, inline: bool) -> Option<Item> {
+    if v.is_object() && !inline {
+        let doc = toml_edit::ser::to_document(v).ok()?;
+        return Some(Item::Table(doc.as_table().clone()));
+    }
+    let value = v.serialize(toml_edit::ser::ValueSerializer::new()).ok()?;
+    Some(Item::Value(value))
+}
+
+/// Where the value at `path` starts in TOML text.
+pub(crate) fn locate_toml(src: &str, path: &[String]) -> Option<Location> {
+    let doc = ImDocument::parse(src).ok()?;
+    let mut item = doc.as_item();
+    for seg in path {
+        item = match seg.parse::<usize>() {
+            Ok(i) if item.is_array() || item.is_array_of_tables() => item.get(i)?,
+            _ => item.get(seg.as_str())?,
+        };
+    }
+    Some(Location::of_offset(src, item.span()?.start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn segments(path: &str) -> Vec<String> {
+        path.split('.').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn format_follows_extension() {
+        assert_eq!(Format::of(Path::new("a/labels.toml")), Format::Toml);
+        assert_eq!(Format::of(Path::new("a/LABELS.TOML")), Format::Toml);
+        assert_eq!(Format::of(Path::new("a/labels.json")), Format::Json);
+        assert_eq!(Format::of(Path::new("a/labels")), Format::Json);
+    }
+
+    #[test]
+    fn toml_precedes_json() {
+        let td = tempfile::tempdir().unwrap();
+        assert_eq!(config_file(td.path()), td.path().join(JSON_FILE));
+        fs::write(td.path().join(JSON_FILE), "{}").unwrap();
+        fs::write(td.path().join(TOML_FILE), "").unwrap();
+        assert_eq!(config_file(td.path()), td.path().join(TOML_FILE));
+    }
+
+    #[test]
+    fn parses_convert_flag() {
+        let args = |l: &[&str]| l.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(convert_arg(args(&[])), None);
+        assert_eq!(
+            convert_arg(args(&["--convert-config", "toml"])),
+            Some("toml".into())
+        );
+        assert_eq!(
+            convert_arg(args(&["--convert-config=json"])),
+            Some("json".into())
+        );
+        assert_eq!(convert_arg(args(&["--convert-config"])), None);
+    }
+
+    #[test]
+    fn toml_syntax_errors_have_positions() {
+        let err = Format::Toml
+            .parse("version = 2\nappearance = {\n")
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::Syntax { line: 2, .. }), "{err}");
+    }
+
+    #[test]
+    fn locates_toml_values() {
+        let src =
+            "version = 2\n\n[appearance]\nfont_size_dip = 0\n\n[hotkeys.edit_title]\nkey = \"\"\n";
+        assert_eq!(
+            locate_toml(src, &segments("appearance.font_size_dip")),
+            Some(Location {
+                line: 4,
+                column: 17
+            })
+        );
+        assert_eq!(
+            locate_toml(src, &segments("hotkeys.edit_title.key")),
+            Some(Location { line: 7, column: 7 })
+        );
+        assert_eq!(locate_toml(src, &segments("appearance.margin_px")), None);
+    }
+
+    #[test]
+    fn toml_patch_keeps_comments() {
+        let src = "# Labels per client\nversion = 2\n\n[appearance]\n# wall display\nfont_size_dip = 24 # big\nmargin_px = 8\n";
+        let new = json!({ "version": 2, "appearance": { "font_size_dip": 20, "margin_px": 8 } });
+        let out = Format::Toml.render(&new, Some(src)).unwrap();
+        assert_eq!(
+            out,
+            "# Labels per client\nversion = 2\n\n[appearance]\n# wall display\nfont_size_dip = 20 # big\nmargin_px = 8\n"
+        );
+    }
+
+    #[test]
+    fn toml_patch_adds_and_removes_members() {
+        let src = "version = 2\n\n[desktops.D178F97B-2525-4ED7-B219-6BA2AA6BE296]\ntitle = \"Work\" # main\ndescription = \"\"\n";
+        let new = json!({
+            "version": 2,
+            "desktops": {
+                "D178F97B-2525-4ED7-B219-6BA2AA6BE296": { "title": "Work", "description": "" },
+                "0A1B2C3D-0000-1111-2222-333344445555": { "title": "Play", "description": "" }
+            }
+        });
+        let out = Format::Toml.render(&new, Some(src)).unwrap();
+        assert!(out.contains("title = \"Work\" # main"), "{out}");
+        assert_eq!(Format::Toml.parse(&out).unwrap().0, new);
+
+        let new = json!({ "version": 2, "desktops": {} });
+        let out = Format::Toml.render(&new, Some(src)).unwrap();
+        assert_eq!(Format::Toml.parse(&out).unwrap().0, new);
+    }
+
+    #[test]
+    fn fresh_toml_drops_nulls() {
+        let doc = json!({ "version": null, "appearance": { "font_size_dip": 16 } });
+        let out = Format::Toml.render(&doc, None).unwrap();
+        assert_eq!(
+            Format::Toml.parse(&out).unwrap().0,
+            json!({ "appearance": { "font_size_dip": 16 } })
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! Edit history of desktop labels.
+//!
+//! Stored in `history.json` next to the config rather than in it, so the
+//! config stays easy to read and a damaged history never stops the labels
+//! from loading. Each desktop in each profile has a bounded stack of earlier
+//! labels to undo to, and a stack of undone labels to redo.
+
+use super::{Config, DesktopId, DesktopLabel, Paths, write_atomic};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const HISTORY_FILE: &str = "history.json";
+/// Earlier labels kept per desktop; older ones are dropped.
+pub const MAX_REVISIONS: usize = 50;
+
+/// A label as it was before an edit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revision {
+    /// When this label was replaced, in seconds since the Unix epoch.
+    pub at: u64,
+    #[serde(flatten)]
+    pub label: DesktopLabel,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stacks {
+    /// Oldest first; undo takes the last.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub undo: Vec<Revision>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redo: Vec<Revision>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    /// The top-level labels, by desktop. Kept apart from `profiles` so a
+    /// profile can have any name, including that of the default one.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub desktops: HashMap<DesktopId, Stacks>,
+    /// Keyed by profile name, then by desktop.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, HashMap<DesktopId, Stacks>>,
+}
+
+/// Seconds since the Unix epoch, for `Revision::at`.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl History {
+    pub fn path(paths: &Paths) -> PathBuf {
+        paths.cfg_dir.join(HISTORY_FILE)
+    }
+
+    /// The saved history, or an empty one if there is none or it is damaged.
+    pub fn load(paths: &Paths) -> Self {
+        let path = Self::path(paths);
+        match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                tracing::warn!(error=%e, path=?path, "ignoring unreadable label history");
+                Self::default()
+            }),
+            Err(e) => {
+                if e.kind() != ErrorKind::NotFound {
+                    tracing::warn!(error=%e, path=?path, "cannot read label history");
+                }
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, paths: &Paths) -> Result<()> {
+        let mut text = serde_json::to_string_pretty(self)?;
+        text.push('\n');
+        write_atomic(&Self::path(paths), text.as_bytes())
+    }
+
+    /// Earlier labels of `desktop` in the active profile, oldest first.
+    pub fn revisions(&self, cfg: &Config, desktop: DesktopId) -> &[Revision] {
+        let desktops = match cfg.active() {
+            Some((name, _)) => self.profiles.get(name),
+            None => Some(&self.desktops),
+        };
+        desktops
+            .and_then(|d| d.get(&desktop))
+            .map_or(&[], |s| &s.undo)
+    }
+
+    /// Set the label of `desktop` in the active profile, remembering the one
+    /// it replaces. Returns false, and records nothing, if the label is
+    /// unchanged.
+    pub fn edit(
+        &mut self,
+        cfg: &mut Config,
+        desktop: DesktopId,
+        label: DesktopLabel,
+        at: u64,
+    ) -> bool {
+        let previous = replace(cfg, desktop, label);
+        if previous == current(cfg, desktop) {
+            return false;
+        }
+        let stacks = self.stacks(cfg, desktop);
+        stacks.undo.push(Revision {
+            at,
+            label: previous,
+        });
+        if stacks.undo.len() > MAX_REVISIONS {
+            stacks.undo.remove(0);
+        }
+        stacks.redo.clear();
+        true
+    }
+
+    /// Restore the label `desktop` had before its last edit. Returns false if
+    /// there is nothing to undo.
+    pub fn undo(&mut self, cfg: &mut Config, desktop: DesktopId, at: u64) -> bool {
+        self.step(cfg, desktop, at, false)
+    }
+
+    /// Reapply the label the last `undo` replaced.
+    pub fn redo(&mut self, cfg: &mut Config, desktop: DesktopId, at: u64) -> bool {
+        self.step(cfg, desktop, at, true)
+    }
+
+    fn step(&mut self, cfg: &mut Config, desktop: DesktopId, at: u64, redo: bool) -> bool {
+        let stacks = self.stacks(cfg, desktop);
+        let (from, to) = if redo {
+            (&mut stacks.redo, &mut stacks.undo)
+        } else {
+            (&mut stacks.undo, &mut stacks.redo)
+        };
+        let Some(target) = from.pop() else {
+            return false;
+        };
+        let replaced = replace(cfg, desktop, target.label);
+        to.push(Revision {
+            at,
+            label: replaced,
+        });
+        true
+    }
+
+    fn stacks(&mut self, cfg: &Config, desktop: DesktopId) -> &mut Stacks {
+        let desktops = match cfg.active() {
+            Some((name, _)) => self.profiles.entry(name.clone()).or_default(),
+            None => &mut self.desktops,
+        };
+        desktops.entry(desktop).or_default()
+    }
+}
+
+fn current(cfg: &Config, desktop: DesktopId) -> DesktopLabel {
+    cfg.labels().get(&desktop).cloned().unwrap_or_default()
+}
+
+/// Store `label` (removing the entry for an empty one) and return the old label.
+fn replace(cfg: &mut Config, desktop: DesktopId, label: DesktopLabel) -> DesktopLabel {
+    let labels = cfg.labels_mut();
+    let old = if label == DesktopLabel::default() {
+        labels.remove(&desktop)
+    } else {
+        labels.insert(desktop, label)
+    };
+    old.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::label;
+
+    const ID: DesktopId = DesktopId::from_u128(1);
+
+    #[test]
+    fn undo_and_redo_walk_the_edits() {
+        let mut cfg = Config::default();
+        let mut h = History::default();
+        assert!(h.edit(&mut cfg, ID, label("One"), 1));
+        assert!(h.edit(&mut cfg, ID, label("Two"), 2));
+        assert!(!h.edit(&mut cfg, ID, label("Two"), 3));
+        assert_eq!(
+            h.revisions(&cfg, ID),
+            &[
+                Revision {
+                    at: 1,
+                    label: DesktopLabel::default()
+                },
+                Revision {
+                    at: 2,
+                    label: label("One")
+                },
+            ]
+        );
+
+        assert!(h.undo(&mut cfg, ID, 4));
+        assert_eq!(cfg.desktops[&ID].title, "One");
+        assert!(h.undo(&mut cfg, ID, 5));
+        assert!(!cfg.desktops.contains_key(&ID));
+        assert!(!h.undo(&mut cfg, ID, 6));
+
+        assert!(h.redo(&mut cfg, ID, 7));
+        assert_eq!(cfg.desktops[&ID].title, "One");
+        // A new edit discards what could still be redone.
+        assert!(h.edit(&mut cfg, ID, label("Three"), 8));
+        assert!(!h.redo(&mut cfg, ID, 9));
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut cfg = Config::default();
+        let mut h = History::default();
+        for i in 0..MAX_REVISIONS + 10 {
+            h.edit(&mut cfg, ID, label(&i.to_string()), i as u64);
+        }
+        let revs = h.revisions(&cfg, ID);
+        assert_eq!(revs.len(), MAX_REVISIONS);
+        assert_eq!(revs[0].label.title, "9");
+    }
+
+    #[test]
+    fn profiles_have_separate_histories() {
+        let mut cfg = Config::default();
+        cfg.profiles.insert("Home".into(), Default::default());
+        let mut h = History::default();
+        h.edit(&mut cfg, ID, label("Work"), 1);
+        cfg.set_active_profile(Some("Home")).unwrap();
+        h.edit(&mut cfg, ID, label("Home"), 2);
+        assert!(h.undo(&mut cfg, ID, 3));
+        assert!(!h.undo(&mut cfg, ID, 4));
+        assert_eq!(cfg.desktops[&ID].title, "Work");
+        assert!(!cfg.profiles["Home"].desktops.contains_key(&ID));
+    }
+
+    #[test]
+    fn a_profile_named_like_the_default_has_its_own_history() {
+        let mut cfg = Config::default();
+        cfg.profiles.insert(
+            super::super::profile::DEFAULT_PROFILE.into(),
+            Default::default(),
+        );
+        let mut h = History::default();
+        h.edit(&mut cfg, ID, label("Top"), 1);
+        cfg.set_active_profile(Some(super::super::profile::DEFAULT_PROFILE))
+            .unwrap();
+        assert!(h.revisions(&cfg, ID).is_empty());
+        assert!(!h.undo(&mut cfg, ID, 2));
+        assert_eq!(cfg.desktops[&ID].title, "Top");
+    }
+
+    #[test]
+    fn saves_next_to_the_config() {
+        let td = tempfile::tempdir().unwrap();
+        let paths = super::super::location::paths_for(td.path());
+        assert!(History::load(&paths).desktops.is_empty());
+
+        let mut cfg = Config::default();
+        let mut h = History::default();
+        h.edit(&mut cfg, ID, label("One"), 1);
+        h.save(&paths).unwrap();
+        assert!(td.path().join(HISTORY_FILE).exists());
+        assert_eq!(History::load(&paths).revisions(&cfg, ID).len(), 1);
+
+        fs::write(History::path(&paths), "{ not json").unwrap();
+        assert!(History::load(&paths).desktops.is_empty());
+    }
+}
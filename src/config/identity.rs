@@ -0,0 +1,368 @@
+//! Recognizing desktops whose GUID changed.
+//!
+//! Windows resets or profile migrations give every desktop a new GUID, which
+//! would orphan every label. The app therefore records the position and OS
+//! name of each labelled desktop in `identities`. When a live desktop has no
+//! label, `plan` looks for an orphaned desktop recorded with the same name
+//! and/or position, and `Config::rebind` moves its labels over.
+//!
+//! A name match is strong evidence; a position alone is not, since a new
+//! desktop often takes the place of a deleted one. Position-only matches are
+//! therefore only `confident` when none of the labelled desktops exist any
+//! more, i.e. when every GUID changed at once.
+
+use super::{Config, DesktopId};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// What the app does with a match, see `plan`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchPolicy {
+    /// Never move labels between desktops.
+    Off,
+    /// Ask before moving labels.
+    #[default]
+    Offer,
+    /// Move labels for confident matches without asking; ask for the rest.
+    Auto,
+}
+
+/// Where a labelled desktop was last seen.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DesktopIdentity {
+    /// Position in Task View, starting at 1.
+    #[schemars(range(min = 1))]
+    pub index: usize,
+    /// Name given to the desktop in Windows, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// A desktop as reported by the OS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveDesktop {
+    pub id: DesktopId,
+    /// Empty for desktops that still have their default name.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Evidence {
+    NameAndIndex,
+    Name,
+    Index,
+}
+
+/// Labels of orphaned desktop `from` probably belong to live desktop `to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rebind {
+    pub from: DesktopId,
+    pub to: DesktopId,
+    pub evidence: Evidence,
+    /// Whether `MatchPolicy::Auto` may apply this without asking.
+    pub confident: bool,
+}
+
+impl DesktopIdentity {
+    fn new(index: usize, name: &str) -> Self {
+        Self {
+            index,
+            name: normalize(name).map(|_| name.trim().to_string()),
+        }
+    }
+}
+
+impl Evidence {
+    /// English description, for the log.
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::NameAndIndex => "same name and position",
+            Self::Name => "same name",
+            Self::Index => "same position",
+        }
+    }
+
+    /// Key of `describe`'s text in the message catalogs (see `i18n`).
+    pub fn message_key(self) -> &'static str {
+        match self {
+            Self::NameAndIndex => "evidence.name_and_index",
+            Self::Name => "evidence.name",
+            Self::Index => "evidence.index",
+        }
+    }
+}
+
+fn normalize(name: &str) -> Option<String> {
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_lowercase())
+}
+
+/// Matches for live desktops without labels, each orphaned desktop used at
+/// most once, in desktop order. Name matches are assigned before position
+/// matches. Empty if `cfg.desktop_matching` is `Off`.
+pub fn plan(cfg: &Config, live: &[LiveDesktop]) -> Vec<Rebind> {
+    if cfg.desktop_matching == MatchPolicy::Off {
+        return Vec::new();
+    }
+    let labelled = cfg.labelled_desktops();
+    let live_ids: HashSet<DesktopId> = live.iter().map(|d| d.id).collect();
+    let mut candidates: Vec<(DesktopId, &DesktopIdentity)> = labelled
+        .iter()
+        .filter(|id| !live_ids.contains(id))
+        .filter_map(|id| cfg.identities.get(id).map(|k| (*id, k)))
+        .collect();
+    let all_changed = labelled.iter().all(|id| !live_ids.contains(id));
+    let mut found: Vec<(usize, Rebind)> = Vec::new();
+    for by_name in [true, false] {
+        for (i, desktop) in live.iter().enumerate() {
+            if labelled.contains(&desktop.id) || found.iter().any(|(_, r)| r.to == desktop.id) {
+                continue;
+            }
+            let best = if by_name {
+                name_match(&candidates, desktop, i + 1)
+            } else {
+                index_match(&candidates, desktop, i + 1)
+            };
+            if let Some((pos, evidence)) = best {
+                let (from, _) = candidates.remove(pos);
+                let confident = evidence != Evidence::Index || all_changed;
+                let rebind = Rebind {
+                    from,
+                    to: desktop.id,
+                    evidence,
+                    confident,
+                };
+                found.push((i, rebind));
+            }
+        }
+    }
+    found.sort_by_key(|(i, _)| *i);
+    found.into_iter().map(|(_, r)| r).collect()
+}
+
+fn recorded_name(k: &DesktopIdentity) -> Option<String> {
+    k.name.as_deref().and_then(normalize)
+}
+
+/// The one candidate recorded with the name of `desktop`, using the position
+/// to break ties.
+fn name_match(
+    candidates: &[(DesktopId, &DesktopIdentity)],
+    desktop: &LiveDesktop,
+    index: usize,
+) -> Option<(usize, Evidence)> {
+    let name = normalize(&desktop.name)?;
+    let same_name: Vec<usize> = (0..candidates.len())
+        .filter(|&c| recorded_name(candidates[c].1).as_ref() == Some(&name))
+        .collect();
+    let same_index: Vec<usize> = same_name
+        .iter()
+        .copied()
+        .filter(|&c| candidates[c].1.index == index)
+        .collect();
+    match (same_name.as_slice(), same_index.as_slice()) {
+        (_, [c]) => Some((*c, Evidence::NameAndIndex)),
+        ([c], _) => Some((*c, Evidence::Name)),
+        _ => None,
+    }
+}
+
+/// The one candidate recorded at `index`, unless its recorded name and the
+/// name of `desktop` differ.
+fn index_match(
+    candidates: &[(DesktopId, &DesktopIdentity)],
+    desktop: &LiveDesktop,
+    index: usize,
+) -> Option<(usize, Evidence)> {
+    let name = normalize(&desktop.name);
+    let same_index: Vec<usize> = (0..candidates.len())
+        .filter(|&c| candidates[c].1.index == index)
+        .filter(|&c| name.is_none() || recorded_name(candidates[c].1).is_none())
+        .collect();
+    match same_index.as_slice() {
+        [c] => Some((*c, Evidence::Index)),
+        _ => None,
+    }
+}
+
+impl Config {
+    /// Record position and name of every labelled live desktop, and forget
+    /// desktops that no longer have a label. Returns whether anything changed.
+    pub fn record_identities(&mut self, live: &[LiveDesktop]) -> bool {
+        let labelled = self.labelled_desktops();
+        let before = self.identities.clone();
+        self.identities.retain(|id, _| labelled.contains(id));
+        for (i, desktop) in live.iter().enumerate() {
+            if labelled.contains(&desktop.id) {
+                let identity = DesktopIdentity::new(i + 1, &desktop.name);
+                self.identities.insert(desktop.id, identity);
+            }
+        }
+        self.identities != before
+    }
+
+    /// Move the labels of `r.from` in every profile to `r.to`, where `r.to`
+    /// has none yet, along with its recorded identity.
+    pub fn rebind(&mut self, r: &Rebind) {
+        let profiles = self.profiles.values_mut().map(|p| &mut p.desktops);
+        for desktops in std::iter::once(&mut self.desktops).chain(profiles) {
+            if desktops.contains_key(&r.to) {
+                continue;
+            }
+            if let Some(label) = desktops.remove(&r.from) {
+                desktops.insert(r.to, label);
+            }
+        }
+        if let Some(identity) = self.identities.remove(&r.from) {
+            self.identities.insert(r.to, identity);
+        }
+        self.archived.orphaned.remove(&r.from);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DesktopLabel;
+
+    fn id(n: u128) -> DesktopId {
+        DesktopId::from_u128(n)
+    }
+
+    fn live(ids: &[(u128, &str)]) -> Vec<LiveDesktop> {
+        ids.iter()
+            .map(|(n, name)| LiveDesktop {
+                id: id(*n),
+                name: name.to_string(),
+            })
+            .collect()
+    }
+
+    /// A config with labels for `desktops`, recorded as seen in that order.
+    fn labelled(desktops: &[(u128, &str)]) -> Config {
+        let mut cfg = Config::default();
+        for (n, _) in desktops {
+            cfg.desktops.insert(
+                id(*n),
+                DesktopLabel {
+                    title: format!("label {n}"),
+                    ..Default::default()
+                },
+            );
+        }
+        assert!(cfg.record_identities(&live(desktops)));
+        cfg
+    }
+
+    fn pairs(plan: &[Rebind]) -> Vec<(u128, u128, Evidence, bool)> {
+        plan.iter()
+            .map(|r| (r.from.as_u128(), r.to.as_u128(), r.evidence, r.confident))
+            .collect()
+    }
+
+    #[test]
+    fn records_labelled_desktops_only() {
+        let mut cfg = labelled(&[(1, "Mail"), (2, "")]);
+        assert_eq!(
+            cfg.identities[&id(1)],
+            DesktopIdentity {
+                index: 1,
+                name: Some("Mail".into())
+            }
+        );
+        assert_eq!(cfg.identities[&id(2)].name, None);
+        assert!(!cfg.record_identities(&live(&[(1, "Mail"), (2, "")])));
+
+        // Reordering is recorded; unlabelled desktops are not.
+        assert!(cfg.record_identities(&live(&[(3, ""), (2, ""), (1, "Mail")])));
+        assert_eq!(cfg.identities[&id(1)].index, 3);
+        assert!(!cfg.identities.contains_key(&id(3)));
+
+        cfg.desktops.remove(&id(2));
+        assert!(cfg.record_identities(&live(&[(1, "Mail")])));
+        assert!(!cfg.identities.contains_key(&id(2)));
+    }
+
+    #[test]
+    fn names_win_over_positions() {
+        let cfg = labelled(&[(1, "Mail"), (2, "Code"), (3, "")]);
+        let plan = plan(&cfg, &live(&[(11, "code"), (12, "Mail"), (13, "")]));
+        assert_eq!(
+            pairs(&plan),
+            vec![
+                (2, 11, Evidence::Name, true),
+                (1, 12, Evidence::Name, true),
+                (3, 13, Evidence::Index, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn position_alone_is_only_confident_when_every_guid_changed() {
+        let mut cfg = labelled(&[(1, ""), (2, "")]);
+        let all_new = plan(&cfg, &live(&[(11, ""), (12, "")]));
+        assert_eq!(
+            pairs(&all_new),
+            vec![
+                (1, 11, Evidence::Index, true),
+                (2, 12, Evidence::Index, true)
+            ]
+        );
+
+        // Desktop 2 was deleted and a new one took its place.
+        let replaced = plan(&cfg, &live(&[(1, ""), (12, "")]));
+        assert_eq!(pairs(&replaced), vec![(2, 12, Evidence::Index, false)]);
+
+        cfg.desktop_matching = MatchPolicy::Off;
+        assert!(plan(&cfg, &live(&[(11, ""), (12, "")])).is_empty());
+    }
+
+    #[test]
+    fn conflicting_names_and_ties_do_not_match() {
+        let cfg = labelled(&[(1, "Mail"), (2, "")]);
+        // A differently named desktop at the same position is not a match.
+        assert!(plan(&cfg, &live(&[(11, "Games"), (2, "")])).is_empty());
+
+        let mut twins = labelled(&[(1, "Work"), (2, "Work")]);
+        let p = plan(&twins, &live(&[(11, "Work"), (12, "Work")]));
+        assert_eq!(
+            pairs(&p),
+            vec![
+                (1, 11, Evidence::NameAndIndex, true),
+                (2, 12, Evidence::NameAndIndex, true)
+            ]
+        );
+        twins.identities.get_mut(&id(2)).unwrap().index = 1;
+        let p = plan(&twins, &live(&[(11, "Work"), (12, "")]));
+        assert!(p.is_empty(), "{p:?}");
+    }
+
+    #[test]
+    fn name_matches_are_assigned_before_position_matches() {
+        let cfg = labelled(&[(1, "Mail"), (2, "")]);
+        // The unnamed first desktop must not take "Mail" by position.
+        let p = plan(&cfg, &live(&[(11, ""), (12, "Mail")]));
+        assert_eq!(pairs(&p), vec![(1, 12, Evidence::Name, true)]);
+    }
+
+    #[test]
+    fn rebind_moves_labels_in_every_profile() {
+        let mut cfg = labelled(&[(1, "Mail")]);
+        let mut home = crate::config::Profile::default();
+        home.desktops.insert(id(1), DesktopLabel::default());
+        cfg.profiles.insert("Home".into(), home);
+        cfg.reconcile(&[id(11)], 0, u64::MAX);
+        assert!(cfg.is_orphaned(id(1)));
+
+        let p = plan(&cfg, &live(&[(11, "Mail")]));
+        cfg.rebind(&p[0]);
+        assert_eq!(cfg.desktops[&id(11)].title, "label 1");
+        assert!(cfg.profiles["Home"].desktops.contains_key(&id(11)));
+        assert!(!cfg.desktops.contains_key(&id(1)));
+        assert!(!cfg.is_orphaned(id(1)));
+        assert_eq!(cfg.identities[&id(11)].index, 1);
+        assert!(plan(&cfg, &live(&[(11, "Mail")])).is_empty());
+    }
+}
@@ -0,0 +1,510 @@
+//! JSON with comments (`//`, `/* */`) and trailing commas.
+//!
+//! `strip` turns such text into plain JSON for serde without moving anything:
+//! every removed character becomes a space, so line and column numbers still
+//! point into the original text. `patch` rewrites a document with new values
+//! while keeping the comments and layout of everything that did not change.
+
+use serde_json::Value;
+
+/// Replace comments and trailing commas with spaces; newlines are kept.
+pub fn strip(src: &str) -> String {
+    let chars: Vec<char> = src.chars().collect();
+    let mut out: Vec<char> = Vec::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' => {
+                let end = string_end(&chars, i);
+                out.extend_from_slice(&chars[i..end]);
+                i = end;
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    out.push(blank(chars[i]));
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let mut j = i + 2;
+                while j < chars.len() && !(chars[j] == '*' && chars.get(j + 1) == Some(&'/')) {
+                    j += 1;
+                }
+                let end = (j + 2).min(chars.len());
+                out.extend(chars[i..end].iter().map(|&c| blank(c)));
+                i = end;
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    // Comments are gone now, so a trailing comma is one followed only by
+    // whitespace and a closing bracket.
+    let mut i = 0;
+    while i < out.len() {
+        match out[i] {
+            '"' => i = string_end(&out, i),
+            ',' => {
+                let next = out[i + 1..].iter().find(|c| !c.is_whitespace());
+                if matches!(next, Some('}' | ']')) {
+                    out[i] = ' ';
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    out.into_iter().collect()
+}
+
+/// Whether `src` contains comments or trailing commas.
+pub fn has_comments(src: &str) -> bool {
+    strip(src) != src
+}
+
+fn blank(c: char) -> char {
+    if c == '\n' || c == '\r' { c } else { ' ' }
+}
+
+/// Index just past the string literal starting at `start`.
+fn string_end(chars: &[char], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// Rewrite `original` so it holds `new`, keeping comments and formatting of
+/// unchanged parts. Members the app added are appended to their object,
+/// removed ones are dropped together with their comments. Returns `None` if
+/// `original` cannot be parsed or the result would not read back as `new`.
+pub fn patch(original: &str, new: &Value) -> Option<String> {
+    let mut p = Parser {
+        src: original.as_bytes(),
+        pos: 0,
+    };
+    p.trivia();
+    let root = p.value()?;
+    p.trivia();
+    if p.pos != original.len() {
+        return None;
+    }
+    let mut out = String::with_capacity(original.len());
+    out.push_str(&original[..root.start()]);
+    render(original, &root, new, "", &mut out);
+    out.push_str(&original[root.end()..]);
+    let reread: Value = serde_json::from_str(&strip(&out)).ok()?;
+    (&reread == new).then_some(out)
+}
+
+enum Node {
+    Object {
+        start: usize,
+        end: usize,
+        members: Vec<Member>,
+        /// Whitespace and comments before the closing brace.
+        tail: (usize, usize),
+    },
+    Other {
+        start: usize,
+        end: usize,
+    },
+}
+
+struct Member {
+    key: String,
+    /// Whitespace and comments before the key.
+    lead: (usize, usize),
+    key_start: usize,
+    value: Node,
+    /// A comment on the same line after the value (and comma).
+    trail: (usize, usize),
+}
+
+impl Node {
+    fn start(&self) -> usize {
+        match self {
+            Node::Object { start, .. } | Node::Other { start, .. } => *start,
+        }
+    }
+
+    fn end(&self) -> usize {
+        match self {
+            Node::Object { end, .. } | Node::Other { end, .. } => *end,
+        }
+    }
+}
+
+fn render(src: &str, node: &Node, new: &Value, indent: &str, out: &mut String) {
+    let text = &src[node.start()..node.end()];
+    let unchanged = serde_json::from_str::<Value>(&strip(text)).is_ok_and(|old| &old == new);
+    if unchanged {
+        out.push_str(text);
+        return;
+    }
+    let (
+        Node::Object {
+            members,
+            tail,
+            start,
+            ..
+        },
+        Value::Object(map),
+    ) = (node, new)
+    else {
+        out.push_str(&pretty(new, indent));
+        return;
+    };
+    // Objects written on one line stay on one line.
+    let single = !text.contains('\n');
+    let member_indent = members
+        .first()
+        .map(|m| line_indent(src, m.key_start).to_string())
+        .unwrap_or_else(|| format!("{indent}  "));
+    let kept: Vec<&Member> = members
+        .iter()
+        .filter(|m| map.contains_key(&m.key))
+        .collect();
+    let added: Vec<(&String, &Value)> = map
+        .iter()
+        .filter(|(k, _)| !members.iter().any(|m| &m.key == *k))
+        .collect();
+    let total = kept.len() + added.len();
+    let has_added = !added.is_empty();
+
+    out.push('{');
+    let mut n = 0;
+    for m in kept {
+        n += 1;
+        out.push_str(&src[m.lead.0..m.lead.1]);
+        out.push_str(&src[m.key_start..m.value.start()]);
+        render(src, &m.value, &map[&m.key], &member_indent, out);
+        if n < total {
+            out.push(',');
+        }
+        out.push_str(&src[m.trail.0..m.trail.1]);
+    }
+    for (k, v) in added {
+        n += 1;
+        let value = if single {
+            out.push(' ');
+            inline(v)
+        } else {
+            out.push('\n');
+            out.push_str(&member_indent);
+            pretty(v, &member_indent)
+        };
+        out.push_str(&Value::from(k.as_str()).to_string());
+        out.push_str(": ");
+        out.push_str(&value);
+        if n < total {
+            out.push(',');
+        }
+    }
+    let tail = &src[tail.0..tail.1];
+    if has_added && !single && !tail.contains('\n') {
+        out.push('\n');
+        out.push_str(line_indent(src, *start));
+    }
+    out.push_str(tail);
+    out.push('}');
+}
+
+/// Single-line JSON with a space after separators: `{ "a": 1, "b": [1, 2] }`.
+fn inline(v: &Value) -> String {
+    match v {
+        Value::Object(map) if !map.is_empty() => {
+            let members: Vec<String> = map
+                .iter()
+                .map(|(k, v)| format!("{}: {}", Value::from(k.as_str()), inline(v)))
+                .collect();
+            format!("{{ {} }}", members.join(", "))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(inline).collect();
+            format!("[{}]", items.join(", "))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Pretty JSON whose continuation lines are indented by `indent`.
+fn pretty(v: &Value, indent: &str) -> String {
+    let s = serde_json::to_string_pretty(v).expect("serialize value");
+    s.replace('\n', &format!("\n{indent}"))
+}
+
+/// Leading whitespace of the line containing byte offset `pos`.
+fn line_indent(src: &str, pos: usize) -> &str {
+    let line_start = src[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line = &src[line_start..pos];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn at(&self, s: &[u8]) -> bool {
+        self.src[self.pos..].starts_with(s)
+    }
+
+    /// Skip whitespace and comments.
+    fn trivia(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\r' | b'\n') => self.pos += 1,
+                Some(b'/') if self.at(b"//") || self.at(b"/*") => self.comment(),
+                _ => return,
+            }
+        }
+    }
+
+    fn comment(&mut self) {
+        if self.at(b"//") {
+            while self.peek().is_some_and(|c| c != b'\n') {
+                self.pos += 1;
+            }
+        } else {
+            self.pos += 2;
+            while self.pos < self.src.len() && !self.at(b"*/") {
+                self.pos += 1;
+            }
+            self.pos = (self.pos + 2).min(self.src.len());
+        }
+    }
+
+    /// Skip spaces, an optional comma and a comment on the same line.
+    /// Returns where the same-line trailer starts and whether a comma was seen.
+    fn same_line_trailer(&mut self) -> (usize, bool) {
+        let before = self.pos;
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.pos += 1;
+        }
+        let comma = self.peek() == Some(b',');
+        let start = if comma { self.pos + 1 } else { before };
+        self.pos = start;
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.pos += 1;
+        }
+        if self.at(b"//") || self.at(b"/*") {
+            self.comment();
+        } else {
+            self.pos = start;
+        }
+        (start, comma)
+    }
+
+    fn value(&mut self) -> Option<Node> {
+        let start = self.pos;
+        match self.peek()? {
+            b'{' => self.object(),
+            b'[' => {
+                self.pos += 1;
+                loop {
+                    self.trivia();
+                    match self.peek()? {
+                        b']' => {
+                            self.pos += 1;
+                            return Some(Node::Other {
+                                start,
+                                end: self.pos,
+                            });
+                        }
+                        b',' => self.pos += 1,
+                        _ => {
+                            self.value()?;
+                        }
+                    }
+                }
+            }
+            b'"' => {
+                self.string()?;
+                Some(Node::Other {
+                    start,
+                    end: self.pos,
+                })
+            }
+            _ => {
+                while self.peek().is_some_and(|c| {
+                    !matches!(c, b',' | b'}' | b']' | b' ' | b'\t' | b'\r' | b'\n' | b'/')
+                }) {
+                    self.pos += 1;
+                }
+                (self.pos > start).then_some(Node::Other {
+                    start,
+                    end: self.pos,
+                })
+            }
+        }
+    }
+
+    fn object(&mut self) -> Option<Node> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut members = Vec::new();
+        loop {
+            let lead_start = self.pos;
+            self.trivia();
+            match self.peek()? {
+                b'}' => {
+                    let tail = (lead_start, self.pos);
+                    self.pos += 1;
+                    return Some(Node::Object {
+                        start,
+                        end: self.pos,
+                        members,
+                        tail,
+                    });
+                }
+                b'"' => {
+                    let key_start = self.pos;
+                    let key = self.string()?;
+                    self.trivia();
+                    (self.peek()? == b':').then_some(())?;
+                    self.pos += 1;
+                    self.trivia();
+                    let value = self.value()?;
+                    let (trail_start, comma) = self.same_line_trailer();
+                    let trail = (trail_start, self.pos);
+                    if !comma {
+                        // A comma on a later line; comments before it are dropped.
+                        let save = self.pos;
+                        self.trivia();
+                        if self.peek() == Some(b',') {
+                            self.pos += 1;
+                        } else {
+                            self.pos = save;
+                        }
+                    }
+                    members.push(Member {
+                        key,
+                        lead: (lead_start, key_start),
+                        key_start,
+                        value,
+                        trail,
+                    });
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => self.pos += 1,
+            }
+        }
+        let raw = std::str::from_utf8(&self.src[start..self.pos]).ok()?;
+        serde_json::from_str(raw).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const DOC: &str = r#"{
+  // Client work lives on desktop 1.
+  "desktops": {
+    "A": { "title": "Client A", "description": "" }, // billable
+    /* old */ "B": { "title": "Old", "description": "" },
+  },
+  "appearance": { "font_size_dip": 16, "margin_px": 8 }, // tuned for 4K
+}
+"#;
+
+    #[test]
+    fn strip_keeps_positions() {
+        let s = strip(DOC);
+        assert_eq!(s.lines().count(), DOC.lines().count());
+        for (a, b) in s.lines().zip(DOC.lines()) {
+            assert_eq!(a.chars().count(), b.chars().count());
+        }
+        let v: Value = serde_json::from_str(&s).unwrap();
+        assert_eq!(v["desktops"]["B"]["title"], "Old");
+    }
+
+    #[test]
+    fn strip_leaves_strings_alone() {
+        let src = r#"{ "url": "http://x/*y*/", "s": "a,]" }"#;
+        assert_eq!(strip(src), src);
+        assert!(!has_comments(src));
+        assert!(has_comments("[1,]"));
+    }
+
+    #[test]
+    fn patch_changes_values_and_keeps_comments() {
+        let mut v: Value = serde_json::from_str(&strip(DOC)).unwrap();
+        v["desktops"]["A"]["title"] = json!("Client A (new)");
+        v["appearance"]["font_size_dip"] = json!(20);
+        let out = patch(DOC, &v).unwrap();
+        assert!(out.contains("// Client work lives on desktop 1."));
+        assert!(out.contains("/* old */"));
+        assert!(out.contains("// billable"));
+        assert!(out.contains("// tuned for 4K"));
+        assert!(out.contains(r#""title": "Client A (new)""#));
+        assert!(out.contains(r#""font_size_dip": 20"#));
+        // Unchanged members keep their exact text.
+        assert!(out.contains(r#""B": { "title": "Old", "description": "" }"#));
+    }
+
+    #[test]
+    fn patch_adds_and_removes_members() {
+        let mut v: Value = serde_json::from_str(&strip(DOC)).unwrap();
+        v["desktops"].as_object_mut().unwrap().remove("B");
+        v["desktops"]["C"] = json!({ "title": "New", "description": "" });
+        v["version"] = json!(2);
+        let out = patch(DOC, &v).unwrap();
+        assert!(!out.contains("/* old */"));
+        assert!(out.contains("// billable"));
+        assert!(out.contains("// billable\n    \"C\": {\n      \""));
+        assert!(out.contains("\n  \"version\": 2\n}"));
+        let reread: Value = serde_json::from_str(&strip(&out)).unwrap();
+        assert_eq!(reread, v);
+    }
+
+    #[test]
+    fn single_line_objects_stay_on_one_line() {
+        let src = "{\n  // x\n  \"a\": { \"b\": 1 }\n}";
+        let out = patch(src, &json!({ "a": { "b": 2, "c": [true, { "d": 1 }] } })).unwrap();
+        assert_eq!(
+            out,
+            "{\n  // x\n  \"a\": { \"b\": 2, \"c\": [true, { \"d\": 1 }] }\n}"
+        );
+    }
+
+    #[test]
+    fn patch_of_identical_value_is_identity() {
+        let v: Value = serde_json::from_str(&strip(DOC)).unwrap();
+        assert_eq!(patch(DOC, &v).unwrap(), DOC);
+    }
+
+    #[test]
+    fn patch_rejects_unparseable_text() {
+        assert_eq!(patch("{ \"a\": ", &json!({})), None);
+    }
+}
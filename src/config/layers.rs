@@ -0,0 +1,560 @@
+//! Layered configuration.
+//!
+//! The effective config is built from, lowest first: the built-in defaults, a
+//! machine-wide `defaults.json` under `%ProgramData%\Acme\DesktopLabeler`
+//! (for IT to push appearance and hotkeys), the user's `labels.json`, and
+//! `MDDSKLBL__SECTION__FIELD=value` environment variables. Later layers win
+//! value by value, and `Provenance` records which layer each value came from.
+//!
+//! Saving writes back only what belongs to the user: environment overrides
+//! are never persisted, and values that merely repeat the machine defaults
+//! are left out unless the user's file already had them.
+
+use super::schema::{SettingKind, setting_kind};
+use super::{Config, Format, Paths};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::path::PathBuf;
+
+/// Prefix of environment overrides, e.g. `MDDSKLBL__APPEARANCE__FONT_SIZE_DIP=20`.
+pub const ENV_PREFIX: &str = "MDDSKLBL__";
+pub const MACHINE_FILE: &str = "defaults.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    Default,
+    Machine,
+    User,
+    Environment,
+}
+
+impl Layer {
+    /// Key of the layer's name in the message catalogs (see `i18n`).
+    pub fn message_key(self) -> &'static str {
+        match self {
+            Layer::Default => "layer.default",
+            Layer::Machine => "layer.machine",
+            Layer::User => "layer.user",
+            Layer::Environment => "layer.environment",
+        }
+    }
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Layer::Default => "default",
+            Layer::Machine => "machine",
+            Layer::User => "user",
+            Layer::Environment => "environment",
+        })
+    }
+}
+
+/// `%ProgramData%\Acme\DesktopLabeler\defaults.json`, if `ProgramData` is set.
+pub fn machine_defaults_file() -> Option<PathBuf> {
+    let base = std::env::var_os("ProgramData")?;
+    Some(
+        PathBuf::from(base)
+            .join("Acme")
+            .join("DesktopLabeler")
+            .join(MACHINE_FILE),
+    )
+}
+
+/// Where the layers around the user file come from.
+#[derive(Debug, Clone, Default)]
+pub struct LayerSources {
+    pub machine_file: Option<PathBuf>,
+    /// `(name, value)` pairs; names without `ENV_PREFIX` are ignored.
+    pub env: Vec<(String, String)>,
+}
+
+impl LayerSources {
+    /// The machine defaults file and environment of this process.
+    pub fn current() -> Self {
+        Self {
+            machine_file: machine_defaults_file(),
+            env: std::env::vars()
+                .filter(|(k, _)| k.starts_with(ENV_PREFIX))
+                .collect(),
+        }
+    }
+
+    /// Read the machine file and parse the environment overrides. A missing
+    /// machine file is normal; an unreadable one is logged and skipped so a
+    /// broken policy file never stops the app.
+    pub fn read(&self) -> Layers {
+        let machine = self.machine_file.as_ref().and_then(|p| {
+            let text = fs::read_to_string(p).ok()?;
+            match serde_json::from_str::<Value>(&super::jsonc::strip(&text)) {
+                Ok(v @ Value::Object(_)) => Some(v),
+                Ok(_) => {
+                    tracing::warn!(path=?p, "Machine defaults file is not a JSON object, ignoring");
+                    None
+                }
+                Err(e) => {
+                    tracing::warn!(path=?p, error=%e, "Failed to parse machine defaults, ignoring");
+                    None
+                }
+            }
+        });
+        let env = self
+            .env
+            .iter()
+            .filter_map(|(k, v)| {
+                let parsed = env_override(k, v);
+                if parsed.is_none() && k.starts_with(ENV_PREFIX) {
+                    tracing::warn!(name=%k, "Ignoring malformed config override variable");
+                }
+                parsed
+            })
+            .collect();
+        Layers { machine, env }
+    }
+}
+
+/// `MDDSKLBL__HOTKEYS__EDIT_TITLE__KEY=E` → (`["hotkeys", "edit_title",
+/// "key"]`, `"E"`). The value is typed when it is applied (see `env_value`).
+/// Desktop labels cannot be overridden.
+fn env_override(name: &str, value: &str) -> Option<(Vec<String>, String)> {
+    let rest = name.strip_prefix(ENV_PREFIX)?;
+    let path: Vec<String> = rest.split("__").map(str::to_ascii_lowercase).collect();
+    if path.iter().any(String::is_empty) || path[0] == "desktops" {
+        return None;
+    }
+    Some((path, value.to_string()))
+}
+
+/// An environment value as the type of the setting it sets: text for a
+/// text setting, so `KEY=7` stays the key `"7"`, and JSON (`20`, `true`)
+/// for anything else, falling back to text.
+fn env_value(raw: &str, kind: SettingKind) -> Value {
+    match kind {
+        SettingKind::Text => Value::String(raw.to_string()),
+        SettingKind::Other => {
+            serde_json::from_str(raw.trim()).unwrap_or_else(|_| Value::String(raw.to_string()))
+        }
+    }
+}
+
+/// The machine and environment layers, ready to be applied.
+#[derive(Debug, Clone, Default)]
+pub struct Layers {
+    pub machine: Option<Value>,
+    /// Paths and raw values of the environment overrides.
+    pub env: Vec<(Vec<String>, String)>,
+}
+
+/// Which layer each effective value came from, keyed by dotted path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance(BTreeMap<String, Layer>);
+
+impl Provenance {
+    pub fn layer_of(&self, path: &str) -> Option<Layer> {
+        self.0.get(path).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Layer)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+}
+
+impl Layers {
+    /// Build the effective document with `user` (already migrated) between
+    /// the machine and environment layers.
+    pub fn apply(&self, user: Option<&Value>) -> (Value, Provenance) {
+        let mut doc = serde_json::to_value(Config::default()).expect("serialize default config");
+        let mut prov = Provenance::default();
+        record(&doc, &mut Vec::new(), Layer::Default, &mut prov);
+        if let Some(machine) = &self.machine {
+            merge(&mut doc, machine);
+            record(machine, &mut Vec::new(), Layer::Machine, &mut prov);
+        }
+        if let Some(user) = user {
+            merge(&mut doc, user);
+            record(user, &mut Vec::new(), Layer::User, &mut prov);
+        }
+        // Settings are looked up in the schema rather than the document, as
+        // optional settings are left out of it while unset.
+        for (path, raw) in &self.env {
+            let set =
+                setting_kind(path).and_then(|kind| insert(&mut doc, path, env_value(raw, kind)));
+            match set {
+                Some(value) => record(value, &mut path.clone(), Layer::Environment, &mut prov),
+                None => {
+                    tracing::warn!(path=%path.join("."), "Config override targets an unknown setting")
+                }
+            }
+        }
+        (doc, prov)
+    }
+
+    /// Drop whichever machine or environment value set `path`, which the
+    /// effective config could not use, and say which layer it was in. `None`
+    /// if the value came from the user's file.
+    pub fn drop_value(&mut self, path: &str, user: &Value) -> Option<Layer> {
+        let path: Vec<String> = path.split('.').map(str::to_string).collect();
+        let before = self.env.len();
+        self.env
+            .retain(|(p, _)| !(p.starts_with(&path) || path.starts_with(p)));
+        if self.env.len() < before {
+            return Some(Layer::Environment);
+        }
+        if get(user, &path).is_some() {
+            return None;
+        }
+        let machine = self.machine.as_mut()?;
+        get(machine, &path)?;
+        remove(machine, &path);
+        Some(Layer::Machine)
+    }
+
+    /// Turn the document of a config about to be saved back into what belongs
+    /// in the user's file. `existing` is the user's file as it is on disk.
+    pub fn user_document(&self, mut doc: Value, existing: Option<&Value>) -> Value {
+        for (path, _) in &self.env {
+            match existing.and_then(|e| get(e, path)) {
+                Some(v) => {
+                    if let Some(slot) = get_mut(&mut doc, path) {
+                        *slot = v.clone();
+                    }
+                }
+                None => remove(&mut doc, path),
+            }
+        }
+        // A value that only repeats a lower layer stays out of the user's file
+        // unless the file already had it. Built-in defaults count only when
+        // the file exists, so a new file still lists every setting.
+        let defaults = serde_json::to_value(Config::default()).expect("serialize default config");
+        let before = doc.clone();
+        let lower = [
+            self.machine.as_ref(),
+            existing.is_some().then_some(&defaults),
+        ];
+        for layer in lower.into_iter().flatten() {
+            let mut leaves = Vec::new();
+            collect_leaves(layer, &mut Vec::new(), &mut leaves);
+            for (path, value) in leaves {
+                let from_user = existing.and_then(|e| get(e, &path)).is_some();
+                if !from_user && get(&doc, &path) == Some(value) {
+                    remove(&mut doc, &path);
+                }
+            }
+        }
+        prune_empty(&mut doc, &before, existing, &mut Vec::new());
+        doc
+    }
+}
+
+/// Recursively merge `top` into `base`; objects merge key by key, anything
+/// else replaces.
+fn merge(base: &mut Value, top: &Value) {
+    match (base, top) {
+        (Value::Object(b), Value::Object(t)) => {
+            for (k, v) in t {
+                match b.get_mut(k) {
+                    Some(slot) => merge(slot, v),
+                    None => {
+                        b.insert(k.clone(), v.clone());
+                    }
+                }
+            }
+        }
+        (b, t) => *b = t.clone(),
+    }
+}
+
+/// Drop objects that `user_document` emptied and the user's file did not
+/// have either.
+fn prune_empty(v: &mut Value, before: &Value, existing: Option<&Value>, path: &mut Vec<String>) {
+    let Value::Object(map) = v else {
+        return;
+    };
+    let keys: Vec<String> = map.keys().cloned().collect();
+    for k in keys {
+        path.push(k.clone());
+        if let Some(child) = map.get_mut(&k) {
+            prune_empty(child, before, existing, path);
+            let emptied = child.as_object().is_some_and(|m| m.is_empty())
+                && get(before, path)
+                    .and_then(Value::as_object)
+                    .is_some_and(|m| !m.is_empty());
+            if emptied && existing.and_then(|e| get(e, path)).is_none() {
+                map.remove(&k);
+            }
+        }
+        path.pop();
+    }
+}
+
+fn record(v: &Value, path: &mut Vec<String>, layer: Layer, prov: &mut Provenance) {
+    let mut leaves = Vec::new();
+    collect_leaves(v, path, &mut leaves);
+    for (p, _) in leaves {
+        prov.0.insert(p.join("."), layer);
+    }
+}
+
+fn collect_leaves<'a>(
+    v: &'a Value,
+    path: &mut Vec<String>,
+    out: &mut Vec<(Vec<String>, &'a Value)>,
+) {
+    match v {
+        Value::Object(map) if !map.is_empty() => {
+            for (k, child) in map {
+                path.push(k.clone());
+                collect_leaves(child, path, out);
+                path.pop();
+            }
+        }
+        _ => out.push((path.clone(), v)),
+    }
+}
+
+fn get<'a>(v: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(v, |cur, k| cur.as_object()?.get(k))
+}
+
+fn get_mut<'a>(v: &'a mut Value, path: &[String]) -> Option<&'a mut Value> {
+    path.iter()
+        .try_fold(v, |cur, k| cur.as_object_mut()?.get_mut(k))
+}
+
+/// Set the value at `path`, creating missing objects on the way.
+fn insert<'a>(v: &'a mut Value, path: &[String], value: Value) -> Option<&'a Value> {
+    let (last, parent) = path.split_last()?;
+    let mut cur = v;
+    for k in parent {
+        let slot = cur
+            .as_object_mut()?
+            .entry(k.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if slot.is_null() {
+            *slot = Value::Object(Map::new());
+        }
+        cur = slot;
+    }
+    let map = cur.as_object_mut()?;
+    map.insert(last.clone(), value);
+    map.get(last)
+}
+
+fn remove(v: &mut Value, path: &[String]) {
+    let Some((last, parent)) = path.split_last() else {
+        return;
+    };
+    if let Some(map) = get_mut(v, parent).and_then(Value::as_object_mut) {
+        map.remove(last);
+    }
+}
+
+/// Human-readable dump of every effective value and the layer it came from.
+pub fn diagnostics(paths: &Paths, sources: &LayerSources) -> String {
+    let layers = sources.read();
+    let user = fs::read_to_string(&paths.cfg_file)
+        .ok()
+        .and_then(|s| Format::of(&paths.cfg_file).parse(&s).ok())
+        .map(|(mut doc, _)| {
+            super::migrate::migrate(&mut doc);
+            doc
+        });
+    let (doc, prov) = layers.apply(user.as_ref());
+
+    let mut out = String::new();
+    let _ = writeln!(out, "Desktop Labeler configuration");
+    let _ = writeln!(
+        out,
+        "config location: {:?}",
+        super::location::current_source()
+    );
+    let _ = writeln!(
+        out,
+        "user file: {} ({})",
+        paths.cfg_file.display(),
+        found(user.is_some())
+    );
+    match &sources.machine_file {
+        Some(p) => {
+            let _ = writeln!(
+                out,
+                "machine defaults: {} ({})",
+                p.display(),
+                found(layers.machine.is_some())
+            );
+        }
+        None => {
+            let _ = writeln!(out, "machine defaults: (ProgramData not set)");
+        }
+    }
+    let _ = writeln!(out, "environment overrides: {}", layers.env.len());
+    let _ = writeln!(out);
+    let mut leaves = Vec::new();
+    collect_leaves(&doc, &mut Vec::new(), &mut leaves);
+    for (path, value) in leaves {
+        let key = path.join(".");
+        let layer = prov.layer_of(&key).unwrap_or(Layer::Default);
+        let _ = writeln!(out, "{key} = {value}  [{layer}]");
+    }
+    out
+}
+
+fn found(yes: bool) -> &'static str {
+    if yes {
+        "loaded"
+    } else {
+        "not found or unreadable"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn layers(machine: Option<Value>, env: &[(&str, &str)]) -> Layers {
+        let sources = LayerSources {
+            machine_file: None,
+            env: env
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        };
+        Layers {
+            machine,
+            env: sources.read().env,
+        }
+    }
+
+    #[test]
+    fn later_layers_win_and_are_recorded() {
+        let l = layers(
+            Some(json!({ "appearance": { "font_size_dip": 18, "font_family": "Consolas" } })),
+            &[("MDDSKLBL__APPEARANCE__FONT_FAMILY", "Cascadia Mono")],
+        );
+        let user = json!({ "appearance": { "font_size_dip": 20 } });
+        let (doc, prov) = l.apply(Some(&user));
+        assert_eq!(doc["appearance"]["font_size_dip"], 20);
+        assert_eq!(doc["appearance"]["font_family"], "Cascadia Mono");
+        assert_eq!(doc["appearance"]["margin_px"], 8);
+        assert_eq!(prov.layer_of("appearance.font_size_dip"), Some(Layer::User));
+        assert_eq!(
+            prov.layer_of("appearance.font_family"),
+            Some(Layer::Environment)
+        );
+        assert_eq!(prov.layer_of("appearance.margin_px"), Some(Layer::Default));
+    }
+
+    #[test]
+    fn env_names_map_to_setting_paths() {
+        assert_eq!(
+            env_override("MDDSKLBL__HOTKEYS__EDIT_TITLE__KEY", "E"),
+            Some((
+                vec!["hotkeys".into(), "edit_title".into(), "key".into()],
+                "E".into()
+            ))
+        );
+        assert_eq!(env_override("MDDSKLBL__APPEARANCE____X", "1"), None);
+        assert_eq!(env_override("MDDSKLBL__DESKTOPS__X__TITLE", "1"), None);
+        assert_eq!(env_override("OTHER", "1"), None);
+    }
+
+    #[test]
+    fn env_values_take_the_type_of_the_setting() {
+        let l = layers(
+            None,
+            &[
+                ("MDDSKLBL__HOTKEYS__EDIT_TITLE__KEY", "7"),
+                ("MDDSKLBL__APPEARANCE__FONT_FAMILY", "true"),
+                ("MDDSKLBL__APPEARANCE__FONT_SIZE_DIP", " 20 "),
+                ("MDDSKLBL__APPEARANCE__HIDE_ON_FULLSCREEN", "true"),
+            ],
+        );
+        let (doc, _) = l.apply(None);
+        assert_eq!(doc["hotkeys"]["edit_title"]["key"], json!("7"));
+        assert_eq!(doc["appearance"]["font_family"], json!("true"));
+        assert_eq!(doc["appearance"]["font_size_dip"], json!(20));
+        assert_eq!(doc["appearance"]["hide_on_fullscreen"], json!(true));
+    }
+
+    #[test]
+    fn unusable_values_are_dropped_from_their_own_layer() {
+        let mut l = layers(
+            Some(json!({ "appearance": { "margin_px": "wide" } })),
+            &[("MDDSKLBL__APPEARANCE__FONT_SIZE_DIP", "big")],
+        );
+        let user = json!({ "appearance": { "max_lines": "x" } });
+        assert_eq!(
+            l.drop_value("appearance.font_size_dip", &user),
+            Some(Layer::Environment)
+        );
+        assert!(l.env.is_empty());
+        assert_eq!(l.drop_value("appearance.max_lines", &user), None);
+        assert_eq!(
+            l.drop_value("appearance.margin_px", &user),
+            Some(Layer::Machine)
+        );
+        assert_eq!(l.machine, Some(json!({ "appearance": {} })));
+    }
+
+    #[test]
+    fn env_can_set_optional_settings() {
+        let l = layers(
+            None,
+            &[
+                ("MDDSKLBL__APPEARANCE__POSITION", "left"),
+                ("MDDSKLBL__APPEARANCE__ACCENT", "#123"),
+                ("MDDSKLBL__APPEARANCE__PADDING_PX", "12"),
+                ("MDDSKLBL__LANGUAGE", "de"),
+            ],
+        );
+        let (doc, prov) = l.apply(None);
+        assert_eq!(doc["appearance"]["position"], json!("left"));
+        assert_eq!(doc["appearance"]["accent"], json!("#123"));
+        assert_eq!(doc["appearance"]["padding_px"], json!(12));
+        assert_eq!(doc["language"], json!("de"));
+        assert_eq!(
+            prov.layer_of("appearance.position"),
+            Some(Layer::Environment)
+        );
+        let cfg: Config = serde_json::from_value(doc).unwrap();
+        assert_eq!(cfg.appearance.padding_px, Some(12));
+        assert_eq!(cfg.language.as_deref(), Some("de"));
+    }
+
+    #[test]
+    fn env_override_of_unknown_setting_is_ignored() {
+        let l = layers(None, &[("MDDSKLBL__APPEARANCE__NOPE", "1")]);
+        let (doc, _) = l.apply(None);
+        assert!(doc["appearance"].get("nope").is_none());
+    }
+
+    #[test]
+    fn user_document_drops_env_and_machine_values() {
+        let l = layers(
+            Some(json!({ "appearance": { "font_size_dip": 18, "margin_px": 4 } })),
+            &[("MDDSKLBL__APPEARANCE__FONT_FAMILY", "Consolas")],
+        );
+        // The user explicitly pinned the margin to the machine value.
+        let existing = json!({ "appearance": { "margin_px": 4 } });
+        let (effective, _) = l.apply(Some(&existing));
+        let saved = l.user_document(effective, Some(&existing));
+        let appearance = saved["appearance"].as_object().unwrap();
+        assert!(!appearance.contains_key("font_size_dip"));
+        assert!(!appearance.contains_key("font_family"));
+        assert_eq!(appearance["margin_px"], 4);
+    }
+
+    #[test]
+    fn env_override_keeps_users_own_value_on_save() {
+        let l = layers(None, &[("MDDSKLBL__APPEARANCE__FONT_SIZE_DIP", "30")]);
+        let existing = json!({ "appearance": { "font_size_dip": 12 } });
+        let (effective, _) = l.apply(Some(&existing));
+        assert_eq!(effective["appearance"]["font_size_dip"], 30);
+        let saved = l.user_document(effective, Some(&existing));
+        assert_eq!(saved["appearance"]["font_size_dip"], 12);
+    }
+}
@@ -0,0 +1,215 @@
+//! Where the config file and the logs live.
+//!
+//! In order of precedence:
+//! 1. `--config <path>` on the command line,
+//! 2. the `MDDSKLBL_CONFIG` environment variable,
+//! 3. a `mddsklbl.portable` marker file next to the executable (portable mode:
+//!    `config\` and `logs\` beside the exe),
+//! 4. the per-user profile (`%APPDATA%\Acme\DesktopLabeler`).
+//!
+//! An explicit path may name either the config file or a directory to keep
+//! it in (`labels.toml` if present, else `labels.json`); logs go to a `logs`
+//! directory next to it.
+
+use super::{Paths, format};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const ENV_VAR: &str = "MDDSKLBL_CONFIG";
+pub const PORTABLE_MARKER: &str = "mddsklbl.portable";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    CommandLine,
+    Environment,
+    Portable,
+    Profile,
+}
+
+static CLI_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Record the `--config` value for this process. Only the first call counts.
+pub fn set_cli_override(path: PathBuf) {
+    let _ = CLI_OVERRIDE.set(path);
+}
+
+/// The value of `--config <path>` or `--config=<path>` in `args` (program
+/// name excluded). The last occurrence wins.
+pub fn config_arg<I: IntoIterator<Item = OsString>>(args: I) -> Option<PathBuf> {
+    let mut found = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            found = args.next().map(PathBuf::from);
+        } else if let Some(v) = arg.to_str().and_then(|s| s.strip_prefix("--config=")) {
+            found = Some(PathBuf::from(v));
+        }
+    }
+    found
+}
+
+/// Paths for an explicitly chosen location: a directory, or the config file
+/// itself.
+pub fn paths_for(target: &Path) -> Paths {
+    let target = std::path::absolute(target).unwrap_or_else(|_| target.to_path_buf());
+    let is_dir = target.is_dir() || target.extension().is_none();
+    let (cfg_dir, cfg_file) = if is_dir {
+        (target.clone(), format::config_file(&target))
+    } else {
+        let dir = target.parent().map(Path::to_path_buf).unwrap_or_default();
+        (dir, target)
+    };
+    Paths {
+        log_dir: cfg_dir.join("logs"),
+        cfg_file,
+        cfg_dir,
+    }
+}
+
+fn portable_paths(exe_dir: &Path) -> Paths {
+    let cfg_dir = exe_dir.join("config");
+    Paths {
+        cfg_file: format::config_file(&cfg_dir),
+        cfg_dir,
+        log_dir: exe_dir.join("logs"),
+    }
+}
+
+/// Apply the override chain. `None` means the per-user profile applies.
+pub fn resolve(
+    cli: Option<&Path>,
+    env: Option<&OsStr>,
+    exe_dir: Option<&Path>,
+) -> Option<(Source, Paths)> {
+    if let Some(p) = cli {
+        return Some((Source::CommandLine, paths_for(p)));
+    }
+    if let Some(p) = env.filter(|v| !v.is_empty()) {
+        return Some((Source::Environment, paths_for(Path::new(p))));
+    }
+    let dir = exe_dir.filter(|d| d.join(PORTABLE_MARKER).is_file())?;
+    Some((Source::Portable, portable_paths(dir)))
+}
+
+/// `resolve` for this process: `--config`, the environment and the running
+/// executable's directory.
+pub fn resolve_current() -> Option<(Source, Paths)> {
+    let exe = std::env::current_exe().ok();
+    let exe_dir = exe.as_deref().and_then(Path::parent);
+    resolve(
+        CLI_OVERRIDE.get().map(PathBuf::as_path),
+        std::env::var_os(ENV_VAR).as_deref(),
+        exe_dir,
+    )
+}
+
+/// Which rule picked this process's config location.
+pub fn current_source() -> Source {
+    resolve_current().map_or(Source::Profile, |(source, _)| source)
+}
+
+/// Name that tells instances apart: the default instance keeps `base`,
+/// instances with their own config location get a suffix derived from its
+/// folder so they can run side by side. The folder, not the file, so that
+/// converting `labels.json` to `labels.toml` keeps the name.
+pub fn instance_name(base: &str) -> String {
+    let custom = resolve_current().map(|(_, p)| p.cfg_dir);
+    instance_name_for(base, custom.as_deref())
+}
+
+fn instance_name_for(base: &str, custom_dir: Option<&Path>) -> String {
+    let Some(cfg_dir) = custom_dir else {
+        return base.to_string();
+    };
+    let key = cfg_dir.to_string_lossy().to_lowercase();
+    format!("{base}.{:016x}", fnv1a(key.as_bytes()))
+}
+
+/// 64-bit FNV-1a. Instance names must not change between builds, which
+/// `DefaultHasher` does not promise.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ u64::from(*b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<OsString> {
+        list.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn parses_config_flag_forms() {
+        assert_eq!(config_arg(args(&[])), None);
+        assert_eq!(
+            config_arg(args(&["--config", "a.json"])),
+            Some(PathBuf::from("a.json"))
+        );
+        assert_eq!(
+            config_arg(args(&["--config=b.json", "--config", "c"])),
+            Some(PathBuf::from("c"))
+        );
+        assert_eq!(config_arg(args(&["--config"])), None);
+    }
+
+    #[test]
+    fn precedence_is_cli_env_portable() {
+        let td = tempfile::tempdir().unwrap();
+        let exe_dir = td.path().join("usb");
+        std::fs::create_dir_all(&exe_dir).unwrap();
+        let cli = td.path().join("cli.json");
+        let env = td.path().join("env");
+
+        assert!(resolve(None, None, Some(&exe_dir)).is_none());
+        std::fs::write(exe_dir.join(PORTABLE_MARKER), "").unwrap();
+
+        let (src, p) = resolve(None, None, Some(&exe_dir)).unwrap();
+        assert_eq!(src, Source::Portable);
+        assert_eq!(p.cfg_file, exe_dir.join("config").join("labels.json"));
+        assert_eq!(p.log_dir, exe_dir.join("logs"));
+
+        let (src, p) = resolve(None, Some(env.as_os_str()), Some(&exe_dir)).unwrap();
+        assert_eq!(src, Source::Environment);
+        assert_eq!(p.cfg_file, env.join("labels.json"));
+
+        let (src, p) = resolve(Some(&cli), Some(env.as_os_str()), Some(&exe_dir)).unwrap();
+        assert_eq!(src, Source::CommandLine);
+        assert_eq!(p.cfg_file, cli);
+        assert_eq!(p.cfg_dir, td.path());
+        assert_eq!(p.log_dir, td.path().join("logs"));
+    }
+
+    #[test]
+    fn empty_env_var_is_ignored() {
+        assert!(resolve(None, Some(OsStr::new("")), None).is_none());
+    }
+
+    #[test]
+    fn relative_paths_are_made_absolute() {
+        let p = paths_for(Path::new("isolated"));
+        assert!(p.cfg_file.is_absolute());
+        assert!(p.cfg_file.ends_with("isolated/labels.json"));
+    }
+
+    #[test]
+    fn instance_names_differ_per_location() {
+        let a = Path::new("C:/a");
+        let b = Path::new("C:/b");
+        assert_eq!(instance_name_for("x", None), "x");
+        assert_ne!(
+            instance_name_for("x", Some(a)),
+            instance_name_for("x", Some(b))
+        );
+        assert_eq!(
+            instance_name_for("x", Some(a)),
+            instance_name_for("x", Some(Path::new("c:/A")))
+        );
+        // Pinned: a running instance must find one started by another build.
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(instance_name_for("x", Some(a)), "x.8110ee91ed05513c");
+    }
+}
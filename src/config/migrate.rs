@@ -0,0 +1,251 @@
+//! Versioned migrations applied to the raw `labels.json` document.
+//!
+//! Each step upgrades the document from `to - 1` to `to`. Steps operate on the
+//! untyped `serde_json::Value` so they can rename, move or rewrite fields that
+//! the current `Config` types no longer understand.
+
+use super::DesktopId;
+use serde_json::{Map, Value};
+
+/// Schema version written by this build.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Where `v2_canonical_desktop_keys` keeps labels it could not place, keyed
+/// as they were. Unknown to `Config`, so it survives saves untouched.
+pub const UNMIGRATED_DESKTOPS: &str = "unmigrated_desktops";
+
+/// A single upgrade step over the raw config document.
+pub struct Migration {
+    /// Version the document is at after this step has run.
+    pub to: u32,
+    pub description: &'static str,
+    pub apply: fn(&mut Value),
+}
+
+/// All known migrations, ordered by target version.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        to: 1,
+        description: "change snap_position hotkey default from S to L",
+        apply: v1_snap_key_s_to_l,
+    },
+    Migration {
+        to: 2,
+        description: "normalize desktop keys to canonical GUIDs",
+        apply: v2_canonical_desktop_keys,
+    },
+];
+
+/// Version recorded in the document; missing, null or malformed counts as 0,
+/// one too large for `u32` as `u32::MAX`.
+pub fn document_version(doc: &Value) -> u32 {
+    doc.get("version")
+        .and_then(Value::as_u64)
+        .map(|v| u32::try_from(v).unwrap_or(u32::MAX))
+        .unwrap_or(0)
+}
+
+/// Run every migration newer than the document's version, in order, and
+/// record the resulting version. Returns `true` if the document changed.
+///
+/// Documents written by a newer build are left untouched.
+pub fn migrate(doc: &mut Value) -> bool {
+    if !doc.is_object() {
+        return false;
+    }
+    let from = document_version(doc);
+    if from >= CURRENT_VERSION {
+        if from > CURRENT_VERSION {
+            tracing::warn!(
+                version = from,
+                supported = CURRENT_VERSION,
+                "config written by a newer version; skipping migrations"
+            );
+        }
+        return false;
+    }
+    for step in MIGRATIONS.iter().filter(|m| m.to > from) {
+        tracing::info!(to = step.to, "config migration: {}", step.description);
+        (step.apply)(doc);
+    }
+    doc["version"] = Value::from(CURRENT_VERSION);
+    true
+}
+
+fn v1_snap_key_s_to_l(doc: &mut Value) {
+    if let Some(key) = doc.pointer_mut("/hotkeys/snap_position/key")
+        && key.as_str().is_some_and(|k| k.eq_ignore_ascii_case("S"))
+    {
+        *key = Value::from("L");
+    }
+}
+
+/// Older builds keyed desktops by the Debug output of the winvd desktop,
+/// `Desktop(Guid(<GUID>))`, or by `default` when the API was unavailable.
+/// Labels whose key is not a GUID in any form, and all but the first of two
+/// keys naming the same desktop, move to `UNMIGRATED_DESKTOPS` so the user
+/// can move them back by hand.
+fn v2_canonical_desktop_keys(doc: &mut Value) {
+    let Some(Value::Object(desktops)) = doc.get_mut("desktops") else {
+        return;
+    };
+    let mut out = Map::new();
+    let mut unmigrated = Map::new();
+    for (key, label) in std::mem::take(desktops) {
+        let id = if key == "default" {
+            Some(DesktopId::FALLBACK)
+        } else {
+            let inner = key
+                .strip_prefix("Desktop(Guid(")
+                .and_then(|k| k.strip_suffix("))"))
+                .unwrap_or(&key);
+            inner.parse::<DesktopId>().ok()
+        };
+        match id {
+            Some(id) if !out.contains_key(&id.to_string()) => {
+                out.insert(id.to_string(), label);
+            }
+            Some(_) => {
+                tracing::warn!(key=%key, "keeping duplicate desktop label under {UNMIGRATED_DESKTOPS}");
+                unmigrated.insert(key, label);
+            }
+            None => {
+                tracing::warn!(key=%key, "keeping label with unrecognized desktop key under {UNMIGRATED_DESKTOPS}");
+                unmigrated.insert(key, label);
+            }
+        }
+    }
+    *desktops = out;
+    if unmigrated.is_empty() {
+        return;
+    }
+    let Some(root) = doc.as_object_mut() else {
+        return;
+    };
+    let slot = root
+        .entry(UNMIGRATED_DESKTOPS)
+        .or_insert_with(|| Value::Object(Map::new()));
+    match slot {
+        Value::Object(kept) => kept.extend(unmigrated),
+        other => *other = Value::Object(unmigrated),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrations_are_ordered_and_end_at_current() {
+        let mut prev = 0;
+        for m in MIGRATIONS {
+            assert!(m.to > prev, "migration to v{} is out of order", m.to);
+            prev = m.to;
+        }
+        assert_eq!(prev, CURRENT_VERSION);
+    }
+
+    #[test]
+    fn missing_or_null_version_is_zero() {
+        assert_eq!(document_version(&json!({})), 0);
+        assert_eq!(document_version(&json!({ "version": null })), 0);
+        assert_eq!(document_version(&json!({ "version": 1 })), 1);
+        // Too large for u32: newer than any build, not version 0.
+        assert_eq!(
+            document_version(&json!({ "version": 1u64 << 32 })),
+            u32::MAX
+        );
+    }
+
+    #[test]
+    fn v1_rewrites_s_to_l() {
+        let mut doc = json!({ "hotkeys": { "snap_position": { "key": "s" } } });
+        v1_snap_key_s_to_l(&mut doc);
+        assert_eq!(doc["hotkeys"]["snap_position"]["key"], "L");
+    }
+
+    #[test]
+    fn v1_keeps_custom_snap_key() {
+        let mut doc = json!({ "hotkeys": { "snap_position": { "key": "P" } } });
+        v1_snap_key_s_to_l(&mut doc);
+        assert_eq!(doc["hotkeys"]["snap_position"]["key"], "P");
+    }
+
+    #[test]
+    fn v1_tolerates_missing_snap_position() {
+        let mut doc = json!({ "hotkeys": {} });
+        v1_snap_key_s_to_l(&mut doc);
+        assert_eq!(doc, json!({ "hotkeys": {} }));
+    }
+
+    #[test]
+    fn v2_normalizes_legacy_and_braced_keys() {
+        let mut doc = json!({ "desktops": {
+            "Desktop(Guid(D178F97B-2525-4ED7-B219-6BA2AA6BE296))": { "title": "A" },
+            "{0a1b2c3d-0000-1111-2222-333344445555}": { "title": "B" },
+            "default": { "title": "C" },
+            "garbage": { "title": "D" }
+        }});
+        v2_canonical_desktop_keys(&mut doc);
+        assert_eq!(
+            doc["desktops"],
+            json!({
+                "D178F97B-2525-4ED7-B219-6BA2AA6BE296": { "title": "A" },
+                "0A1B2C3D-0000-1111-2222-333344445555": { "title": "B" },
+                "00000000-0000-0000-0000-000000000000": { "title": "C" }
+            })
+        );
+        assert_eq!(
+            doc[UNMIGRATED_DESKTOPS],
+            json!({ "garbage": { "title": "D" } })
+        );
+    }
+
+    #[test]
+    fn v2_keeps_first_of_duplicate_keys() {
+        let mut doc = json!({ "desktops": {
+            "D178F97B-2525-4ED7-B219-6BA2AA6BE296": { "title": "canonical" },
+            "Desktop(Guid(D178F97B-2525-4ED7-B219-6BA2AA6BE296))": { "title": "legacy" }
+        }});
+        v2_canonical_desktop_keys(&mut doc);
+        let desktops = doc["desktops"].as_object().unwrap();
+        assert_eq!(desktops.len(), 1);
+        let kept = doc[UNMIGRATED_DESKTOPS].as_object().unwrap();
+        assert_eq!(kept.len(), 1);
+        let (key, label) = kept.iter().next().unwrap();
+        let winner = &desktops["D178F97B-2525-4ED7-B219-6BA2AA6BE296"];
+        assert_ne!(label, winner);
+        assert!(key.contains("D178F97B"));
+    }
+
+    #[test]
+    fn migrate_records_version() {
+        let mut doc = json!({ "version": null });
+        assert!(migrate(&mut doc));
+        assert_eq!(doc["version"], CURRENT_VERSION);
+    }
+
+    #[test]
+    fn migrate_is_noop_when_current() {
+        let mut doc =
+            json!({ "version": CURRENT_VERSION, "hotkeys": { "snap_position": { "key": "S" } } });
+        let before = doc.clone();
+        assert!(!migrate(&mut doc));
+        assert_eq!(doc, before);
+    }
+
+    #[test]
+    fn migrate_leaves_newer_documents_alone() {
+        let mut doc = json!({ "version": CURRENT_VERSION + 1 });
+        let before = doc.clone();
+        assert!(!migrate(&mut doc));
+        assert_eq!(doc, before);
+    }
+
+    #[test]
+    fn migrate_ignores_non_objects() {
+        let mut doc = json!([1, 2, 3]);
+        assert!(!migrate(&mut doc));
+    }
+}
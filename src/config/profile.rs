@@ -0,0 +1,366 @@
+//! Named label profiles.
+//!
+//! The top-level `desktops` map is the default profile. Each entry in
+//! `profiles` holds its own desktop labels and may override parts of the
+//! appearance; `active_profile` picks the one in use.
+
+use super::{
+    Appearance, Config, DesktopId, DesktopLabel, OverlayLayout, OverlayPosition, TextAlign,
+    TextDirection, ThemePreset, schema,
+};
+use crate::i18n::tr;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::ops::Bound;
+use thiserror::Error;
+
+/// Name of the top-level labels in logs and on the pipe; users see it
+/// translated (see `display_name`).
+pub const DEFAULT_PROFILE: &str = "Default";
+
+/// How the profile `name` is shown to the user, `None` being the top-level
+/// labels.
+pub fn display_name(name: Option<&str>) -> &str {
+    name.unwrap_or_else(|| tr("menu.default_profile"))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(default)]
+pub struct Profile {
+    /// Labels per virtual desktop while this profile is active.
+    #[serde(default)]
+    #[schemars(schema_with = "schema::desktops")]
+    pub desktops: HashMap<DesktopId, DesktopLabel>,
+    /// Appearance settings that differ from the top-level ones.
+    #[serde(default, skip_serializing_if = "AppearanceOverrides::is_empty")]
+    pub appearance: AppearanceOverrides,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A partial `Appearance`; unset fields keep the top-level value.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AppearanceOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 1))]
+    pub font_size_dip: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0))]
+    pub margin_px: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hide_on_fullscreen: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_template: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<OverlayLayout>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<ThemePreset>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0, max = 20))]
+    pub border_px: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0, max = 100))]
+    pub opacity_percent: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<OverlayPosition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_hints: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<TextDirection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub align: Option<TextAlign>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("no profile named {0:?}")]
+pub struct UnknownProfile(pub String);
+
+impl AppearanceOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `base` with these overrides applied.
+    pub fn apply(&self, base: &Appearance) -> Appearance {
+        let mut out = base.clone();
+        if let Some(v) = &self.font_family {
+            out.font_family = v.clone();
+        }
+        if let Some(v) = self.font_size_dip {
+            out.font_size_dip = v;
+        }
+        if let Some(v) = self.margin_px {
+            out.margin_px = v;
+        }
+        if let Some(v) = self.hide_on_fullscreen {
+            out.hide_on_fullscreen = v;
+        }
+        if let Some(v) = &self.line_template {
+            out.line_template = v.clone();
+        }
+        if let Some(v) = self.layout {
+            out.layout = v;
+        }
+        if let Some(v) = self.theme {
+            out.theme = v;
+        }
+        for (over, field) in [
+            (&self.foreground, &mut out.foreground),
+            (&self.background, &mut out.background),
+            (&self.border_color, &mut out.border_color),
+            (&self.accent, &mut out.accent),
+        ] {
+            if over.is_some() {
+                field.clone_from(over);
+            }
+        }
+        if self.border_px.is_some() {
+            out.border_px = self.border_px;
+        }
+        if let Some(v) = self.opacity_percent {
+            out.opacity_percent = v;
+        }
+        if self.position.is_some() {
+            out.position = self.position;
+        }
+        if let Some(v) = self.show_hints {
+            out.show_hints = v;
+        }
+        if let Some(v) = self.direction {
+            out.direction = v;
+        }
+        if let Some(v) = self.align {
+            out.align = v;
+        }
+        out
+    }
+}
+
+impl Config {
+    pub(super) fn active(&self) -> Option<(&String, &Profile)> {
+        self.profiles.get_key_value(self.active_profile.as_ref()?)
+    }
+
+    /// Labels of the active profile.
+    pub fn labels(&self) -> &HashMap<DesktopId, DesktopLabel> {
+        self.active().map_or(&self.desktops, |(_, p)| &p.desktops)
+    }
+
+    /// Mutable labels of the active profile.
+    pub fn labels_mut(&mut self) -> &mut HashMap<DesktopId, DesktopLabel> {
+        let active = self.active_profile.as_ref();
+        match active.and_then(|name| self.profiles.get_mut(name)) {
+            Some(p) => &mut p.desktops,
+            None => &mut self.desktops,
+        }
+    }
+
+    /// Appearance with the active profile's overrides applied.
+    pub fn effective_appearance(&self) -> Appearance {
+        match self.active() {
+            Some((_, p)) => p.appearance.apply(&self.appearance),
+            None => self.appearance.clone(),
+        }
+    }
+
+    /// Appearance for `desktop`: the effective one with the desktop's own
+    /// overrides applied.
+    pub fn appearance_for(&self, desktop: DesktopId) -> Appearance {
+        let base = self.effective_appearance();
+        match self.labels().get(&desktop) {
+            Some(label) => label.appearance.apply(&base),
+            None => base,
+        }
+    }
+
+    /// The overlay line template for `desktop`: its own, else the profile's
+    /// or the top-level one.
+    pub fn line_template(&self, desktop: DesktopId) -> String {
+        match self.labels().get(&desktop).and_then(|l| l.template.clone()) {
+            Some(t) => t,
+            None => self.appearance_for(desktop).line_template,
+        }
+    }
+
+    /// Name of the active profile, `DEFAULT_PROFILE` for the top-level labels.
+    pub fn active_profile_name(&self) -> &str {
+        self.active().map_or(DEFAULT_PROFILE, |(name, _)| name)
+    }
+
+    /// `display_name` of the active profile.
+    pub fn active_profile_display_name(&self) -> &str {
+        display_name(self.active().map(|(name, _)| name.as_str()))
+    }
+
+    /// Switch to the named profile, or to the top-level labels for `None`.
+    pub fn set_active_profile(&mut self, name: Option<&str>) -> Result<(), UnknownProfile> {
+        match name {
+            Some(n) if !self.profiles.contains_key(n) => Err(UnknownProfile(n.to_string())),
+            _ => {
+                self.active_profile = name.map(str::to_string);
+                Ok(())
+            }
+        }
+    }
+
+    /// Switch to the next profile in name order, wrapping around through the
+    /// top-level labels. Returns the new active profile.
+    pub fn cycle_profile(&mut self) -> Option<&str> {
+        let next = match self.active() {
+            None => self.profiles.keys().next(),
+            Some((cur, _)) => self
+                .profiles
+                .range::<String, _>((Bound::Excluded(cur), Bound::Unbounded))
+                .next()
+                .map(|(name, _)| name),
+        };
+        self.active_profile = next.cloned();
+        self.active_profile.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: DesktopId = DesktopId::from_u128(7);
+
+    fn with_profiles(names: &[&str]) -> Config {
+        let mut cfg = Config::default();
+        cfg.desktops.insert(
+            ID,
+            DesktopLabel {
+                title: "Base".into(),
+                ..Default::default()
+            },
+        );
+        for name in names {
+            let mut p = Profile::default();
+            p.desktops.insert(
+                ID,
+                DesktopLabel {
+                    title: name.to_string(),
+                    ..Default::default()
+                },
+            );
+            cfg.profiles.insert(name.to_string(), p);
+        }
+        cfg
+    }
+
+    #[test]
+    fn labels_follow_the_active_profile() {
+        let mut cfg = with_profiles(&["Home", "On-call"]);
+        assert_eq!(cfg.labels()[&ID].title, "Base");
+        cfg.set_active_profile(Some("On-call")).unwrap();
+        assert_eq!(cfg.labels()[&ID].title, "On-call");
+        assert_eq!(cfg.active_profile_name(), "On-call");
+
+        cfg.labels_mut().get_mut(&ID).unwrap().title = "Pager".into();
+        assert_eq!(cfg.profiles["On-call"].desktops[&ID].title, "Pager");
+        assert_eq!(cfg.desktops[&ID].title, "Base");
+
+        cfg.set_active_profile(None).unwrap();
+        assert_eq!(cfg.labels()[&ID].title, "Base");
+        assert_eq!(cfg.active_profile_name(), DEFAULT_PROFILE);
+    }
+
+    #[test]
+    fn unknown_profiles_are_rejected() {
+        let mut cfg = with_profiles(&["Home"]);
+        assert_eq!(
+            cfg.set_active_profile(Some("Work")),
+            Err(UnknownProfile("Work".into()))
+        );
+        assert_eq!(cfg.active_profile, None);
+
+        // A dangling name from the file falls back to the top-level labels.
+        cfg.active_profile = Some("Gone".into());
+        assert_eq!(cfg.labels()[&ID].title, "Base");
+        assert_eq!(cfg.active_profile_name(), DEFAULT_PROFILE);
+    }
+
+    #[test]
+    fn line_template_prefers_the_desktop_then_the_profile() {
+        let mut cfg = with_profiles(&["Home"]);
+        let other = DesktopId::from_u128(8);
+        assert_eq!(cfg.line_template(ID), crate::template::DEFAULT_TEMPLATE);
+        cfg.profiles
+            .get_mut("Home")
+            .unwrap()
+            .appearance
+            .line_template = Some("{index}".into());
+        cfg.set_active_profile(Some("Home")).unwrap();
+        assert_eq!(cfg.line_template(other), "{index}");
+        cfg.labels_mut().get_mut(&ID).unwrap().template = Some("{time}".into());
+        assert_eq!(cfg.line_template(ID), "{time}");
+        assert_eq!(cfg.line_template(other), "{index}");
+    }
+
+    #[test]
+    fn desktop_appearance_goes_over_the_profile() {
+        let mut cfg = with_profiles(&["Home"]);
+        let other = DesktopId::from_u128(8);
+        cfg.profiles
+            .get_mut("Home")
+            .unwrap()
+            .appearance
+            .font_size_dip = Some(24);
+        cfg.set_active_profile(Some("Home")).unwrap();
+        let label = cfg.labels_mut().get_mut(&ID).unwrap();
+        label.appearance.accent = Some("red".into());
+        label.appearance.position = Some(OverlayPosition::Right);
+        label.appearance.font_family = Some("Consolas".into());
+
+        let a = cfg.appearance_for(ID);
+        assert_eq!((a.font_family.as_str(), a.font_size_dip), ("Consolas", 24));
+        assert_eq!(a.position, Some(OverlayPosition::Right));
+        let theme = a.theme_colors();
+        assert_eq!(theme.border.to_string(), "#ff0000ff");
+        assert_eq!(theme.border_px, 3);
+
+        let a = cfg.appearance_for(other);
+        assert_eq!((a.font_family.as_str(), a.position), ("Segoe UI", None));
+        assert_eq!(a.theme_colors().border_px, 0);
+    }
+
+    #[test]
+    fn cycling_visits_every_profile_then_the_default() {
+        let mut cfg = with_profiles(&["B", "A"]);
+        assert_eq!(cfg.cycle_profile(), Some("A"));
+        assert_eq!(cfg.cycle_profile(), Some("B"));
+        assert_eq!(cfg.cycle_profile(), None);
+        assert_eq!(cfg.cycle_profile(), Some("A"));
+
+        let mut none = Config::default();
+        assert_eq!(none.cycle_profile(), None);
+    }
+
+    #[test]
+    fn overrides_apply_over_top_level_appearance() {
+        let mut cfg = with_profiles(&["Wall"]);
+        cfg.profiles.get_mut("Wall").unwrap().appearance = AppearanceOverrides {
+            font_size_dip: Some(32),
+            ..Default::default()
+        };
+        assert_eq!(cfg.effective_appearance().font_size_dip, 16);
+        cfg.set_active_profile(Some("Wall")).unwrap();
+        let a = cfg.effective_appearance();
+        assert_eq!(a.font_size_dip, 32);
+        assert_eq!(a.font_family, "Segoe UI");
+    }
+}
@@ -0,0 +1,176 @@
+//! JSON Schema for `labels.json`, generated from the config types.
+//!
+//! A copy is checked in as `schema/labels.schema.json` and installed next to
+//! the executable. The app also keeps a copy next to `labels.json` and points
+//! the file's `$schema` at it, so editors opened via "Open Config" offer
+//! completion and validation.
+
+use super::identity::DesktopIdentity;
+use super::{Config, DesktopLabel, Paths};
+use anyhow::{Context, Result};
+use schemars::r#gen::SchemaGenerator;
+use schemars::schema::{
+    InstanceType, ObjectValidation, RootSchema, Schema, SchemaObject, SingleOrVec,
+};
+use schemars::schema_for;
+use std::fs;
+
+pub const SCHEMA_FILE: &str = "labels.schema.json";
+/// Value written to `$schema`, relative to `labels.json`.
+pub const SCHEMA_REF: &str = "./labels.schema.json";
+
+const GUID_PATTERN: &str =
+    r"^\{?[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12}\}?$";
+
+pub fn schema() -> RootSchema {
+    let mut root = schema_for!(Config);
+    // A chord's fields left out of the file come from the default chord of
+    // that hotkey (see `layers`), so none of them is required.
+    if let Some(Schema::Object(chord)) = root.definitions.get_mut("KeyChord")
+        && let Some(object) = chord.object.as_mut()
+    {
+        object.required.clear();
+    }
+    root
+}
+
+/// The schema as pretty-printed JSON with a trailing newline.
+pub fn schema_json() -> String {
+    let mut s = serde_json::to_string_pretty(&schema()).expect("serialize schema");
+    s.push('\n');
+    s
+}
+
+/// Write the schema next to the config file unless an identical copy is
+/// already there.
+pub fn write_schema(paths: &Paths) -> Result<()> {
+    let target = paths.cfg_dir.join(SCHEMA_FILE);
+    let text = schema_json();
+    if fs::read_to_string(&target).is_ok_and(|t| t == text) {
+        return Ok(());
+    }
+    fs::write(&target, text).context("write schema")
+}
+
+/// What a setting holds, as far as an override given as text cares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SettingKind {
+    /// Text, including names like `left`: the override is used as is.
+    Text,
+    /// A number, flag or object: the override is read as JSON.
+    Other,
+}
+
+/// The kind of the setting at `path`, following the named fields of the
+/// config types; `None` if there is no such setting. Unlike the serialized
+/// defaults this knows optional settings that are unset by default.
+pub(crate) fn setting_kind(path: &[String]) -> Option<SettingKind> {
+    let root = schema();
+    let mut cur = &root.schema;
+    for key in path {
+        cur = property(&root, cur, key)?;
+    }
+    Some(if is_text(&root, cur) {
+        SettingKind::Text
+    } else {
+        SettingKind::Other
+    })
+}
+
+/// `s` itself, or the definition it refers to.
+fn resolve<'a>(root: &'a RootSchema, s: &'a SchemaObject) -> &'a SchemaObject {
+    let name = s
+        .reference
+        .as_deref()
+        .and_then(|r| r.strip_prefix("#/definitions/"));
+    match name.and_then(|n| root.definitions.get(n)) {
+        Some(Schema::Object(def)) => def,
+        _ => s,
+    }
+}
+
+/// `s` and the schemas it combines with `allOf`, `anyOf` or `oneOf`.
+fn alternatives<'a>(root: &'a RootSchema, s: &'a SchemaObject) -> Vec<&'a SchemaObject> {
+    let s = resolve(root, s);
+    let mut out = vec![s];
+    if let Some(sub) = &s.subschemas {
+        for list in [&sub.all_of, &sub.any_of, &sub.one_of]
+            .into_iter()
+            .flatten()
+        {
+            for schema in list {
+                if let Schema::Object(o) = schema {
+                    out.extend(alternatives(root, o));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn property<'a>(root: &'a RootSchema, s: &'a SchemaObject, key: &str) -> Option<&'a SchemaObject> {
+    alternatives(root, s).into_iter().find_map(|o| {
+        match o.object.as_ref()?.properties.get(key)? {
+            Schema::Object(p) => Some(p),
+            Schema::Bool(_) => None,
+        }
+    })
+}
+
+fn is_text(root: &RootSchema, s: &SchemaObject) -> bool {
+    alternatives(root, s).into_iter().any(|o| {
+        let typed = match &o.instance_type {
+            Some(SingleOrVec::Single(t)) => **t == InstanceType::String,
+            Some(SingleOrVec::Vec(ts)) => ts.contains(&InstanceType::String),
+            None => false,
+        };
+        typed
+            || o.enum_values
+                .as_ref()
+                .is_some_and(|vs| vs.iter().any(serde_json::Value::is_string))
+    })
+}
+
+/// `desktops`: an object keyed by desktop GUID.
+pub(crate) fn desktops(r#gen: &mut SchemaGenerator) -> Schema {
+    guid_map(r#gen.subschema_for::<DesktopLabel>())
+}
+
+/// `archived.orphaned`: desktop GUID to a Unix time.
+pub(crate) fn desktop_times(r#gen: &mut SchemaGenerator) -> Schema {
+    guid_map(r#gen.subschema_for::<u64>())
+}
+
+/// `identities`: desktop GUID to where it was last seen.
+pub(crate) fn identities(r#gen: &mut SchemaGenerator) -> Schema {
+    guid_map(r#gen.subschema_for::<DesktopIdentity>())
+}
+
+/// A desktop GUID string.
+pub(crate) fn guid(_: &mut SchemaGenerator) -> Schema {
+    guid_string().into()
+}
+
+fn guid_string() -> SchemaObject {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        string: Some(Box::new(schemars::schema::StringValidation {
+            pattern: Some(GUID_PATTERN.to_string()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
+
+fn guid_map(values: Schema) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        object: Some(Box::new(ObjectValidation {
+            additional_properties: Some(Box::new(values)),
+            property_names: Some(Box::new(guid_string().into())),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
@@ -0,0 +1,276 @@
+//! Minimal scanner over the config text used to map a JSON path back to the
+//! line and column where its value starts. TOML text is handed to
+//! `format::locate_toml`.
+
+/// One-based line and column (in characters) within the config text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// Location of the byte offset `offset` within `src`.
+    pub fn of_offset(src: &str, offset: usize) -> Self {
+        let before = &src[..offset.min(src.len())];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let column = before[line_start..].chars().count() + 1;
+        Self { line, column }
+    }
+}
+
+/// Find where the value at a dotted `path` (e.g. `hotkeys.edit_title.key`)
+/// starts in `src`. Array elements are addressed by index (`items.0` or
+/// `items[0]`). Returns `None` if the text is malformed or the path is absent.
+///
+/// TOML text is recognised by not starting with `{`, which a TOML document
+/// cannot.
+pub fn locate(src: &str, path: &str) -> Option<Location> {
+    let segments: Vec<String> = path
+        .replace('[', ".")
+        .replace(']', "")
+        .split('.')
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    if !src.trim_start().starts_with('{') {
+        return super::format::locate_toml(src, &segments);
+    }
+    let mut scanner = Scanner {
+        src: src.as_bytes(),
+        pos: 0,
+    };
+    let offset = scanner.find(&segments)?;
+    Some(Location::of_offset(src, offset))
+}
+
+struct Scanner<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\r' | b'\n')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        self.skip_ws();
+        (self.peek()? == b).then(|| self.pos += 1)
+    }
+
+    fn find(&mut self, path: &[String]) -> Option<usize> {
+        self.skip_ws();
+        let Some((head, rest)) = path.split_first() else {
+            return Some(self.pos);
+        };
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+                loop {
+                    self.skip_ws();
+                    if self.peek()? == b'}' {
+                        return None;
+                    }
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    if &key == head {
+                        return self.find(rest);
+                    }
+                    self.skip_value()?;
+                    self.skip_ws();
+                    match self.peek()? {
+                        b',' => self.pos += 1,
+                        _ => return None,
+                    }
+                }
+            }
+            b'[' => {
+                let index: usize = head.parse().ok()?;
+                self.pos += 1;
+                for i in 0.. {
+                    self.skip_ws();
+                    if self.peek()? == b']' {
+                        return None;
+                    }
+                    if i == index {
+                        return self.find(rest);
+                    }
+                    self.skip_value()?;
+                    self.skip_ws();
+                    match self.peek()? {
+                        b',' => self.pos += 1,
+                        _ => return None,
+                    }
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        self.skip_ws();
+        match self.peek()? {
+            b'"' => self.string().map(|_| ()),
+            open @ (b'{' | b'[') => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.pos += 1;
+                loop {
+                    self.skip_ws();
+                    if self.peek()? == close {
+                        self.pos += 1;
+                        return Some(());
+                    }
+                    if open == b'{' {
+                        self.string()?;
+                        self.expect(b':')?;
+                    }
+                    self.skip_value()?;
+                    self.skip_ws();
+                    match self.peek()? {
+                        b',' => self.pos += 1,
+                        c if c == close => {}
+                        _ => return None,
+                    }
+                }
+            }
+            _ => {
+                // Number, true, false or null.
+                let start = self.pos;
+                while self.peek().is_some_and(|c| {
+                    !matches!(c, b',' | b'}' | b']' | b' ' | b'\t' | b'\r' | b'\n')
+                }) {
+                    self.pos += 1;
+                }
+                (self.pos > start).then_some(())
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.skip_ws();
+        if self.peek()? != b'"' {
+            return None;
+        }
+        self.pos += 1;
+        let mut out = Vec::new();
+        loop {
+            let c = self.peek()?;
+            self.pos += 1;
+            match c {
+                b'"' => return String::from_utf8(out).ok(),
+                b'\\' => {
+                    let esc = self.peek()?;
+                    self.pos += 1;
+                    let decoded = match esc {
+                        b'n' => '\n',
+                        b't' => '\t',
+                        b'r' => '\r',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => {
+                            let hex = self.src.get(self.pos..self.pos + 4)?;
+                            self.pos += 4;
+                            let code =
+                                u32::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?;
+                            char::from_u32(code).unwrap_or('\u{fffd}')
+                        }
+                        other => other as char,
+                    };
+                    let mut buf = [0u8; 4];
+                    out.extend_from_slice(decoded.encode_utf8(&mut buf).as_bytes());
+                }
+                _ => out.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"{
+  "desktops": {},
+  "hotkeys": {
+    "edit_title": { "ctrl": true, "key": "" },
+    "snap_position": { "key": "L" }
+  },
+  "list": [1, {"a": [true, null]}, "x"],
+  "appearance": { "font_size_dip": 0 }
+}"#;
+
+    #[test]
+    fn locates_nested_values() {
+        assert_eq!(
+            locate(DOC, "hotkeys.edit_title.key"),
+            Some(Location {
+                line: 4,
+                column: 42
+            })
+        );
+        assert_eq!(
+            locate(DOC, "appearance.font_size_dip"),
+            Some(Location {
+                line: 8,
+                column: 36
+            })
+        );
+    }
+
+    #[test]
+    fn locates_array_elements() {
+        assert_eq!(
+            locate(DOC, "list[2]"),
+            Some(Location {
+                line: 7,
+                column: 36
+            })
+        );
+        assert_eq!(
+            locate(DOC, "list.1.a.1"),
+            Some(Location {
+                line: 7,
+                column: 28
+            })
+        );
+    }
+
+    #[test]
+    fn missing_path_is_none() {
+        assert_eq!(locate(DOC, "hotkeys.toggle_overlay.key"), None);
+        assert_eq!(locate(DOC, "list[9]"), None);
+    }
+
+    #[test]
+    fn malformed_text_is_none() {
+        assert_eq!(locate("{\"a\": ", "a.b"), None);
+        assert_eq!(locate("", "a"), None);
+    }
+
+    #[test]
+    fn escaped_keys_match() {
+        let src = r#"{"a\"b": 1, "c": 2}"#;
+        assert_eq!(
+            locate(src, "c"),
+            Some(Location {
+                line: 1,
+                column: 18
+            })
+        );
+    }
+
+    #[test]
+    fn offset_columns_count_characters() {
+        let src = "{\"é\": 1}";
+        assert_eq!(Location::of_offset(src, src.find('1').unwrap()).column, 7);
+    }
+}
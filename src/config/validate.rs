@@ -0,0 +1,836 @@
+//! Semantic checks for a loaded `Config`.
+//!
+//! Problems are reported with the JSON path of the offending value and, when
+//! the config text is available, its line and column.
+
+use super::layers::Layer;
+use super::source::{Location, locate};
+use super::{Appearance, AppearanceOverrides, Config, DesktopLabel, KeyChord};
+use crate::color::Rgba;
+use crate::i18n::{tr, tr_with};
+use crate::template::{LocalTime, Template};
+use std::fmt;
+use std::path::Path;
+
+/// A problem with the config. Displayed in the current language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The file exists but could not be read: locked, no permission, or not
+    /// UTF-8 text.
+    Unreadable { message: String },
+    /// A machine default or environment override the app cannot use; it is
+    /// ignored and the user's file is kept.
+    Override {
+        layer: Layer,
+        path: String,
+        message: String,
+    },
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    Type {
+        path: String,
+        location: Option<Location>,
+        message: String,
+    },
+    EmptyHotkeyKey {
+        path: String,
+        location: Option<Location>,
+    },
+    UnknownKeyName {
+        path: String,
+        location: Option<Location>,
+        key: String,
+    },
+    ZeroFontSize {
+        path: String,
+        location: Option<Location>,
+    },
+    NegativeMargin {
+        path: String,
+        location: Option<Location>,
+        value: i32,
+    },
+    OutOfRange {
+        path: String,
+        location: Option<Location>,
+        value: u32,
+        min: u32,
+        max: u32,
+    },
+    UnknownProfile {
+        path: String,
+        location: Option<Location>,
+        name: String,
+    },
+    BadTemplate {
+        path: String,
+        location: Option<Location>,
+        message: String,
+    },
+    BadDeadline {
+        path: String,
+        location: Option<Location>,
+        value: String,
+    },
+    BadColor {
+        path: String,
+        location: Option<Location>,
+        error: crate::color::BadColor,
+    },
+}
+
+fn at(location: &Option<Location>) -> String {
+    match location {
+        Some(l) => tr_with(
+            "config.at",
+            &[
+                ("line", &l.line.to_string()),
+                ("column", &l.column.to_string()),
+            ],
+        ),
+        None => String::new(),
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Values from the file go last, so a `{name}` in them is left alone.
+        let text = match self {
+            ConfigError::Unreadable { message } => {
+                tr_with("config.unreadable", &[("message", message)])
+            }
+            ConfigError::Override {
+                layer,
+                path,
+                message,
+            } => tr_with(
+                "config.override",
+                &[
+                    ("layer", tr(layer.message_key())),
+                    ("path", path),
+                    ("message", message),
+                ],
+            ),
+            ConfigError::Syntax {
+                line,
+                column,
+                message,
+            } => tr_with(
+                "config.syntax",
+                &[
+                    ("line", &line.to_string()),
+                    ("column", &column.to_string()),
+                    ("message", message),
+                ],
+            ),
+            ConfigError::Type {
+                path,
+                location,
+                message,
+            } => tr_with(
+                "config.type",
+                &[("at", &at(location)), ("path", path), ("message", message)],
+            ),
+            ConfigError::EmptyHotkeyKey { path, location } => tr_with(
+                "config.empty_hotkey_key",
+                &[("at", &at(location)), ("path", path)],
+            ),
+            ConfigError::UnknownKeyName {
+                path,
+                location,
+                key,
+            } => tr_with(
+                "config.unknown_key_name",
+                &[
+                    ("at", &at(location)),
+                    ("path", path),
+                    ("key", &format!("{key:?}")),
+                ],
+            ),
+            ConfigError::ZeroFontSize { path, location } => tr_with(
+                "config.zero_font_size",
+                &[("at", &at(location)), ("path", path)],
+            ),
+            ConfigError::NegativeMargin {
+                path,
+                location,
+                value,
+            } => tr_with(
+                "config.negative_margin",
+                &[
+                    ("at", &at(location)),
+                    ("value", &value.to_string()),
+                    ("path", path),
+                ],
+            ),
+            ConfigError::OutOfRange {
+                path,
+                location,
+                value,
+                min,
+                max,
+            } => tr_with(
+                "config.out_of_range",
+                &[
+                    ("at", &at(location)),
+                    ("value", &value.to_string()),
+                    ("min", &min.to_string()),
+                    ("max", &max.to_string()),
+                    ("path", path),
+                ],
+            ),
+            ConfigError::UnknownProfile {
+                path,
+                location,
+                name,
+            } => tr_with(
+                "config.unknown_profile",
+                &[
+                    ("at", &at(location)),
+                    ("path", path),
+                    ("name", &format!("{name:?}")),
+                ],
+            ),
+            ConfigError::BadTemplate {
+                path,
+                location,
+                message,
+            } => tr_with(
+                "config.bad_template",
+                &[("at", &at(location)), ("path", path), ("message", message)],
+            ),
+            ConfigError::BadDeadline {
+                path,
+                location,
+                value,
+            } => tr_with(
+                "config.bad_deadline",
+                &[
+                    ("at", &at(location)),
+                    ("path", path),
+                    ("value", &format!("{value:?}")),
+                ],
+            ),
+            ConfigError::BadColor {
+                path,
+                location,
+                error,
+            } => tr_with(
+                "config.bad_color",
+                &[
+                    ("at", &at(location)),
+                    ("path", path),
+                    ("value", &format!("{:?}", error.0)),
+                ],
+            ),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl ConfigError {
+    /// Dotted JSON path of the offending value; empty for syntax errors.
+    pub fn path(&self) -> &str {
+        match self {
+            ConfigError::Unreadable { .. } | ConfigError::Syntax { .. } => "",
+            ConfigError::Override { path, .. }
+            | ConfigError::Type { path, .. }
+            | ConfigError::EmptyHotkeyKey { path, .. }
+            | ConfigError::UnknownKeyName { path, .. }
+            | ConfigError::ZeroFontSize { path, .. }
+            | ConfigError::NegativeMargin { path, .. }
+            | ConfigError::OutOfRange { path, .. }
+            | ConfigError::UnknownProfile { path, .. }
+            | ConfigError::BadTemplate { path, .. }
+            | ConfigError::BadDeadline { path, .. }
+            | ConfigError::BadColor { path, .. } => path,
+        }
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            ConfigError::Unreadable { .. } | ConfigError::Override { .. } => None,
+            ConfigError::Syntax { line, column, .. } => Some(Location {
+                line: *line,
+                column: *column,
+            }),
+            ConfigError::Type { location, .. }
+            | ConfigError::EmptyHotkeyKey { location, .. }
+            | ConfigError::UnknownKeyName { location, .. }
+            | ConfigError::ZeroFontSize { location, .. }
+            | ConfigError::NegativeMargin { location, .. }
+            | ConfigError::OutOfRange { location, .. }
+            | ConfigError::UnknownProfile { location, .. }
+            | ConfigError::BadTemplate { location, .. }
+            | ConfigError::BadDeadline { location, .. }
+            | ConfigError::BadColor { location, .. } => *location,
+        }
+    }
+
+    /// Whether the file could not be read as a `Config` at all, as opposed to
+    /// containing individual values that were replaced by defaults.
+    pub fn is_unreadable(&self) -> bool {
+        matches!(
+            self,
+            ConfigError::Unreadable { .. } | ConfigError::Syntax { .. } | ConfigError::Type { .. }
+        )
+    }
+
+    pub(crate) fn from_json(e: &serde_json::Error) -> Self {
+        ConfigError::Syntax {
+            line: e.line(),
+            column: e.column(),
+            message: strip_position(&e.to_string()),
+        }
+    }
+
+    pub(crate) fn from_typed(
+        e: serde_path_to_error::Error<serde_json::Error>,
+        src: Option<&str>,
+    ) -> Self {
+        let path = e.path().to_string();
+        let location = src.and_then(|s| locate(s, &path));
+        ConfigError::Type {
+            location,
+            message: strip_position(&e.into_inner().to_string()),
+            path,
+        }
+    }
+}
+
+/// One-line summary of the problems of the config file `file`, suitable for
+/// a tray balloon, or `None` if there is nothing to report.
+pub fn summarize(file: &Path, problems: &[ConfigError]) -> Option<String> {
+    let first = problems.first()?;
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let first = first.to_string();
+    Some(match problems.len() {
+        1 => tr_with("config.summary", &[("file", &name), ("problem", &first)]),
+        n => tr_with(
+            "config.summary_more",
+            &[
+                ("more", &(n - 1).to_string()),
+                ("file", &name),
+                ("problem", &first),
+            ],
+        ),
+    })
+}
+
+/// serde_json appends " at line X column Y"; we report positions separately.
+fn strip_position(msg: &str) -> String {
+    match msg.rfind(" at line ") {
+        Some(i) => msg[..i].to_string(),
+        None => msg.to_string(),
+    }
+}
+
+/// Keys the hotkey registration can map to a virtual-key code.
+pub fn is_supported_key(key: &str) -> bool {
+    let mut chars = key.trim().chars();
+    matches!((chars.next(), chars.next()), (Some(c), None) if c.is_ascii_alphanumeric())
+}
+
+fn chords(cfg: &Config) -> [(&'static str, &KeyChord); 7] {
+    let hk = &cfg.hotkeys;
+    [
+        ("edit_title", &hk.edit_title),
+        ("edit_description", &hk.edit_description),
+        ("toggle_overlay", &hk.toggle_overlay),
+        ("snap_position", &hk.snap_position),
+        ("cycle_profile", &hk.cycle_profile),
+        ("undo_label", &hk.undo_label),
+        ("redo_label", &hk.redo_label),
+    ]
+}
+
+/// Every desktop label in `cfg` with its JSON path.
+fn labels(cfg: &Config) -> Vec<(String, &DesktopLabel)> {
+    let profiles = cfg
+        .profiles
+        .iter()
+        .map(|(name, p)| (format!("profiles.{name}.desktops"), &p.desktops));
+    std::iter::once(("desktops".to_string(), &cfg.desktops))
+        .chain(profiles)
+        .flat_map(|(prefix, desktops)| {
+            desktops
+                .iter()
+                .map(move |(id, l)| (format!("{prefix}.{id}"), l))
+        })
+        .collect()
+}
+
+/// Every appearance override in `cfg`, of profiles and of desktops, with its
+/// JSON path.
+fn overrides(cfg: &Config) -> Vec<(String, &AppearanceOverrides)> {
+    let profiles = cfg
+        .profiles
+        .iter()
+        .map(|(name, p)| (format!("profiles.{name}.appearance"), &p.appearance));
+    let desktops = labels(cfg)
+        .into_iter()
+        .map(|(path, l)| (format!("{path}.appearance"), &l.appearance));
+    profiles.chain(desktops).collect()
+}
+
+/// Every line template in `cfg` with its JSON path.
+fn templates(cfg: &Config) -> Vec<(String, &str)> {
+    let mut out = vec![(
+        "appearance.line_template".to_string(),
+        cfg.appearance.line_template.as_str(),
+    )];
+    for (prefix, o) in overrides(cfg) {
+        if let Some(t) = &o.line_template {
+            out.push((format!("{prefix}.line_template"), t));
+        }
+    }
+    for (path, label) in labels(cfg) {
+        if let Some(t) = &label.template {
+            out.push((format!("{path}.template"), t));
+        }
+    }
+    out
+}
+
+/// Bounded appearance values as (field, value, min, max); unset optional
+/// values are `None`.
+fn ranges(a: &Appearance) -> [(&'static str, Option<u32>, u32, u32); 7] {
+    [
+        (
+            "description_size_percent",
+            Some(a.description_size_percent),
+            1,
+            100,
+        ),
+        (
+            "description_opacity_percent",
+            Some(a.description_opacity_percent),
+            0,
+            100,
+        ),
+        ("max_lines", Some(a.max_lines), 1, 100),
+        ("opacity_percent", Some(a.opacity_percent), 0, 100),
+        ("border_px", a.border_px, 0, 20),
+        ("corner_radius_px", a.corner_radius_px, 0, 100),
+        ("padding_px", a.padding_px, 0, 200),
+    ]
+}
+
+/// Bounded override values, as in `ranges`.
+fn override_ranges(o: &AppearanceOverrides) -> [(&'static str, Option<u32>, u32, u32); 2] {
+    [
+        ("border_px", o.border_px, 0, 20),
+        ("opacity_percent", o.opacity_percent, 0, 100),
+    ]
+}
+
+const COLOR_FIELDS: [&str; 4] = ["foreground", "background", "border_color", "accent"];
+
+/// Every color in `cfg` with its JSON path.
+fn colors(cfg: &Config) -> Vec<(String, &str)> {
+    let a = &cfg.appearance;
+    let mut sets = vec![(
+        "appearance".to_string(),
+        [&a.foreground, &a.background, &a.border_color, &a.accent],
+    )];
+    for (prefix, o) in overrides(cfg) {
+        sets.push((
+            prefix,
+            [&o.foreground, &o.background, &o.border_color, &o.accent],
+        ));
+    }
+    sets.into_iter()
+        .flat_map(|(prefix, values)| {
+            COLOR_FIELDS
+                .iter()
+                .zip(values)
+                .filter_map(move |(field, v)| Some((format!("{prefix}.{field}"), v.as_deref()?)))
+        })
+        .collect()
+}
+
+fn is_bad_color(c: &str) -> bool {
+    c.parse::<Rgba>().is_err()
+}
+
+fn is_bad_template(t: &str) -> bool {
+    t.parse::<Template>().is_err()
+}
+
+fn is_bad_deadline(d: &str) -> bool {
+    d.parse::<LocalTime>().is_err()
+}
+
+/// Check `cfg` for values the app cannot use. `source` is the config text the
+/// values came from and is only used to attach line/column information.
+pub fn validate(cfg: &Config, source: Option<&str>) -> Vec<ConfigError> {
+    let loc = |path: &str| source.and_then(|s| locate(s, path));
+    let mut problems = Vec::new();
+    for (name, chord) in chords(cfg) {
+        let path = format!("hotkeys.{name}.key");
+        if chord.key.trim().is_empty() {
+            problems.push(ConfigError::EmptyHotkeyKey {
+                location: loc(&path),
+                path,
+            });
+        } else if !is_supported_key(&chord.key) {
+            problems.push(ConfigError::UnknownKeyName {
+                location: loc(&path),
+                path,
+                key: chord.key.clone(),
+            });
+        }
+    }
+    let appearances = std::iter::once((
+        "appearance".to_string(),
+        Some(cfg.appearance.font_size_dip),
+        Some(cfg.appearance.margin_px),
+    ))
+    .chain(
+        overrides(cfg)
+            .into_iter()
+            .map(|(prefix, o)| (prefix, o.font_size_dip, o.margin_px)),
+    );
+    for (prefix, font_size, margin) in appearances {
+        if font_size == Some(0) {
+            let path = format!("{prefix}.font_size_dip");
+            problems.push(ConfigError::ZeroFontSize {
+                location: loc(&path),
+                path,
+            });
+        }
+        if let Some(value) = margin.filter(|m| *m < 0) {
+            let path = format!("{prefix}.margin_px");
+            problems.push(ConfigError::NegativeMargin {
+                location: loc(&path),
+                path,
+                value,
+            });
+        }
+    }
+    let bounded = std::iter::once(("appearance".to_string(), ranges(&cfg.appearance).to_vec()))
+        .chain(
+            overrides(cfg)
+                .into_iter()
+                .map(|(prefix, o)| (prefix, override_ranges(o).to_vec())),
+        );
+    for (prefix, fields) in bounded {
+        for (field, value, min, max) in fields {
+            let Some(value) = value.filter(|v| !(min..=max).contains(v)) else {
+                continue;
+            };
+            let path = format!("{prefix}.{field}");
+            problems.push(ConfigError::OutOfRange {
+                location: loc(&path),
+                path,
+                value,
+                min,
+                max,
+            });
+        }
+    }
+    for (path, value) in colors(cfg) {
+        if let Err(error) = value.parse::<Rgba>() {
+            problems.push(ConfigError::BadColor {
+                location: loc(&path),
+                path,
+                error,
+            });
+        }
+    }
+    if let Some(name) = &cfg.active_profile
+        && !cfg.profiles.contains_key(name)
+    {
+        let path = "active_profile".to_string();
+        problems.push(ConfigError::UnknownProfile {
+            location: loc(&path),
+            path,
+            name: name.clone(),
+        });
+    }
+    for (path, template) in templates(cfg) {
+        if let Err(e) = template.parse::<Template>() {
+            problems.push(ConfigError::BadTemplate {
+                location: loc(&path),
+                path,
+                message: e.to_string(),
+            });
+        }
+    }
+    for (path, label) in labels(cfg) {
+        if let Some(value) = label.deadline.as_deref().filter(|d| is_bad_deadline(d)) {
+            let path = format!("{path}.deadline");
+            problems.push(ConfigError::BadDeadline {
+                location: loc(&path),
+                path,
+                value: value.to_string(),
+            });
+        }
+    }
+    problems
+}
+
+/// Reset every value `validate` would reject to its default so the app can
+/// keep running with the rest of the user's settings.
+pub(crate) fn sanitize(cfg: &mut Config) {
+    let defaults = Config::default();
+    let hk = &mut cfg.hotkeys;
+    for (chord, default) in [
+        (&mut hk.edit_title, &defaults.hotkeys.edit_title),
+        (&mut hk.edit_description, &defaults.hotkeys.edit_description),
+        (&mut hk.toggle_overlay, &defaults.hotkeys.toggle_overlay),
+        (&mut hk.snap_position, &defaults.hotkeys.snap_position),
+        (&mut hk.cycle_profile, &defaults.hotkeys.cycle_profile),
+        (&mut hk.undo_label, &defaults.hotkeys.undo_label),
+        (&mut hk.redo_label, &defaults.hotkeys.redo_label),
+    ] {
+        if !is_supported_key(&chord.key) {
+            *chord = default.clone();
+        }
+    }
+    if cfg.appearance.font_size_dip == 0 {
+        cfg.appearance.font_size_dip = defaults.appearance.font_size_dip;
+    }
+    if cfg.appearance.margin_px < 0 {
+        cfg.appearance.margin_px = defaults.appearance.margin_px;
+    }
+    let a = &mut cfg.appearance;
+    let d = &defaults.appearance;
+    if !(1..=100).contains(&a.description_size_percent) {
+        a.description_size_percent = d.description_size_percent;
+    }
+    if a.description_opacity_percent > 100 {
+        a.description_opacity_percent = d.description_opacity_percent;
+    }
+    if !(1..=100).contains(&a.max_lines) {
+        a.max_lines = d.max_lines;
+    }
+    if a.opacity_percent > 100 {
+        a.opacity_percent = d.opacity_percent;
+    }
+    a.border_px = a.border_px.filter(|v| *v <= 20);
+    a.corner_radius_px = a.corner_radius_px.filter(|v| *v <= 100);
+    a.padding_px = a.padding_px.filter(|v| *v <= 200);
+    for color in [
+        &mut a.foreground,
+        &mut a.background,
+        &mut a.border_color,
+        &mut a.accent,
+    ] {
+        if color.as_deref().is_some_and(is_bad_color) {
+            *color = None;
+        }
+    }
+    for p in cfg.profiles.values_mut() {
+        sanitize_overrides(&mut p.appearance);
+    }
+    if is_bad_template(&cfg.appearance.line_template) {
+        cfg.appearance.line_template = defaults.appearance.line_template;
+    }
+    let profiles = cfg.profiles.values_mut().map(|p| &mut p.desktops);
+    for label in std::iter::once(&mut cfg.desktops)
+        .chain(profiles)
+        .flat_map(|d| d.values_mut())
+    {
+        if label.template.as_deref().is_some_and(is_bad_template) {
+            label.template = None;
+        }
+        if label.deadline.as_deref().is_some_and(is_bad_deadline) {
+            label.deadline = None;
+        }
+        sanitize_overrides(&mut label.appearance);
+    }
+    if cfg
+        .active_profile
+        .as_ref()
+        .is_some_and(|name| !cfg.profiles.contains_key(name))
+    {
+        cfg.active_profile = None;
+    }
+}
+
+fn sanitize_overrides(a: &mut AppearanceOverrides) {
+    a.font_size_dip = a.font_size_dip.filter(|s| *s > 0);
+    a.margin_px = a.margin_px.filter(|m| *m >= 0);
+    if a.line_template.as_deref().is_some_and(is_bad_template) {
+        a.line_template = None;
+    }
+    a.border_px = a.border_px.filter(|v| *v <= 20);
+    a.opacity_percent = a.opacity_percent.filter(|v| *v <= 100);
+    for color in [
+        &mut a.foreground,
+        &mut a.background,
+        &mut a.border_color,
+        &mut a.accent,
+    ] {
+        if color.as_deref().is_some_and(is_bad_color) {
+            *color = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DesktopId;
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(validate(&Config::default(), None).is_empty());
+    }
+
+    #[test]
+    fn supported_keys() {
+        assert!(is_supported_key("T"));
+        assert!(is_supported_key(" 7 "));
+        assert!(!is_supported_key(""));
+        assert!(!is_supported_key("F5"));
+        assert!(!is_supported_key("Ä"));
+    }
+
+    #[test]
+    fn display_includes_path_and_location() {
+        let e = ConfigError::ZeroFontSize {
+            path: "appearance.font_size_dip".into(),
+            location: Some(Location { line: 3, column: 9 }),
+        };
+        assert_eq!(
+            e.to_string(),
+            "appearance.font_size_dip (line 3, column 9): font size must be greater than 0"
+        );
+        let e = ConfigError::EmptyHotkeyKey {
+            path: "hotkeys.edit_title.key".into(),
+            location: None,
+        };
+        assert_eq!(
+            e.to_string(),
+            "hotkeys.edit_title.key: hotkey key must not be empty"
+        );
+    }
+
+    #[test]
+    fn sanitize_resets_only_invalid_values() {
+        let mut cfg = Config::default();
+        cfg.hotkeys.edit_title.key = "".into();
+        cfg.hotkeys.toggle_overlay.key = "Q".into();
+        cfg.appearance.font_size_dip = 0;
+        cfg.appearance.margin_px = -4;
+        cfg.appearance.font_family = "Consolas".into();
+        cfg.profiles.insert("Wall".into(), Default::default());
+        cfg.profiles.get_mut("Wall").unwrap().appearance.margin_px = Some(-1);
+        cfg.active_profile = Some("Gone".into());
+        cfg.appearance.line_template = "{title".into();
+        cfg.appearance.description_opacity_percent = 150;
+        cfg.appearance.max_lines = 0;
+        cfg.appearance.background = Some("#00000g".into());
+        cfg.appearance.border_px = Some(50);
+        cfg.appearance.foreground = Some("gold".into());
+        let id = DesktopId::from_u128(1);
+        cfg.desktops.insert(
+            id,
+            DesktopLabel {
+                title: "Work".into(),
+                template: Some("{nope}".into()),
+                deadline: Some("friday".into()),
+                ..Default::default()
+            },
+        );
+        let problems = validate(&cfg, None);
+        assert_eq!(problems.len(), 13);
+        assert_eq!(
+            problems[4].to_string(),
+            "appearance.description_opacity_percent: must be between 0 and 100 (got 150)"
+        );
+        assert_eq!(
+            problems[11].to_string(),
+            format!(
+                "desktops.{id}.template: invalid line template: column 1: unknown placeholder {{nope}}"
+            )
+        );
+        assert!(problems.iter().any(|p| p.to_string()
+            == r##"appearance.background: expected a color like "#f80", "#ff8800cc" or "orange", got "#00000g""##));
+        sanitize(&mut cfg);
+        assert!(validate(&cfg, None).is_empty());
+        assert_eq!(cfg.active_profile, None);
+        assert_eq!(cfg.hotkeys.edit_title.key, "T");
+        assert_eq!(cfg.hotkeys.toggle_overlay.key, "Q");
+        assert_eq!(cfg.appearance.font_family, "Consolas");
+        assert_eq!(
+            cfg.appearance.line_template,
+            crate::template::DEFAULT_TEMPLATE
+        );
+        assert_eq!(cfg.desktops[&id].template, None);
+        assert_eq!(cfg.desktops[&id].deadline, None);
+        assert_eq!(cfg.appearance.description_opacity_percent, 70);
+        assert_eq!(cfg.appearance.max_lines, 2);
+        assert_eq!(cfg.appearance.background, None);
+        assert_eq!(cfg.appearance.border_px, None);
+        assert_eq!(cfg.desktops[&id].title, "Work");
+    }
+
+    #[test]
+    fn desktop_overrides_are_checked() {
+        let mut cfg = Config::default();
+        let id = DesktopId::from_u128(1);
+        let mut label = DesktopLabel {
+            title: "Prod".into(),
+            ..Default::default()
+        };
+        label.appearance.background = Some("#c00".into());
+        label.appearance.accent = Some("redish".into());
+        label.appearance.font_size_dip = Some(0);
+        label.appearance.opacity_percent = Some(101);
+        cfg.desktops.insert(id, label);
+        let problems: Vec<String> = validate(&cfg, None)
+            .iter()
+            .map(|p| p.path().to_string())
+            .collect();
+        let prefix = format!("desktops.{id}.appearance");
+        assert_eq!(
+            problems,
+            [
+                format!("{prefix}.font_size_dip"),
+                format!("{prefix}.opacity_percent"),
+                format!("{prefix}.accent"),
+            ]
+        );
+        sanitize(&mut cfg);
+        assert!(validate(&cfg, None).is_empty());
+        let a = &cfg.desktops[&id].appearance;
+        assert_eq!(a.background.as_deref(), Some("#c00"));
+        assert_eq!((a.accent.as_deref(), a.font_size_dip), (None, None));
+    }
+
+    #[test]
+    fn summarize_counts_extra_problems() {
+        let file = Path::new("cfg").join("labels.toml");
+        assert_eq!(summarize(&file, &[]), None);
+        let p = ConfigError::ZeroFontSize {
+            path: "appearance.font_size_dip".into(),
+            location: None,
+        };
+        assert_eq!(
+            summarize(&file, std::slice::from_ref(&p)).unwrap(),
+            "labels.toml: appearance.font_size_dip: font size must be greater than 0"
+        );
+        assert!(
+            summarize(&file, &[p.clone(), p])
+                .unwrap()
+                .ends_with("(+1 more)")
+        );
+    }
+
+    #[test]
+    fn strips_serde_position_suffix() {
+        assert_eq!(
+            strip_position("expected value at line 1 column 2"),
+            "expected value"
+        );
+        assert_eq!(strip_position("plain"), "plain");
+    }
+}
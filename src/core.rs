@@ -1,3 +1,7 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
 pub fn should_show(toggled_on: bool, high_contrast: bool, fullscreen: bool) -> bool {
     toggled_on && !high_contrast && !fullscreen
 }
@@ -15,10 +19,406 @@ pub fn calc_top_center(
     (x, y)
 }
 
+/// Measures text for the layout model; the overlay implements it with
+/// DirectWrite, tests with fixed-width glyphs.
+pub trait TextMeasure {
+    /// Width of `text` on one line at `size_px`.
+    fn width(&self, text: &str, size_px: f32) -> f32;
+    /// Distance between baselines of consecutive lines at `size_px`.
+    fn line_height(&self, size_px: f32) -> f32 {
+        size_px * 1.3
+    }
+}
+
+/// Settings for the two-line layout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TwoLineStyle {
+    pub title_px: f32,
+    pub description_px: f32,
+    pub hints_px: f32,
+    /// Wrap lines wider than this; `None` never wraps.
+    pub max_width: Option<f32>,
+    /// Lines per block; text beyond the last one is cut with an ellipsis.
+    pub max_lines: usize,
+    /// Where lines narrower than the widest go: 0.0 at the left, 0.5
+    /// centred, 1.0 at the right.
+    pub align: f32,
+}
+
+/// Which part of the two-line layout a line belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Block {
+    Title,
+    Description,
+    /// Hotkey hints, on a line of their own that is never wrapped.
+    Hints,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedLine {
+    pub text: String,
+    pub block: Block,
+    pub size_px: f32,
+    /// Offset from the top-left of the text area.
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+}
+
+/// Lines positioned inside a text area of `width` x `height`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextLayout {
+    pub lines: Vec<PlacedLine>,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl TextLayout {
+    /// Recompute `width` from the lines and place each line by `align` (see
+    /// `TwoLineStyle::align`); for after line widths changed.
+    pub fn align(&mut self, align: f32) {
+        self.width = self.lines.iter().map(|l| l.width).fold(0.0, f32::max);
+        for l in &mut self.lines {
+            l.x = (self.width - l.width) * align;
+        }
+    }
+}
+
+pub const ELLIPSIS: &str = "\u{2026}";
+
+/// Which part of over-long text gives way to the ellipsis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Ellipsis {
+    /// `Release freeze at…`
+    #[default]
+    End,
+    /// `Release…at noon`
+    Middle,
+}
+
+/// Text kept around an ellipsis: `text[..head]` and `text[tail..]`, both on
+/// grapheme cluster boundaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cut {
+    pub head: usize,
+    pub tail: usize,
+}
+
+impl Cut {
+    pub fn apply(self, text: &str) -> String {
+        format!("{}{ELLIPSIS}{}", &text[..self.head], &text[self.tail..])
+    }
+}
+
+/// Shorten `text` until `fits` accepts it, keeping as many grapheme clusters
+/// as possible so emoji and combining marks are never split. `None` if the
+/// text fits as it is.
+pub fn elide(text: &str, at: Ellipsis, fits: impl Fn(&str) -> bool) -> Option<Cut> {
+    if fits(text) {
+        return None;
+    }
+    Some(cut(text, at, fits))
+}
+
+/// Whether `text` has at most `max` grapheme clusters.
+pub fn within_chars(text: &str, max: usize) -> bool {
+    text.graphemes(true).nth(max).is_none()
+}
+
+fn cut(text: &str, at: Ellipsis, fits: impl Fn(&str) -> bool) -> Cut {
+    let bounds: Vec<usize> = text
+        .grapheme_indices(true)
+        .map(|(i, _)| i)
+        .chain([text.len()])
+        .collect();
+    let n = bounds.len() - 1;
+    let keeping = |keep: usize| {
+        let (head, tail) = match at {
+            Ellipsis::End => (keep, 0),
+            Ellipsis::Middle => (keep - keep / 2, keep / 2),
+        };
+        let head = text[..bounds[head]].trim_end().len();
+        let tail = bounds[n - tail];
+        let tail = tail + (text[tail..].len() - text[tail..].trim_start().len());
+        Cut { head, tail }
+    };
+    // The largest number of kept clusters that fits, or none at all.
+    let (mut lo, mut hi) = (0, n);
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        if fits(&keeping(mid).apply(text)) {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    keeping(lo)
+}
+
+/// Break `text` into at most `max_lines` lines no wider than `max_width`.
+/// Words wider than a line are split between grapheme clusters; if text is left
+/// over, the last line ends in an ellipsis.
+pub fn wrap(
+    text: &str,
+    size_px: f32,
+    max_width: Option<f32>,
+    max_lines: usize,
+    m: &impl TextMeasure,
+) -> Vec<String> {
+    let max_lines = max_lines.max(1);
+    let Some(max_width) = max_width else {
+        return vec![text.to_string()];
+    };
+    let fits = |s: &str| m.width(s, size_px) <= max_width;
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    let mut words = text.split_whitespace().peekable();
+    for word in words.by_ref() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{line} {word}")
+        };
+        if fits(&candidate) {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        // Split a word that does not fit on a line of its own.
+        let mut rest = word;
+        while !fits(rest) {
+            let cut = rest
+                .grapheme_indices(true)
+                .skip(1)
+                .map(|(i, _)| i)
+                .take_while(|i| fits(&rest[..*i]))
+                .last()
+                .unwrap_or_else(|| rest.graphemes(true).next().map_or(0, str::len));
+            lines.push(rest[..cut].to_string());
+            rest = &rest[cut..];
+        }
+        line = rest.to_string();
+        if lines.len() >= max_lines {
+            break;
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    let truncated = lines.len() > max_lines || words.peek().is_some();
+    lines.truncate(max_lines);
+    if truncated && let Some(last) = lines.last_mut() {
+        *last = cut(last, Ellipsis::End, fits).apply(last);
+    }
+    lines
+}
+
+/// Title on top in the main size, description below in its own size, then
+/// the hints; each line placed in the text area by `style.align`. An empty
+/// description or hints leave no line.
+pub fn two_line_layout(
+    title: &str,
+    description: &str,
+    hints: &str,
+    style: &TwoLineStyle,
+    m: &impl TextMeasure,
+) -> TextLayout {
+    let blocks = [
+        (Block::Title, title, style.title_px),
+        (Block::Description, description, style.description_px),
+        (Block::Hints, hints, style.hints_px),
+    ];
+    let mut lines = Vec::new();
+    let mut y = 0.0;
+    for (block, text, size_px) in blocks {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let texts = match block {
+            Block::Hints => vec![text.trim().to_string()],
+            _ => wrap(text.trim(), size_px, style.max_width, style.max_lines, m),
+        };
+        for text in texts {
+            let width = m.width(&text, size_px);
+            lines.push(PlacedLine {
+                text,
+                block,
+                size_px,
+                x: 0.0,
+                y,
+                width,
+            });
+            y += m.line_height(size_px);
+        }
+    }
+    let mut layout = TextLayout {
+        lines,
+        width: 0.0,
+        height: y,
+    };
+    layout.align(style.align);
+    layout
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Every character is `size_px / 2` wide.
+    struct Fixed;
+
+    impl TextMeasure for Fixed {
+        fn width(&self, text: &str, size_px: f32) -> f32 {
+            text.chars().count() as f32 * size_px / 2.0
+        }
+    }
+
+    fn texts(lines: &[String]) -> Vec<&str> {
+        lines.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn wrap_breaks_at_spaces() {
+        // 10px per character, 100px per line.
+        let lines = wrap("the quick brown fox jumps", 20.0, Some(100.0), 3, &Fixed);
+        assert_eq!(texts(&lines), ["the quick", "brown fox", "jumps"]);
+        let lines = wrap("the quick brown fox jumps", 20.0, None, 1, &Fixed);
+        assert_eq!(texts(&lines), ["the quick brown fox jumps"]);
+    }
+
+    #[test]
+    fn wrap_clamps_with_an_ellipsis() {
+        let lines = wrap("the quick brown fox jumps", 20.0, Some(100.0), 2, &Fixed);
+        assert_eq!(texts(&lines), ["the quick", "brown fox\u{2026}"]);
+        let lines = wrap("the quick brown fox jumps", 20.0, Some(90.0), 1, &Fixed);
+        assert_eq!(texts(&lines), ["the quic\u{2026}"]);
+    }
+
+    #[test]
+    fn wrap_splits_long_words() {
+        let lines = wrap("abcdefghijklmnop qr", 20.0, Some(50.0), 5, &Fixed);
+        assert_eq!(texts(&lines), ["abcde", "fghij", "klmno", "p qr"]);
+        let lines = wrap("abcdefghijklmnop", 20.0, Some(50.0), 2, &Fixed);
+        assert_eq!(texts(&lines), ["abcde", "fghi\u{2026}"]);
+    }
+
+    #[test]
+    fn elide_keeps_grapheme_clusters_whole() {
+        let max = |n| move |s: &str| within_chars(s, n);
+        let t = "Release freeze at noon";
+        assert_eq!(elide(t, Ellipsis::End, max(30)), None);
+        let cut = elide(t, Ellipsis::End, max(10)).unwrap();
+        assert_eq!(cut.apply(t), "Release f\u{2026}");
+        let cut = elide(t, Ellipsis::Middle, max(10)).unwrap();
+        assert_eq!(cut.apply(t), "Relea\u{2026}noon");
+
+        // A family emoji (ZWJ sequence) and e + combining acute are one each.
+        let t = "ab\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}e\u{301}cd";
+        assert!(within_chars(t, 6) && !within_chars(t, 5));
+        assert_eq!(
+            elide(t, Ellipsis::End, max(4)).unwrap().apply(t),
+            "ab\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{2026}"
+        );
+        assert_eq!(
+            elide(t, Ellipsis::Middle, max(4)).unwrap().apply(t),
+            "ab\u{2026}d"
+        );
+        assert_eq!(
+            elide(t, Ellipsis::End, max(0)).unwrap().apply(t),
+            "\u{2026}"
+        );
+    }
+
+    #[test]
+    fn elide_by_width() {
+        // 10px per character.
+        let fits = |s: &str| Fixed.width(s, 20.0) <= 60.0;
+        let t = "abcdefghij";
+        assert_eq!(
+            elide(t, Ellipsis::End, fits).unwrap().apply(t),
+            "abcde\u{2026}"
+        );
+        assert_eq!(
+            elide(t, Ellipsis::Middle, fits).unwrap().apply(t),
+            "abc\u{2026}ij"
+        );
+    }
+
+    #[test]
+    fn wrap_never_splits_a_cluster() {
+        let t = "e\u{301}e\u{301}e\u{301}e\u{301}";
+        let lines = wrap(t, 20.0, Some(25.0), 4, &Fixed);
+        // Each cluster is two chars, so 20px wide in `Fixed`.
+        assert_eq!(texts(&lines), ["e\u{301}"; 4]);
+    }
+
+    #[test]
+    fn two_line_layout_stacks_and_centres() {
+        let style = TwoLineStyle {
+            title_px: 20.0,
+            description_px: 10.0,
+            hints_px: 8.0,
+            max_width: Some(80.0),
+            max_lines: 2,
+            align: 0.5,
+        };
+        let l = two_line_layout("Work", "Focus on the tickets", "", &style, &Fixed);
+        let summary: Vec<_> = l
+            .lines
+            .iter()
+            .map(|p| (p.text.as_str(), p.block, p.x, p.y))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Work", Block::Title, 10.0, 0.0),
+                ("Focus on the", Block::Description, 0.0, 26.0),
+                ("tickets", Block::Description, 12.5, 39.0),
+            ]
+        );
+        assert_eq!(l.width, 60.0);
+        assert_eq!(l.height, 52.0);
+
+        let l = two_line_layout("Work", "  ", "", &style, &Fixed);
+        assert_eq!(l.lines.len(), 1);
+        assert_eq!(l.height, 26.0);
+
+        // Right-aligned, as `start` on a right-to-left overlay.
+        let style = TwoLineStyle {
+            align: 1.0,
+            ..style
+        };
+        let l = two_line_layout("Work", "Focus on the tickets", "", &style, &Fixed);
+        let xs: Vec<f32> = l.lines.iter().map(|p| p.x).collect();
+        assert_eq!(xs, [20.0, 0.0, 25.0]);
+    }
+
+    #[test]
+    fn two_line_layout_keeps_hints_whole_on_their_own_line() {
+        let style = TwoLineStyle {
+            title_px: 20.0,
+            description_px: 10.0,
+            hints_px: 8.0,
+            max_width: Some(80.0),
+            max_lines: 2,
+            align: 0.0,
+        };
+        let hints = "(Ctrl+Alt+T, Ctrl+Alt+D)";
+        let l = two_line_layout("Work", "Tickets", hints, &style, &Fixed);
+        let last = l.lines.last().unwrap();
+        assert_eq!((last.text.as_str(), last.block), (hints, Block::Hints));
+        assert_eq!((last.size_px, last.y, last.width), (8.0, 39.0, 96.0));
+        assert_eq!(l.lines.len(), 3);
+        assert_eq!(l.width, 96.0);
+
+        let l = two_line_layout("Work", "", hints, &style, &Fixed);
+        let blocks: Vec<Block> = l.lines.iter().map(|p| p.block).collect();
+        assert_eq!(blocks, [Block::Title, Block::Hints]);
+    }
+
     #[test]
     fn center_calc_basic() {
         let (x, y) = calc_top_center((0, 0, 1000, 800), 200, 20, 10);
@@ -66,6 +66,9 @@ pub const HK_EDIT_TITLE: i32 = 1;
 pub const HK_EDIT_DESC: i32 = 2;
 pub const HK_TOGGLE: i32 = 3;
 pub const HK_SNAP: i32 = 4;
+pub const HK_PROFILE: i32 = 5;
+pub const HK_UNDO: i32 = 6;
+pub const HK_REDO: i32 = 7;
 
 // Utility: detect duplicates between hotkey chords (case-insensitive key, same modifiers).
 use crate::config::Hotkeys;
@@ -76,10 +79,16 @@ pub fn has_duplicates(hk: &Hotkeys) -> bool {
             && a.shift == b.shift
             && a.key.eq_ignore_ascii_case(&b.key)
     }
-    same(&hk.edit_title, &hk.edit_description)
-        || same(&hk.edit_title, &hk.toggle_overlay)
-        || same(&hk.edit_description, &hk.toggle_overlay)
-        || same(&hk.edit_title, &hk.snap_position)
-        || same(&hk.edit_description, &hk.snap_position)
-        || same(&hk.toggle_overlay, &hk.snap_position)
+    let all = [
+        &hk.edit_title,
+        &hk.edit_description,
+        &hk.toggle_overlay,
+        &hk.snap_position,
+        &hk.cycle_profile,
+        &hk.undo_label,
+        &hk.redo_label,
+    ];
+    all.iter()
+        .enumerate()
+        .any(|(i, a)| all[i + 1..].iter().any(|b| same(a, b)))
 }
@@ -0,0 +1,215 @@
+//! User-facing strings: tray menu, dialogs, balloons and the About box.
+//!
+//! Each language is a catalog of `key => text` in its own file. Texts may
+//! contain `{name}` placeholders filled in by `tr_with`. A key missing from a
+//! catalog falls back to English, and a missing English text to the key
+//! itself, so a gap shows up as `menu.exit` rather than an empty menu item.
+//!
+//! The language comes from `language` in the config, or the Windows display
+//! language when that is unset or `auto`.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+mod de;
+mod en;
+mod fr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Locale::En, Locale::De, Locale::Fr];
+
+    /// The language of a tag like `de`, `de-AT`, `fr_CA.UTF-8` or `EN`;
+    /// `None` for languages without a catalog.
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let lang = tag
+            .split(['-', '_', '.', '@'])
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase();
+        match lang.as_str() {
+            "en" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            "fr" => Some(Locale::Fr),
+            _ => None,
+        }
+    }
+
+    pub fn tag(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+            Locale::Fr => "fr",
+        }
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::En => en::MESSAGES,
+            Locale::De => de::MESSAGES,
+            Locale::Fr => fr::MESSAGES,
+        }
+    }
+
+    /// The text for `key` in this language, falling back to English and then
+    /// to the key.
+    pub fn text(self, key: &'static str) -> &'static str {
+        lookup(self.catalog(), key)
+            .or_else(|| lookup(en::MESSAGES, key))
+            .unwrap_or(key)
+    }
+}
+
+fn lookup(catalog: &'static [(&'static str, &'static str)], key: &str) -> Option<&'static str> {
+    catalog.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+/// The language to use: the configured one if it has a catalog, else the
+/// system's, else English.
+pub fn resolve(configured: Option<&str>, system: Option<&str>) -> Locale {
+    configured
+        .filter(|tag| !tag.trim().eq_ignore_ascii_case("auto"))
+        .and_then(Locale::from_tag)
+        .or_else(|| system.and_then(Locale::from_tag))
+        .unwrap_or_default()
+}
+
+/// The user's display language as a tag like `de-DE`.
+#[cfg(windows)]
+pub fn system_tag() -> Option<String> {
+    use windows::Win32::Globalization::{GetUserDefaultUILanguage, LCIDToLocaleName};
+    let mut buf = [0u16; 85];
+    let len = unsafe { LCIDToLocaleName(u32::from(GetUserDefaultUILanguage()), Some(&mut buf), 0) };
+    (len > 1).then(|| String::from_utf16_lossy(&buf[..len as usize - 1]))
+}
+
+/// The user's language from `LC_ALL`, `LC_MESSAGES` or `LANG`.
+#[cfg(not(windows))]
+pub fn system_tag() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|v| !v.is_empty())
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// Switch the language used by `tr` and `tr_with`.
+pub fn set_locale(locale: Locale) {
+    CURRENT.store(locale as u8, Ordering::Relaxed);
+}
+
+pub fn locale() -> Locale {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Locale::De,
+        2 => Locale::Fr,
+        _ => Locale::En,
+    }
+}
+
+/// The text for `key` in the current language.
+pub fn tr(key: &'static str) -> &'static str {
+    locale().text(key)
+}
+
+/// `tr` with each `{name}` replaced by its value.
+pub fn tr_with(key: &'static str, args: &[(&str, &str)]) -> String {
+    fill(tr(key), args)
+}
+
+fn fill(text: &str, args: &[(&str, &str)]) -> String {
+    args.iter().fold(text.to_string(), |out, (name, value)| {
+        out.replace(&format!("{{{name}}}"), value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        text.split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn every_catalog_has_every_key() {
+        let english: BTreeSet<&str> = en::MESSAGES.iter().map(|(k, _)| *k).collect();
+        assert_eq!(english.len(), en::MESSAGES.len(), "duplicate English key");
+        for locale in Locale::ALL {
+            let keys: BTreeSet<&str> = locale.catalog().iter().map(|(k, _)| *k).collect();
+            assert_eq!(
+                keys.len(),
+                locale.catalog().len(),
+                "duplicate key in {locale:?}"
+            );
+            assert_eq!(keys, english, "{locale:?} keys differ from English");
+            for (key, text) in locale.catalog() {
+                assert!(!text.trim().is_empty(), "{locale:?} {key} is empty");
+                assert_eq!(
+                    placeholders(text),
+                    placeholders(Locale::En.text(key)),
+                    "{locale:?} {key} placeholders"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn keys_named_by_other_modules_exist() {
+        use crate::config::archive::ReattachError;
+        use crate::config::identity::Evidence;
+        use crate::config::layers::Layer;
+        let keys = [
+            Evidence::NameAndIndex.message_key(),
+            Evidence::Name.message_key(),
+            Evidence::Index.message_key(),
+            Layer::Default.message_key(),
+            Layer::Machine.message_key(),
+            Layer::User.message_key(),
+            Layer::Environment.message_key(),
+            ReattachError::NoSuchLabel(0).message_key(),
+            ReattachError::Labelled(crate::config::DesktopId::from_u128(1)).message_key(),
+        ];
+        for key in keys {
+            assert!(lookup(en::MESSAGES, key).is_some(), "{key}");
+        }
+    }
+
+    #[test]
+    fn tags_pick_a_catalog_or_fall_back() {
+        assert_eq!(Locale::from_tag("de-AT"), Some(Locale::De));
+        assert_eq!(Locale::from_tag("fr_CA.UTF-8"), Some(Locale::Fr));
+        assert_eq!(Locale::from_tag("EN"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("ja-JP"), None);
+        assert_eq!(Locale::from_tag("C"), None);
+        assert_eq!(resolve(Some("fr"), Some("de-DE")), Locale::Fr);
+        assert_eq!(resolve(Some("auto"), Some("de-DE")), Locale::De);
+        assert_eq!(resolve(Some("klingon"), Some("fr-FR")), Locale::Fr);
+        assert_eq!(resolve(None, Some("ja-JP")), Locale::En);
+        assert_eq!(resolve(None, None), Locale::En);
+    }
+
+    #[test]
+    fn texts_fall_back_and_fill_placeholders() {
+        assert_eq!(Locale::De.text("menu.exit"), "Beenden");
+        assert_eq!(Locale::De.text("no.such.key"), "no.such.key");
+        assert_eq!(
+            fill(
+                Locale::Fr.text("msg.restored"),
+                &[("file", "labels-1.json")]
+            ),
+            "labels-1.json restauré"
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! German.
+
+pub const MESSAGES: &[(&str, &str)] = &[
+    ("app.name", "Desktop Labeler"),
+    ("action.edit_title", "Titel bearbeiten"),
+    ("action.edit_description", "Beschreibung bearbeiten"),
+    ("action.toggle_overlay", "Overlay ein/aus"),
+    ("action.snap_position", "Position wechseln"),
+    ("action.cycle_profile", "Nächstes Profil"),
+    ("action.undo_label", "Label-Änderung rückgängig"),
+    ("action.redo_label", "Label-Änderung wiederholen"),
+    ("menu.default_profile", "Standard"),
+    ("menu.profile", "Profil"),
+    ("menu.reattach", "Archiviertes Label zuordnen"),
+    ("menu.open_config", "Konfiguration öffnen"),
+    ("menu.restore_backup", "Letzte Sicherung wiederherstellen"),
+    ("menu.diagnostics", "Konfigurationsdiagnose"),
+    ("menu.run_at_startup", "Beim Anmelden starten"),
+    ("menu.about", "Info..."),
+    ("menu.exit", "Beenden"),
+    ("dialog.ok", "OK"),
+    ("dialog.cancel", "Abbrechen"),
+    ("edit.title_caption", "Desktop-Titel bearbeiten"),
+    ("edit.title_hint", "Titel ändern"),
+    (
+        "edit.description_caption",
+        "Desktop-Beschreibung bearbeiten",
+    ),
+    ("edit.description_hint", "Beschreibung ändern"),
+    ("balloon.config", "Konfiguration"),
+    ("balloon.hotkeys", "Tastenkürzel"),
+    ("balloon.profile", "Profil"),
+    ("balloon.reattach", "Zuordnen"),
+    ("balloon.undo", "Rückgängig"),
+    ("balloon.redo", "Wiederholen"),
+    (
+        "msg.not_saved",
+        "{text} (nicht gespeichert: zuerst die Fehler in {file} beheben)",
+    ),
+    (
+        "msg.label_not_saved",
+        "Label nicht gespeichert: zuerst die Fehler in {file} beheben",
+    ),
+    (
+        "msg.save_failed",
+        "{text} (nicht gespeichert: {file} konnte nicht geschrieben werden)",
+    ),
+    (
+        "msg.label_save_failed",
+        "Label nicht gespeichert: {file} konnte nicht geschrieben werden",
+    ),
+    (
+        "msg.duplicate_hotkeys",
+        "Doppelte Tastenkürzel gefunden; bitte labels.json anpassen",
+    ),
+    (
+        "msg.hotkeys_failed",
+        "Einige Tastenkürzel konnten nicht registriert werden. Bitte labels.json anpassen",
+    ),
+    (
+        "msg.diagnostics_failed",
+        "Die Diagnosedatei konnte nicht geschrieben werden",
+    ),
+    ("msg.restored", "{file} wiederhergestellt"),
+    (
+        "msg.no_backup",
+        "Keine Sicherung zum Wiederherstellen vorhanden",
+    ),
+    (
+        "msg.nothing_to_undo",
+        "Auf diesem Desktop gibt es nichts rückgängig zu machen",
+    ),
+    (
+        "msg.nothing_to_redo",
+        "Auf diesem Desktop gibt es nichts zu wiederholen",
+    ),
+    (
+        "msg.offer_matches",
+        "Diese Desktops sehen aus wie beschriftete Desktops mit geänderter ID:\r\n\r\n{matches}\r\n\r\nLabels dorthin verschieben?",
+    ),
+    (
+        "msg.offer_match",
+        "\"{title}\" \u{2192} Desktop {number} ({evidence})",
+    ),
+    (
+        "evidence.name_and_index",
+        "gleicher Name und gleiche Position",
+    ),
+    ("evidence.name", "gleicher Name"),
+    ("evidence.index", "gleiche Position"),
+    ("about.caption", "Info über Desktop Labeler"),
+    (
+        "about.text",
+        "Desktop Labeler\r\n\r\nZeigt auf dem Hauptbildschirm einen Titel für jeden virtuellen Desktop.\r\n\r\nTastenkürzel:\r\n{hotkeys}",
+    ),
+    (
+        "cli.converted",
+        "Die Konfiguration wurde nach {file} umgewandelt.\r\n\r\nDie bisherige Datei liegt jetzt im Sicherungsordner.",
+    ),
+    (
+        "cli.convert_failed",
+        "Die Konfiguration konnte nicht umgewandelt werden:\r\n\r\n{error}",
+    ),
+    (
+        "cli.exchange_failed",
+        "Labels konnten nicht ausgetauscht werden:\r\n\r\n{error}",
+    ),
+    (
+        "exchange.exported",
+        "{count} Label(s) nach {file} exportiert.",
+    ),
+    (
+        "exchange.imported",
+        "{file} in das Profil „{profile}“ importiert ({strategy}): {report}.",
+    ),
+    (
+        "exchange.report",
+        "{added} hinzugefügt, {updated} aktualisiert, {unchanged} unverändert, {kept} beibehalten, {unmatched} ohne passenden Desktop",
+    ),
+    (
+        "exchange.report_settings",
+        "; Tastenkürzel/Darstellung importiert",
+    ),
+    (
+        "exchange.config_problems",
+        "vor dem Import zuerst die Konfiguration korrigieren: {problem}",
+    ),
+    (
+        "exchange.bad_settings",
+        "{file} kann nicht importiert werden: {problem}",
+    ),
+    ("config.at", " (Zeile {line}, Spalte {column})"),
+    (
+        "config.unreadable",
+        "Die Datei kann nicht gelesen werden: {message}",
+    ),
+    (
+        "config.override",
+        "{path}: Wert aus der Ebene „{layer}“ ignoriert: {message}",
+    ),
+    (
+        "config.syntax",
+        "Syntaxfehler in Zeile {line}, Spalte {column}: {message}",
+    ),
+    ("config.type", "{path}{at}: {message}"),
+    (
+        "config.empty_hotkey_key",
+        "{path}{at}: Die Taste des Tastenkürzels darf nicht leer sein",
+    ),
+    (
+        "config.unknown_key_name",
+        "{path}{at}: unbekannter Tastenname {key}; einen einzelnen Buchstaben oder eine Ziffer verwenden",
+    ),
+    (
+        "config.zero_font_size",
+        "{path}{at}: Die Schriftgröße muss größer als 0 sein",
+    ),
+    (
+        "config.negative_margin",
+        "{path}{at}: Der Rand darf nicht negativ sein (Wert: {value})",
+    ),
+    (
+        "config.out_of_range",
+        "{path}{at}: muss zwischen {min} und {max} liegen (Wert: {value})",
+    ),
+    (
+        "config.unknown_profile",
+        "{path}{at}: kein Profil namens {name}",
+    ),
+    (
+        "config.bad_template",
+        "{path}{at}: ungültige Zeilenvorlage: {message}",
+    ),
+    (
+        "config.bad_deadline",
+        "{path}{at}: erwartet wird eine Ortszeit wie \"2024-05-31 18:00\", nicht {value}",
+    ),
+    (
+        "config.bad_color",
+        "{path}{at}: erwartet wird eine Farbe wie \"#f80\", \"#ff8800cc\" oder \"orange\", nicht {value}",
+    ),
+    ("config.summary", "{file}: {problem}"),
+    ("config.summary_more", "{file}: {problem} (+{more} weitere)"),
+    ("layer.default", "Standard"),
+    ("layer.machine", "Rechner"),
+    ("layer.user", "Benutzer"),
+    ("layer.environment", "Umgebung"),
+    (
+        "reattach.no_such_label",
+        "Dieses archivierte Label gibt es nicht mehr",
+    ),
+    ("reattach.labelled", "Dieser Desktop hat bereits ein Label"),
+    ("template.untitled", "Desktop"),
+];
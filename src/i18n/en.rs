@@ -0,0 +1,170 @@
+//! English, the reference catalog: every other catalog has exactly these keys.
+
+pub const MESSAGES: &[(&str, &str)] = &[
+    ("app.name", "Desktop Labeler"),
+    ("action.edit_title", "Edit Title"),
+    ("action.edit_description", "Edit Description"),
+    ("action.toggle_overlay", "Toggle Overlay"),
+    ("action.snap_position", "Snap Position"),
+    ("action.cycle_profile", "Next Profile"),
+    ("action.undo_label", "Undo Label Edit"),
+    ("action.redo_label", "Redo Label Edit"),
+    ("menu.default_profile", "Default"),
+    ("menu.profile", "Profile"),
+    ("menu.reattach", "Re-attach Archived Label"),
+    ("menu.open_config", "Open Config"),
+    ("menu.restore_backup", "Restore Last Backup"),
+    ("menu.diagnostics", "Config Diagnostics"),
+    ("menu.run_at_startup", "Run at startup"),
+    ("menu.about", "About..."),
+    ("menu.exit", "Exit"),
+    ("dialog.ok", "OK"),
+    ("dialog.cancel", "Cancel"),
+    ("edit.title_caption", "Edit Desktop Title"),
+    ("edit.title_hint", "Change the title"),
+    ("edit.description_caption", "Edit Desktop Description"),
+    ("edit.description_hint", "Change the description"),
+    ("balloon.config", "Config"),
+    ("balloon.hotkeys", "Hotkeys"),
+    ("balloon.profile", "Profile"),
+    ("balloon.reattach", "Re-attach"),
+    ("balloon.undo", "Undo"),
+    ("balloon.redo", "Redo"),
+    (
+        "msg.not_saved",
+        "{text} (not saved: fix the errors in {file} first)",
+    ),
+    (
+        "msg.label_not_saved",
+        "Label not saved: fix the errors in {file} first",
+    ),
+    (
+        "msg.save_failed",
+        "{text} (not saved: could not write {file})",
+    ),
+    (
+        "msg.label_save_failed",
+        "Label not saved: could not write {file}",
+    ),
+    (
+        "msg.duplicate_hotkeys",
+        "Duplicate hotkeys detected; adjust labels.json",
+    ),
+    (
+        "msg.hotkeys_failed",
+        "Some hotkeys failed to register. Adjust in labels.json",
+    ),
+    (
+        "msg.diagnostics_failed",
+        "Could not write the diagnostics file",
+    ),
+    ("msg.restored", "Restored {file}"),
+    ("msg.no_backup", "No backup available to restore"),
+    ("msg.nothing_to_undo", "Nothing to undo on this desktop"),
+    ("msg.nothing_to_redo", "Nothing to redo on this desktop"),
+    (
+        "msg.offer_matches",
+        "These desktops look like labelled desktops whose ID changed:\r\n\r\n{matches}\r\n\r\nMove the labels to them?",
+    ),
+    (
+        "msg.offer_match",
+        "\"{title}\" \u{2192} desktop {number} ({evidence})",
+    ),
+    ("evidence.name_and_index", "same name and position"),
+    ("evidence.name", "same name"),
+    ("evidence.index", "same position"),
+    ("about.caption", "About Desktop Labeler"),
+    (
+        "about.text",
+        "Desktop Labeler\r\n\r\nShows a per-desktop title overlay on the primary monitor.\r\n\r\nHotkeys:\r\n{hotkeys}",
+    ),
+    (
+        "cli.converted",
+        "Converted the configuration to {file}.\r\n\r\nThe previous file was moved to the backups folder.",
+    ),
+    (
+        "cli.convert_failed",
+        "Could not convert the configuration:\r\n\r\n{error}",
+    ),
+    (
+        "cli.exchange_failed",
+        "Could not exchange labels:\r\n\r\n{error}",
+    ),
+    ("exchange.exported", "Exported {count} label(s) to {file}."),
+    (
+        "exchange.imported",
+        "Imported {file} into profile \"{profile}\" ({strategy}): {report}.",
+    ),
+    (
+        "exchange.report",
+        "{added} added, {updated} updated, {unchanged} unchanged, {kept} kept, {unmatched} without a matching desktop",
+    ),
+    ("exchange.report_settings", "; hotkeys/appearance imported"),
+    (
+        "exchange.config_problems",
+        "fix the configuration before importing: {problem}",
+    ),
+    (
+        "exchange.bad_settings",
+        "{file} cannot be imported: {problem}",
+    ),
+    ("config.at", " (line {line}, column {column})"),
+    ("config.unreadable", "cannot read the file: {message}"),
+    (
+        "config.override",
+        "{path}: {layer} override ignored: {message}",
+    ),
+    (
+        "config.syntax",
+        "syntax error at line {line}, column {column}: {message}",
+    ),
+    ("config.type", "{path}{at}: {message}"),
+    (
+        "config.empty_hotkey_key",
+        "{path}{at}: hotkey key must not be empty",
+    ),
+    (
+        "config.unknown_key_name",
+        "{path}{at}: unknown key name {key}; use a single letter or digit",
+    ),
+    (
+        "config.zero_font_size",
+        "{path}{at}: font size must be greater than 0",
+    ),
+    (
+        "config.negative_margin",
+        "{path}{at}: margin must not be negative (got {value})",
+    ),
+    (
+        "config.out_of_range",
+        "{path}{at}: must be between {min} and {max} (got {value})",
+    ),
+    (
+        "config.unknown_profile",
+        "{path}{at}: no profile named {name}",
+    ),
+    (
+        "config.bad_template",
+        "{path}{at}: invalid line template: {message}",
+    ),
+    (
+        "config.bad_deadline",
+        "{path}{at}: expected a local time like \"2024-05-31 18:00\", got {value}",
+    ),
+    (
+        "config.bad_color",
+        "{path}{at}: expected a color like \"#f80\", \"#ff8800cc\" or \"orange\", got {value}",
+    ),
+    ("config.summary", "{file}: {problem}"),
+    ("config.summary_more", "{file}: {problem} (+{more} more)"),
+    ("layer.default", "default"),
+    ("layer.machine", "machine"),
+    ("layer.user", "user"),
+    ("layer.environment", "environment"),
+    (
+        "reattach.no_such_label",
+        "That archived label no longer exists",
+    ),
+    ("reattach.labelled", "This desktop already has a label"),
+    ("template.untitled", "Desktop"),
+];
@@ -0,0 +1,185 @@
+//! French.
+
+pub const MESSAGES: &[(&str, &str)] = &[
+    ("app.name", "Desktop Labeler"),
+    ("action.edit_title", "Modifier le titre"),
+    ("action.edit_description", "Modifier la description"),
+    ("action.toggle_overlay", "Afficher/masquer l'overlay"),
+    ("action.snap_position", "Changer la position"),
+    ("action.cycle_profile", "Profil suivant"),
+    ("action.undo_label", "Annuler la modification"),
+    ("action.redo_label", "Rétablir la modification"),
+    ("menu.default_profile", "Par défaut"),
+    ("menu.profile", "Profil"),
+    ("menu.reattach", "Rattacher une étiquette archivée"),
+    ("menu.open_config", "Ouvrir la configuration"),
+    ("menu.restore_backup", "Restaurer la dernière sauvegarde"),
+    ("menu.diagnostics", "Diagnostic de la configuration"),
+    ("menu.run_at_startup", "Lancer au démarrage"),
+    ("menu.about", "À propos..."),
+    ("menu.exit", "Quitter"),
+    ("dialog.ok", "OK"),
+    ("dialog.cancel", "Annuler"),
+    ("edit.title_caption", "Modifier le titre du bureau"),
+    ("edit.title_hint", "Nouveau titre"),
+    (
+        "edit.description_caption",
+        "Modifier la description du bureau",
+    ),
+    ("edit.description_hint", "Nouvelle description"),
+    ("balloon.config", "Configuration"),
+    ("balloon.hotkeys", "Raccourcis"),
+    ("balloon.profile", "Profil"),
+    ("balloon.reattach", "Rattacher"),
+    ("balloon.undo", "Annuler"),
+    ("balloon.redo", "Rétablir"),
+    (
+        "msg.not_saved",
+        "{text} (non enregistré : corrigez d'abord les erreurs de {file})",
+    ),
+    (
+        "msg.label_not_saved",
+        "Étiquette non enregistrée : corrigez d'abord les erreurs de {file}",
+    ),
+    (
+        "msg.save_failed",
+        "{text} (non enregistré : impossible d'écrire {file})",
+    ),
+    (
+        "msg.label_save_failed",
+        "Étiquette non enregistrée : impossible d'écrire {file}",
+    ),
+    (
+        "msg.duplicate_hotkeys",
+        "Raccourcis en double ; modifiez labels.json",
+    ),
+    (
+        "msg.hotkeys_failed",
+        "Certains raccourcis n'ont pas pu être enregistrés. Modifiez labels.json",
+    ),
+    (
+        "msg.diagnostics_failed",
+        "Impossible d'écrire le fichier de diagnostic",
+    ),
+    ("msg.restored", "{file} restauré"),
+    ("msg.no_backup", "Aucune sauvegarde à restaurer"),
+    ("msg.nothing_to_undo", "Rien à annuler sur ce bureau"),
+    ("msg.nothing_to_redo", "Rien à rétablir sur ce bureau"),
+    (
+        "msg.offer_matches",
+        "Ces bureaux ressemblent à des bureaux étiquetés dont l'identifiant a changé :\r\n\r\n{matches}\r\n\r\nY déplacer les étiquettes ?",
+    ),
+    (
+        "msg.offer_match",
+        "« {title} » \u{2192} bureau {number} ({evidence})",
+    ),
+    ("evidence.name_and_index", "même nom et même position"),
+    ("evidence.name", "même nom"),
+    ("evidence.index", "même position"),
+    ("about.caption", "À propos de Desktop Labeler"),
+    (
+        "about.text",
+        "Desktop Labeler\r\n\r\nAffiche le titre de chaque bureau virtuel sur l'écran principal.\r\n\r\nRaccourcis :\r\n{hotkeys}",
+    ),
+    (
+        "cli.converted",
+        "La configuration a été convertie en {file}.\r\n\r\nL'ancien fichier a été déplacé dans le dossier des sauvegardes.",
+    ),
+    (
+        "cli.convert_failed",
+        "Impossible de convertir la configuration :\r\n\r\n{error}",
+    ),
+    (
+        "cli.exchange_failed",
+        "Impossible d'échanger les étiquettes :\r\n\r\n{error}",
+    ),
+    (
+        "exchange.exported",
+        "{count} étiquette(s) exportée(s) vers {file}.",
+    ),
+    (
+        "exchange.imported",
+        "{file} importé dans le profil « {profile} » ({strategy}) : {report}.",
+    ),
+    (
+        "exchange.report",
+        "{added} ajoutée(s), {updated} mise(s) à jour, {unchanged} inchangée(s), {kept} conservée(s), {unmatched} sans bureau correspondant",
+    ),
+    (
+        "exchange.report_settings",
+        " ; raccourcis/apparence importés",
+    ),
+    (
+        "exchange.config_problems",
+        "corrigez d'abord la configuration avant l'import : {problem}",
+    ),
+    (
+        "exchange.bad_settings",
+        "{file} ne peut pas être importé : {problem}",
+    ),
+    ("config.at", " (ligne {line}, colonne {column})"),
+    (
+        "config.unreadable",
+        "impossible de lire le fichier : {message}",
+    ),
+    (
+        "config.override",
+        "{path} : valeur de la couche « {layer} » ignorée : {message}",
+    ),
+    (
+        "config.syntax",
+        "erreur de syntaxe ligne {line}, colonne {column} : {message}",
+    ),
+    ("config.type", "{path}{at} : {message}"),
+    (
+        "config.empty_hotkey_key",
+        "{path}{at} : la touche du raccourci ne doit pas être vide",
+    ),
+    (
+        "config.unknown_key_name",
+        "{path}{at} : nom de touche inconnu {key} ; utilisez une seule lettre ou un chiffre",
+    ),
+    (
+        "config.zero_font_size",
+        "{path}{at} : la taille de police doit être supérieure à 0",
+    ),
+    (
+        "config.negative_margin",
+        "{path}{at} : la marge ne doit pas être négative (valeur : {value})",
+    ),
+    (
+        "config.out_of_range",
+        "{path}{at} : doit être comprise entre {min} et {max} (valeur : {value})",
+    ),
+    (
+        "config.unknown_profile",
+        "{path}{at} : aucun profil nommé {name}",
+    ),
+    (
+        "config.bad_template",
+        "{path}{at} : modèle de ligne invalide : {message}",
+    ),
+    (
+        "config.bad_deadline",
+        "{path}{at} : heure locale attendue, par exemple \"2024-05-31 18:00\", reçu {value}",
+    ),
+    (
+        "config.bad_color",
+        "{path}{at} : couleur attendue, par exemple \"#f80\", \"#ff8800cc\" ou \"orange\", reçu {value}",
+    ),
+    ("config.summary", "{file} : {problem}"),
+    (
+        "config.summary_more",
+        "{file} : {problem} (+{more} autre(s))",
+    ),
+    ("layer.default", "par défaut"),
+    ("layer.machine", "machine"),
+    ("layer.user", "utilisateur"),
+    ("layer.environment", "environnement"),
+    (
+        "reattach.no_such_label",
+        "Cette étiquette archivée n'existe plus",
+    ),
+    ("reattach.labelled", "Ce bureau a déjà une étiquette"),
+    ("template.untitled", "Bureau"),
+];
@@ -2,28 +2,69 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::config::{self, DesktopLabel};
+use crate::config::archive::ArchivedLabel;
+use crate::config::history::{History, Revision};
+use crate::config::{self, Config, DesktopId, DesktopLabel};
 
+/// Pipe of the default instance. Instances started with their own config
+/// location append a suffix (see `config::location::instance_name`).
 const PIPE_NAME: &str = r"\\.\pipe\Acme.DesktopLabeler.mddsklbl";
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "op", rename_all = "snake_case")]
 enum Request {
     List,
-    ResolveWindow { hwnd: u64 },
+    ResolveWindow {
+        hwnd: u64,
+    },
+    ListProfiles,
+    /// Switch profiles; `null` or no name selects the top-level labels.
+    SetProfile {
+        #[serde(default)]
+        name: Option<String>,
+    },
+    /// Earlier labels of a desktop in the active profile, oldest first.
+    History {
+        desktop_id: String,
+    },
+    /// Labels of deleted desktops, see `config::archive`.
+    ListArchived,
+    /// Give archived label `index` to a desktop.
+    Reattach {
+        index: usize,
+        desktop_id: String,
+    },
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 struct Response {
     ok: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    labels: Option<HashMap<String, DesktopLabel>>,
+    labels: Option<HashMap<DesktopId, DesktopLabel>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     desktop_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     label: Option<DesktopLabel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profiles: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    active_profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    history: Option<Vec<Revision>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archived: Option<Vec<ArchivedLabel>>,
+}
+
+/// Labels of the active profile, without those of desktops that no longer
+/// exist.
+fn live_labels(cfg: &Config) -> HashMap<DesktopId, DesktopLabel> {
+    cfg.labels()
+        .iter()
+        .filter(|(id, _)| !cfg.is_orphaned(**id))
+        .map(|(id, label)| (*id, label.clone()))
+        .collect()
 }
 
 pub fn start_server() {
@@ -45,7 +86,10 @@ fn run_server_forever() -> anyhow::Result<()> {
     };
     use windows::core::PCWSTR;
 
-    let mut pipe_name: Vec<u16> = PIPE_NAME.encode_utf16().chain(std::iter::once(0)).collect();
+    let mut pipe_name: Vec<u16> = config::location::instance_name(PIPE_NAME)
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
     let pipe_name = PCWSTR(pipe_name.as_mut_ptr());
 
     loop {
@@ -84,41 +128,94 @@ fn run_server_forever() -> anyhow::Result<()> {
             Ok(Request::List) => match list_labels() {
                 Ok(labels) => Response {
                     ok: true,
-                    error: None,
                     labels: Some(labels),
-                    desktop_id: None,
-                    label: None,
+                    ..Default::default()
                 },
                 Err(e) => Response {
                     ok: false,
                     error: Some(format!("list failed: {e}")),
-                    labels: None,
-                    desktop_id: None,
-                    label: None,
+                    ..Default::default()
                 },
             },
             Ok(Request::ResolveWindow { hwnd }) => match resolve_window(hwnd) {
                 Ok((desktop_id, label)) => Response {
                     ok: true,
-                    error: None,
-                    labels: None,
                     desktop_id: Some(desktop_id),
                     label: Some(label),
+                    ..Default::default()
                 },
                 Err(e) => Response {
                     ok: false,
                     error: Some(format!("resolve_window failed: {e}")),
-                    labels: None,
-                    desktop_id: None,
-                    label: None,
+                    ..Default::default()
+                },
+            },
+            Ok(Request::ListProfiles) => match list_profiles() {
+                Ok((profiles, active_profile)) => Response {
+                    ok: true,
+                    profiles: Some(profiles),
+                    active_profile: Some(active_profile),
+                    ..Default::default()
+                },
+                Err(e) => Response {
+                    ok: false,
+                    error: Some(format!("list_profiles failed: {e}")),
+                    ..Default::default()
+                },
+            },
+            Ok(Request::SetProfile { name }) => match set_profile(name.as_deref()) {
+                Ok(active_profile) => Response {
+                    ok: true,
+                    active_profile: Some(active_profile),
+                    ..Default::default()
+                },
+                Err(e) => Response {
+                    ok: false,
+                    error: Some(format!("set_profile failed: {e}")),
+                    ..Default::default()
+                },
+            },
+            Ok(Request::History { desktop_id }) => match label_history(&desktop_id) {
+                Ok(history) => Response {
+                    ok: true,
+                    history: Some(history),
+                    ..Default::default()
+                },
+                Err(e) => Response {
+                    ok: false,
+                    error: Some(format!("history failed: {e}")),
+                    ..Default::default()
+                },
+            },
+            Ok(Request::ListArchived) => match list_archived() {
+                Ok(archived) => Response {
+                    ok: true,
+                    archived: Some(archived),
+                    ..Default::default()
+                },
+                Err(e) => Response {
+                    ok: false,
+                    error: Some(format!("list_archived failed: {e}")),
+                    ..Default::default()
+                },
+            },
+            Ok(Request::Reattach { index, desktop_id }) => match reattach(index, &desktop_id) {
+                Ok(label) => Response {
+                    ok: true,
+                    desktop_id: Some(desktop_id),
+                    label: Some(label),
+                    ..Default::default()
+                },
+                Err(e) => Response {
+                    ok: false,
+                    error: Some(format!("reattach failed: {e}")),
+                    ..Default::default()
                 },
             },
             Err(e) => Response {
                 ok: false,
                 error: Some(format!("bad request: {e}")),
-                labels: None,
-                desktop_id: None,
-                label: None,
+                ..Default::default()
             },
         };
 
@@ -126,9 +223,7 @@ fn run_server_forever() -> anyhow::Result<()> {
             serde_json::to_vec(&Response {
                 ok: false,
                 error: Some(format!("serialize failed: {e}")),
-                labels: None,
-                desktop_id: None,
-                label: None,
+                ..Default::default()
             })
             .expect("serialize minimal error response")
         });
@@ -163,16 +258,45 @@ fn run_server_forever() -> anyhow::Result<()> {
         Ok(serde_json::from_slice::<Request>(&buf)?)
     }
 
-    fn list_labels() -> anyhow::Result<HashMap<String, DesktopLabel>> {
+    fn list_labels() -> anyhow::Result<HashMap<DesktopId, DesktopLabel>> {
         let (cfg, _) = config::load_or_default()?;
+        Ok(live_labels(&cfg))
+    }
 
-        let mut out = HashMap::new();
-        for (k, v) in cfg.desktops {
-            if let Some(guid) = extract_guid_from_key(&k) {
-                out.insert(guid.to_string(), v);
-            }
-        }
-        Ok(out)
+    fn list_archived() -> anyhow::Result<Vec<ArchivedLabel>> {
+        let (cfg, _) = config::load_or_default()?;
+        Ok(cfg.archived.labels)
+    }
+
+    /// Saving is enough: the running overlay reloads the changed file.
+    fn reattach(index: usize, desktop_id: &str) -> anyhow::Result<DesktopLabel> {
+        let desktop: DesktopId = desktop_id.parse()?;
+        let (mut cfg, paths) = config::load_or_default()?;
+        let entry = cfg.reattach(index, desktop)?;
+        config::save_atomic(&cfg, &paths)?;
+        Ok(entry.label)
+    }
+
+    fn list_profiles() -> anyhow::Result<(Vec<String>, String)> {
+        let (cfg, _) = config::load_or_default()?;
+        Ok((
+            cfg.profiles.keys().cloned().collect(),
+            cfg.active_profile_name().to_string(),
+        ))
+    }
+
+    /// Saving is enough: the running overlay reloads the changed file.
+    fn set_profile(name: Option<&str>) -> anyhow::Result<String> {
+        let (mut cfg, paths) = config::load_or_default()?;
+        cfg.set_active_profile(name)?;
+        config::save_atomic(&cfg, &paths)?;
+        Ok(cfg.active_profile_name().to_string())
+    }
+
+    fn label_history(desktop_id: &str) -> anyhow::Result<Vec<Revision>> {
+        let desktop: DesktopId = desktop_id.parse()?;
+        let (cfg, paths) = config::load_or_default()?;
+        Ok(History::load(&paths).revisions(&cfg, desktop).to_vec())
     }
 
     fn resolve_window(hwnd: u64) -> anyhow::Result<(String, DesktopLabel)> {
@@ -185,22 +309,14 @@ fn run_server_forever() -> anyhow::Result<()> {
         let guid = desktop
             .get_id()
             .map_err(|e| anyhow::anyhow!(format!("winvd: {e:?}")))?;
-        let desktop_id = format!("{:?}", guid);
+        let desktop_id = DesktopId::from_u128(guid.to_u128());
 
         let (cfg, _) = config::load_or_default()?;
-        let key = format!("Desktop(Guid({desktop_id}))");
-        let label = cfg.desktops.get(&key).cloned().unwrap_or_default();
-        Ok((desktop_id, label))
+        let label = cfg.labels().get(&desktop_id).cloned().unwrap_or_default();
+        Ok((desktop_id.to_string(), label))
     }
 }
 
-fn extract_guid_from_key(key: &str) -> Option<&str> {
-    // Current on-disk format: "Desktop(Guid(<GUID>))"
-    let start = key.find("Guid(")? + "Guid(".len();
-    let end = key[start..].find(')')? + start;
-    Some(&key[start..end])
-}
-
 struct HandleGuard(windows::Win32::Foundation::HANDLE);
 impl Drop for HandleGuard {
     fn drop(&mut self) {
@@ -215,23 +331,17 @@ mod tests {
     use super::*;
 
     #[test]
-    fn extracts_guid_from_key() {
-        assert_eq!(
-            extract_guid_from_key("Desktop(Guid(D178F97B-2525-4ED7-B219-6BA2AA6BE296))"),
-            Some("D178F97B-2525-4ED7-B219-6BA2AA6BE296")
-        );
-        assert_eq!(extract_guid_from_key("not a key"), None);
-    }
-
-    #[test]
-    fn extracts_guid_empty_key() {
-        assert_eq!(extract_guid_from_key(""), None);
-    }
-
-    #[test]
-    fn extracts_guid_partial_key() {
-        // Missing closing paren
-        assert_eq!(extract_guid_from_key("Desktop(Guid(ABCD"), None);
+    fn list_response_uses_canonical_desktop_ids() {
+        let id: DesktopId = "{d178f97b-2525-4ed7-b219-6ba2aa6be296}".parse().unwrap();
+        let mut labels = HashMap::new();
+        labels.insert(id, DesktopLabel::default());
+        let resp = Response {
+            ok: true,
+            labels: Some(labels),
+            ..Default::default()
+        };
+        let v = serde_json::to_value(&resp).unwrap();
+        assert!(v["labels"]["D178F97B-2525-4ED7-B219-6BA2AA6BE296"].is_object());
     }
 
     #[test]
@@ -246,6 +356,57 @@ mod tests {
         assert!(matches!(req, Request::ResolveWindow { hwnd: 12345 }));
     }
 
+    #[test]
+    fn deserializes_profile_requests() {
+        let req: Request = serde_json::from_str(r#"{"op":"list_profiles"}"#).unwrap();
+        assert!(matches!(req, Request::ListProfiles));
+        let req: Request = serde_json::from_str(r#"{"op":"set_profile","name":"Home"}"#).unwrap();
+        assert!(matches!(req, Request::SetProfile { name: Some(n) } if n == "Home"));
+        let req: Request = serde_json::from_str(r#"{"op":"set_profile"}"#).unwrap();
+        assert!(matches!(req, Request::SetProfile { name: None }));
+    }
+
+    #[test]
+    fn history_response_lists_revisions() {
+        let req: Request = serde_json::from_str(
+            r#"{"op":"history","desktop_id":"{d178f97b-2525-4ed7-b219-6ba2aa6be296}"}"#,
+        )
+        .unwrap();
+        assert!(matches!(req, Request::History { .. }));
+        let resp = Response {
+            ok: true,
+            history: Some(vec![Revision {
+                at: 1_700_000_000,
+                label: DesktopLabel {
+                    title: "Mail".into(),
+                    ..Default::default()
+                },
+            }]),
+            ..Default::default()
+        };
+        let v = serde_json::to_value(&resp).unwrap();
+        assert_eq!(v["history"][0]["at"], 1_700_000_000);
+        assert_eq!(v["history"][0]["title"], "Mail");
+    }
+
+    #[test]
+    fn list_skips_orphaned_desktops() {
+        let (live, gone) = (DesktopId::from_u128(1), DesktopId::from_u128(2));
+        let mut cfg = Config::default();
+        cfg.desktops.insert(live, DesktopLabel::default());
+        cfg.desktops.insert(gone, DesktopLabel::default());
+        cfg.reconcile(&[live], 0, u64::MAX);
+        let labels = live_labels(&cfg);
+        assert!(labels.contains_key(&live));
+        assert!(!labels.contains_key(&gone));
+
+        let req: Request = serde_json::from_str(
+            r#"{"op":"reattach","index":0,"desktop_id":"{d178f97b-2525-4ed7-b219-6ba2aa6be296}"}"#,
+        )
+        .unwrap();
+        assert!(matches!(req, Request::Reattach { index: 0, .. }));
+    }
+
     #[test]
     fn rejects_unknown_op() {
         let result = serde_json::from_str::<Request>(r#"{"op":"unknown"}"#);
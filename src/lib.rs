@@ -1,8 +1,14 @@
 pub mod autorun;
+pub mod bidi;
+pub mod chord;
+pub mod color;
 pub mod config;
 pub mod core;
 pub mod hotkeys;
+pub mod i18n;
 pub mod ipc;
+pub mod markup;
+pub mod template;
 pub mod utils;
 
 // Windows-only modules
@@ -6,10 +6,14 @@ mod windows_main;
 // Windows entry point: initialize logging then delegate to module
 #[cfg(windows)]
 fn main() -> anyhow::Result<()> {
+    if let Some(path) = mddskmgr::config::location::config_arg(std::env::args_os().skip(1)) {
+        mddskmgr::config::location::set_cli_override(path);
+    }
     // Best-effort tracing setup to a rolling daily log under the app's data dir.
     // Falls back silently if initialization fails (e.g., IO errors).
     {
-        if let Ok((_, paths)) = mddskmgr::config::load_or_default() {
+        if let Ok((cfg, paths)) = mddskmgr::config::load_or_default() {
+            windows_main::apply_language(&cfg);
             std::fs::create_dir_all(&paths.log_dir).ok();
             let file_appender = tracing_appender::rolling::daily(&paths.log_dir, "mddsklbl.log");
             let (nb_writer, _guard) = tracing_appender::non_blocking(file_appender);
@@ -20,9 +24,21 @@ fn main() -> anyhow::Result<()> {
                 .with_ansi(false)
                 .with_writer(nb_writer)
                 .try_init();
-            tracing::info!("mddsklbl starting");
+            tracing::info!(
+                config=?paths.cfg_file,
+                source=?mddskmgr::config::location::current_source(),
+                "mddsklbl starting"
+            );
         }
     }
+    if let Some(to) = mddskmgr::config::format::convert_arg(std::env::args_os().skip(1)) {
+        return windows_main::convert_config(&to);
+    }
+    if let Some(cmd) =
+        mddskmgr::config::exchange::command_arg(std::env::args_os().skip(1)).transpose()
+    {
+        return windows_main::exchange_labels(cmd);
+    }
     windows_main::main()
 }
 
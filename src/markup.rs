@@ -0,0 +1,436 @@
+//! Lightweight markup in labels.
+//!
+//! - `**bold**`
+//! - `_italic_`: only at word boundaries, so `snake_case_names` stay as they
+//!   are.
+//! - `{color:#f80}orange{/color}`: any `Rgb` form; colors nest.
+//!
+//! A backslash makes the next `*`, `_`, `{`, `}` or `\` literal. Markers
+//! without a partner are shown as typed.
+
+use crate::color::Rgb;
+use crate::core::Cut;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    /// `None` keeps the overlay's text color.
+    pub color: Option<Rgb>,
+}
+
+/// Text drawn in one style.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    pub text: String,
+    pub style: Style,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Bold,
+    Italic,
+    Color(Rgb),
+    EndColor,
+}
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Marker {
+        kind: Kind,
+        raw: String,
+        can_open: bool,
+        can_close: bool,
+        matched: bool,
+    },
+}
+
+/// Split `src` into styled runs. Adjacent runs always differ in style.
+pub fn parse(src: &str) -> Vec<Run> {
+    let mut tokens = tokenize(src);
+    pair_markers(&mut tokens);
+
+    let mut runs: Vec<Run> = Vec::new();
+    let (mut bold, mut italic) = (false, false);
+    let mut colors: Vec<Rgb> = Vec::new();
+    for token in tokens {
+        let text = match token {
+            Token::Marker {
+                kind,
+                matched: true,
+                ..
+            } => {
+                match kind {
+                    Kind::Bold => bold = !bold,
+                    Kind::Italic => italic = !italic,
+                    Kind::Color(c) => colors.push(c),
+                    Kind::EndColor => {
+                        colors.pop();
+                    }
+                }
+                continue;
+            }
+            Token::Marker { raw, .. } => raw,
+            Token::Text(t) => t,
+        };
+        let style = Style {
+            bold,
+            italic,
+            color: colors.last().copied(),
+        };
+        match runs.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(&text),
+            _ => runs.push(Run { text, style }),
+        }
+    }
+    runs
+}
+
+/// The text of `src` without markup.
+pub fn plain(src: &str) -> String {
+    parse(src).into_iter().map(|r| r.text).collect()
+}
+
+/// Apply `cut`, made on the plain text of `runs`, to the runs themselves.
+/// The ellipsis takes the style of the text just before it.
+pub fn cut_runs(runs: &[Run], cut: Cut) -> Vec<Run> {
+    let mut head: Vec<Run> = Vec::new();
+    let mut tail: Vec<Run> = Vec::new();
+    let mut start = 0;
+    for run in runs {
+        let end = start + run.text.len();
+        if start < cut.head {
+            let text = run.text[..cut.head.min(end) - start].to_string();
+            head.push(Run {
+                text,
+                style: run.style,
+            });
+        }
+        if end > cut.tail {
+            let text = run.text[cut.tail.max(start) - start..].to_string();
+            tail.push(Run {
+                text,
+                style: run.style,
+            });
+        }
+        start = end;
+    }
+    let style = head
+        .last()
+        .or(tail.first())
+        .map(|r| r.style)
+        .unwrap_or_default();
+    head.push(Run {
+        text: crate::core::ELLIPSIS.to_string(),
+        style,
+    });
+    let mut out: Vec<Run> = Vec::new();
+    for run in head.into_iter().chain(tail) {
+        match out.last_mut() {
+            Some(last) if last.style == run.style => last.text.push_str(&run.text),
+            _ => out.push(run),
+        }
+    }
+    out
+}
+
+/// Lay the styles of `runs` over `lines`: the plain text of `runs` broken
+/// into lines, with the whitespace between words changed or dropped and an
+/// ellipsis where text was left out, as `core::wrap` does. Returns the runs
+/// of each line. Text not found in `runs`, such as the ellipsis, takes the
+/// style of the text before it.
+pub fn runs_for_lines<'a>(runs: &[Run], lines: impl IntoIterator<Item = &'a str>) -> Vec<Vec<Run>> {
+    let mut source = runs
+        .iter()
+        .flat_map(|r| r.text.chars().map(move |c| (c, r.style)))
+        .peekable();
+    let mut style = Style::default();
+    let mut out = Vec::new();
+    for line in lines {
+        let mut line_runs: Vec<Run> = Vec::new();
+        for c in line.chars() {
+            // Whitespace matches any amount of it, including none.
+            while let Some(&(s, st)) = source.peek()
+                && s.is_whitespace()
+            {
+                if c.is_whitespace() {
+                    style = st;
+                }
+                source.next();
+            }
+            if !c.is_whitespace()
+                && let Some(&(s, st)) = source.peek()
+                && s == c
+            {
+                style = st;
+                source.next();
+            }
+            match line_runs.last_mut() {
+                Some(last) if last.style == style => last.text.push(c),
+                _ => line_runs.push(Run {
+                    text: c.to_string(),
+                    style,
+                }),
+            }
+        }
+        out.push(line_runs);
+    }
+    out
+}
+
+fn tokenize(src: &str) -> Vec<Token> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut i = 0;
+    let is_space = |c: Option<&char>| c.is_none_or(|c| c.is_whitespace());
+    let is_word = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric());
+    while i < chars.len() {
+        let c = chars[i];
+        let prev = i.checked_sub(1).and_then(|p| chars.get(p));
+        let marker = match c {
+            '\\' if matches!(chars.get(i + 1), Some('*' | '_' | '{' | '}' | '\\')) => {
+                text.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let next = chars.get(i + 2);
+                Some((Kind::Bold, 2, !is_space(next), !is_space(prev)))
+            }
+            '_' => {
+                let next = chars.get(i + 1);
+                let open = !is_space(next) && !is_word(prev);
+                let close = !is_space(prev) && !is_word(next);
+                Some((Kind::Italic, 1, open, close))
+            }
+            '{' => {
+                let rest: String = chars[i..].iter().collect();
+                let tag = rest.find('}').map(|end| &rest[1..end]);
+                match tag {
+                    Some("/color") => Some((Kind::EndColor, 8, false, true)),
+                    Some(t) => t
+                        .strip_prefix("color:")
+                        .and_then(|c| c.parse::<Rgb>().ok())
+                        .map(|rgb| (Kind::Color(rgb), t.chars().count() + 2, true, false)),
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+        match marker {
+            Some((kind, len, can_open, can_close)) if can_open || can_close => {
+                if !text.is_empty() {
+                    tokens.push(Token::Text(std::mem::take(&mut text)));
+                }
+                tokens.push(Token::Marker {
+                    kind,
+                    raw: chars[i..i + len].iter().collect(),
+                    can_open,
+                    can_close,
+                    matched: false,
+                });
+                i += len;
+            }
+            Some((_, len, ..)) => {
+                text.extend(&chars[i..i + len]);
+                i += len;
+            }
+            None => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    tokens
+}
+
+/// Mark openers and closers that belong together. A closer pairs with the
+/// nearest open marker of its kind; openers between the two stay literal.
+fn pair_markers(tokens: &mut [Token]) {
+    let mut open: Vec<usize> = Vec::new();
+    for i in 0..tokens.len() {
+        let Token::Marker {
+            kind,
+            can_open,
+            can_close,
+            ..
+        } = tokens[i]
+        else {
+            continue;
+        };
+        let partner = |k: Kind| {
+            matches!(
+                (kind, k),
+                (Kind::EndColor, Kind::Color(_))
+                    | (Kind::Bold, Kind::Bold)
+                    | (Kind::Italic, Kind::Italic)
+            )
+        };
+        let opener = can_close
+            .then(|| {
+                open.iter().rposition(|&j| match tokens[j] {
+                    Token::Marker { kind: k, .. } => partner(k),
+                    Token::Text(_) => false,
+                })
+            })
+            .flatten();
+        if let Some(pos) = opener {
+            let j = open[pos];
+            open.truncate(pos);
+            for t in [j, i] {
+                if let Token::Marker { matched, .. } = &mut tokens[t] {
+                    *matched = true;
+                }
+            }
+        } else if can_open {
+            open.push(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORANGE: Rgb = Rgb {
+        r: 0xff,
+        g: 0x88,
+        b: 0,
+    };
+
+    fn run(text: &str, bold: bool, italic: bool, color: Option<Rgb>) -> Run {
+        Run {
+            text: text.into(),
+            style: Style {
+                bold,
+                italic,
+                color,
+            },
+        }
+    }
+
+    #[test]
+    fn plain_text_is_one_run() {
+        assert_eq!(
+            parse("Work : Tickets"),
+            [run("Work : Tickets", false, false, None)]
+        );
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn bold_italic_and_color() {
+        assert_eq!(
+            parse("**Release** _today_ {color:#f80}freeze{/color}!"),
+            [
+                run("Release", true, false, None),
+                run(" ", false, false, None),
+                run("today", false, true, None),
+                run(" ", false, false, None),
+                run("freeze", false, false, Some(ORANGE)),
+                run("!", false, false, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn styles_nest() {
+        assert_eq!(
+            parse("**a _b {color:#f80}c{color:#fff}d{/color}e{/color}_**"),
+            [
+                run("a ", true, false, None),
+                run("b ", true, true, None),
+                run("c", true, true, Some(ORANGE)),
+                run("d", true, true, Some(Rgb::WHITE)),
+                run("e", true, true, Some(ORANGE)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unmatched_markers_stay_literal() {
+        assert_eq!(plain("**half"), "**half");
+        assert_eq!(plain("a ** b ** c"), "a ** b ** c");
+        assert_eq!(plain("{color:#f80}open"), "{color:#f80}open");
+        assert_eq!(plain("close{/color}"), "close{/color}");
+        assert_eq!(plain("{color:ornge}x{/color}"), "{color:ornge}x{/color}");
+        assert_eq!(plain("{title}"), "{title}");
+    }
+
+    #[test]
+    fn colors_can_be_named() {
+        let orange = Rgb::from_u32(0xffa500);
+        assert_eq!(
+            parse("{color:orange}x{/color}"),
+            [run("x", false, false, Some(orange))]
+        );
+    }
+
+    #[test]
+    fn underscores_inside_words_are_literal() {
+        assert_eq!(plain("my_pc_name"), "my_pc_name");
+        assert_eq!(plain("_a_b_"), "a_b");
+        assert_eq!(parse("x _y_")[1], run("y", false, true, None));
+    }
+
+    #[test]
+    fn backslash_escapes_markers() {
+        assert_eq!(
+            plain(r"\*\*not bold\*\* \_x\_ \{color:#f80} \\"),
+            r"**not bold** _x_ {color:#f80} \"
+        );
+        assert_eq!(plain(r"a\b"), r"a\b");
+    }
+
+    #[test]
+    fn cutting_keeps_styles_on_both_sides() {
+        let runs = parse("**Release** freeze {color:#f80}at noon{/color}");
+        let text = plain("**Release** freeze {color:#f80}at noon{/color}");
+        let cut = Cut {
+            head: 4,
+            tail: text.len() - 3,
+        };
+        assert_eq!(
+            cut_runs(&runs, cut),
+            [
+                run("Rele\u{2026}", true, false, None),
+                run("oon", false, false, Some(ORANGE)),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapped_lines_keep_their_styles() {
+        let runs = parse("**Release**  freeze {color:#f80}at noon today{/color}");
+        assert_eq!(
+            runs_for_lines(&runs, ["Release", "freeze at", "noon\u{2026}"]),
+            [
+                vec![run("Release", true, false, None)],
+                vec![
+                    run("freeze ", false, false, None),
+                    run("at", false, false, Some(ORANGE)),
+                ],
+                vec![run("noon\u{2026}", false, false, Some(ORANGE))],
+            ]
+        );
+        // A word split between lines keeps its style on both.
+        assert_eq!(
+            runs_for_lines(&parse("_abcdef_"), ["abc", "def"]),
+            [
+                vec![run("abc", false, true, None)],
+                vec![run("def", false, true, None)],
+            ]
+        );
+    }
+
+    #[test]
+    fn crossed_markers_pair_with_the_nearest_opener() {
+        // The italic opener between ** and ** stays literal.
+        assert_eq!(parse("**a _b**"), [run("a _b", true, false, None)]);
+    }
+}
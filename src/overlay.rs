@@ -1,3 +1,9 @@
+use crate::bidi::{TextAlign, TextDirection, join_hints};
+use crate::color::{Rgb, Rgba, Theme, ThemePreset};
+use crate::core::{
+    Block, Ellipsis, TextLayout, TextMeasure, TwoLineStyle, elide, two_line_layout, within_chars,
+};
+use crate::markup::{self, Run};
 use crate::utils::to_utf16;
 use anyhow::{Result, anyhow};
 use once_cell::sync::OnceCell;
@@ -16,7 +22,7 @@ use windows::Win32::UI::WindowsAndMessaging::{
     SPI_GETWORKAREA, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, SystemParametersInfoW, ULW_ALPHA,
     UpdateLayeredWindow,
 };
-use windows::core::{Interface, PCWSTR};
+use windows::core::{IUnknown, Interface, PCWSTR};
 
 /// RAII wrapper for HDC that automatically releases the DC on drop
 struct ScopedDC {
@@ -121,6 +127,96 @@ impl Drop for ScopedFont {
     }
 }
 
+/// How much text the overlay shows before cutting it with an ellipsis.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fit {
+    /// Widest the overlay may get; `None` for the work area width.
+    pub max_width_px: Option<u32>,
+    /// Most grapheme clusters per line of text.
+    pub max_chars: Option<usize>,
+    pub ellipsis: Ellipsis,
+}
+
+impl Fit {
+    /// The runs of `text`, cut to `max_chars`.
+    fn runs(&self, text: &str) -> Vec<Run> {
+        let runs = markup::parse(text);
+        let Some(max) = self.max_chars else {
+            return runs;
+        };
+        let plain: String = runs.iter().map(|r| r.text.as_str()).collect();
+        match elide(&plain, self.ellipsis, |s| within_chars(s, max)) {
+            Some(cut) => markup::cut_runs(&runs, cut),
+            None => runs,
+        }
+    }
+}
+
+/// Colors, box metrics and text direction of the overlay.
+#[derive(Debug, Clone, Copy)]
+pub struct Look {
+    pub theme: Theme,
+    /// Opacity of the whole overlay, 0.0 to 1.0.
+    pub opacity: f32,
+    /// `None` for a radius that follows the font size.
+    pub corner_radius_px: Option<u32>,
+    /// Space around the text; `None` to use the margin.
+    pub padding_px: Option<u32>,
+    pub direction: TextDirection,
+    pub align: TextAlign,
+}
+
+impl Default for Look {
+    fn default() -> Self {
+        Self {
+            theme: ThemePreset::Dark.theme(),
+            opacity: 1.0,
+            corner_radius_px: None,
+            padding_px: None,
+            direction: TextDirection::default(),
+            align: TextAlign::default(),
+        }
+    }
+}
+
+impl Look {
+    fn radius(&self, font_px: i32) -> f32 {
+        self.corner_radius_px
+            .map_or((font_px as f32 * 0.5).clamp(6.0, 20.0), |r| r as f32)
+    }
+
+    fn padding(&self, margin_px: i32) -> i32 {
+        self.padding_px.map_or(margin_px, |p| p as i32)
+    }
+}
+
+/// Settings for `Overlay::draw_two_lines_top_anchor`.
+#[derive(Debug, Clone, Copy)]
+pub struct TwoLines {
+    /// Description size relative to the title, in percent.
+    pub description_percent: u32,
+    /// Description opacity, 0.0 to 1.0.
+    pub description_opacity: f32,
+    /// Lines for each of title and description.
+    pub max_lines: usize,
+}
+
+/// What `render_and_update` draws.
+enum Content<'a> {
+    Line {
+        runs: &'a [Run],
+        hints: &'a str,
+        rtl: bool,
+    },
+    Lines {
+        layout: &'a TextLayout,
+        /// The runs of each line of `layout`; none for the hints.
+        runs: &'a [Vec<Run>],
+        description_opacity: f32,
+        rtl: bool,
+    },
+}
+
 #[derive(Clone)]
 pub struct Overlay {
     hwnd: HWND,
@@ -138,6 +234,15 @@ impl Overlay {
         })
     }
 
+    /// This overlay drawing in another font.
+    pub fn with_font(&self, font_family: &str, font_size_dip: u32) -> Self {
+        Self {
+            hwnd: self.hwnd,
+            font_family: font_family.to_string(),
+            font_px: font_size_dip as i32,
+        }
+    }
+
     pub fn draw_line_top_center(&self, text: &str, margin_px: i32) -> Result<()> {
         self.draw_line_top_center_with_hints(text, "", margin_px)
     }
@@ -149,9 +254,13 @@ impl Overlay {
         margin_px: i32,
     ) -> Result<()> {
         tracing::debug!(text=%text, hints=%hints, "overlay: draw_line_top_center");
-        let (w, h) = self.measure_text_with_hints(text, hints)?;
-        let w_pad = w + margin_px * 2;
-        let h_pad = h + margin_px * 2;
+        let runs = markup::parse(text);
+        let look = Look::default();
+        let pad = look.padding(margin_px);
+        let rtl = look.direction.is_rtl(&markup::plain(text));
+        let (w, h) = self.measure_text_with_hints(&runs, hints, rtl)?;
+        let w_pad = w + pad * 2;
+        let h_pad = h + pad * 2;
 
         // Compute top-center position on the primary work area
         let mut work: RECT = RECT::default();
@@ -167,44 +276,119 @@ impl Overlay {
         let x = work.left + (work_w - w_pad) / 2;
         let y = work.top + margin_px;
 
-        let res = self.render_and_update(text, hints, x, y, w_pad, h_pad, margin_px);
+        let content = Content::Line {
+            runs: &runs,
+            hints,
+            rtl,
+        };
+        let res = self.render_and_update(&content, &look, x, y, w_pad, h_pad, pad);
         if let Err(e) = &res {
             tracing::warn!(error=?e, "overlay: render_and_update error");
         }
         res
     }
 
+    /// `text` cut to `fit`, with `hints` after it kept whole.
     pub fn draw_line_top_anchor_with_hints(
         &self,
         text: &str,
         hints: &str,
+        fit: &Fit,
+        look: &Look,
         margin_px: i32,
         anchor_ratio: f32,
     ) -> Result<()> {
-        let (w, h) = self.measure_text_with_hints(text, hints)?;
-        let w_pad = w + margin_px * 2;
-        let h_pad = h + margin_px * 2;
-        // Work area
-        let mut work: RECT = RECT::default();
-        unsafe {
-            let _ = SystemParametersInfoW(
-                SPI_GETWORKAREA,
-                0,
-                Some(&mut work as *mut _ as *mut c_void),
-                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
-            );
+        let work = work_area();
+        let pad = look.padding(margin_px);
+        let runs = self.fit_runs(text, hints, fit, &work, pad)?;
+        let rtl = look.direction.is_rtl(&markup::plain(text));
+        let (w, h) = self.measure_text_with_hints(&runs, hints, rtl)?;
+        let w_pad = w + pad * 2;
+        let h_pad = h + pad * 2;
+        let (x, y) = anchored_origin(&work, w_pad, margin_px, anchor_ratio);
+        let content = Content::Line {
+            runs: &runs,
+            hints,
+            rtl,
         };
-        let work_w = work.right - work.left;
-        let anchor_x = work.left as f32 + (work_w as f32 * anchor_ratio);
-        let mut x = anchor_x.round() as i32 - w_pad / 2;
-        if x < work.left {
-            x = work.left;
+        let res = self.render_and_update(&content, look, x, y, w_pad, h_pad, pad);
+        if let Err(e) = &res {
+            tracing::warn!(error=?e, "overlay: render_and_update error");
         }
-        if x + w_pad > work.right {
-            x = work.right - w_pad;
+        res
+    }
+
+    /// Title on top, description below in a smaller, fainter style; both
+    /// wrapped to the maximum width and clamped. `hints` get a line of their
+    /// own in the hints' size, so wrapping never splits them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_two_lines_top_anchor(
+        &self,
+        title: &str,
+        description: &str,
+        hints: &str,
+        opts: &TwoLines,
+        fit: &Fit,
+        look: &Look,
+        margin_px: i32,
+        anchor_ratio: f32,
+    ) -> Result<()> {
+        // Wrapping works on plain text; the styles are laid back over the
+        // lines afterwards.
+        let title_runs = fit.runs(title);
+        let description_runs = fit.runs(description);
+        let title: String = title_runs.iter().map(|r| r.text.as_str()).collect();
+        let description: String = description_runs.iter().map(|r| r.text.as_str()).collect();
+        let rtl = look.direction.is_rtl(if title.trim().is_empty() {
+            &description
+        } else {
+            &title
+        });
+        let work = work_area();
+        let work_w = (work.right - work.left) as u32;
+        let pad = look.padding(margin_px);
+        let max_w = fit.max_width_px.map_or(work_w, |w| w.min(work_w)) as i32 - pad * 2;
+        let style = TwoLineStyle {
+            title_px: self.font_px as f32,
+            description_px: (self.font_px as f32 * opts.description_percent as f32 / 100.0)
+                .max(8.0),
+            hints_px: hints_size(self.font_px),
+            max_width: Some(max_w.max(1) as f32),
+            max_lines: opts.max_lines,
+            align: look.align.ratio(rtl),
+        };
+        let measure = DWriteMeasure::new(&self.font_family)?;
+        let mut layout = two_line_layout(&title, &description, hints, &style, &measure);
+        let lines_of = |block: Block| {
+            layout
+                .lines
+                .iter()
+                .filter(move |l| l.block == block)
+                .map(|l| l.text.as_str())
+        };
+        let mut runs = markup::runs_for_lines(&title_runs, lines_of(Block::Title));
+        runs.extend(markup::runs_for_lines(
+            &description_runs,
+            lines_of(Block::Description),
+        ));
+        runs.resize(layout.lines.len(), Vec::new());
+        // Lines were measured without styles; bold or italic text is wider.
+        for (line, runs) in layout.lines.iter_mut().zip(&runs) {
+            if runs.iter().any(|r| r.style.bold || r.style.italic) {
+                line.width = measure.styled_width(runs, line.size_px);
+            }
         }
-        let y = work.top + margin_px;
-        let res = self.render_and_update(text, hints, x, y, w_pad, h_pad, margin_px);
+        layout.align(style.align);
+        let w_pad = (layout.width.ceil() as i32).max(1) + pad * 2;
+        let h_pad = (layout.height.ceil() as i32).max(1) + pad * 2;
+        let (x, y) = anchored_origin(&work, w_pad, margin_px, anchor_ratio);
+        let content = Content::Lines {
+            layout: &layout,
+            runs: &runs,
+            description_opacity: opts.description_opacity,
+            rtl,
+        };
+        let res = self.render_and_update(&content, look, x, y, w_pad, h_pad, pad);
         if let Err(e) = &res {
             tracing::warn!(error=?e, "overlay: render_and_update error");
         }
@@ -214,8 +398,8 @@ impl Overlay {
     #[allow(clippy::too_many_arguments)]
     fn render_and_update(
         &self,
-        text: &str,
-        hints: &str,
+        content: &Content,
+        look: &Look,
         x: i32,
         y: i32,
         width: i32,
@@ -242,31 +426,34 @@ impl Overlay {
         // RAII wrapper for bitmap - will automatically restore old object and delete bitmap
         let _scoped_bitmap = ScopedBitmap::new(mem_dc.handle(), hbm);
 
-        // Fill background (black) — no per-pixel alpha; use global alpha in blend
+        // Fill with the background color. Direct2D clears this; the GDI
+        // fallback has no per-pixel alpha and uses it as is, with a global
+        // alpha in the blend.
         let stride = (width * 4) as usize;
         let total = (height as usize) * stride;
+        let bg = look.theme.background;
         unsafe {
             let buf = std::slice::from_raw_parts_mut(bits as *mut u8, total);
             for y in 0..height as usize {
                 let row = &mut buf[y * stride..(y + 1) * stride];
                 for px in row.chunks_exact_mut(4) {
-                    // BGRA order; opaque RGB, alpha ignored because we use global SourceConstantAlpha
-                    px[0] = 0; // B
-                    px[1] = 0; // G
-                    px[2] = 0; // R
-                    px[3] = 0; // A (transparent; D2D will draw alpha)
+                    // BGRA order
+                    px[0] = bg.b;
+                    px[1] = bg.g;
+                    px[2] = bg.r;
+                    px[3] = 0; // transparent; D2D will draw alpha
                 }
             }
         }
 
         // Prefer Direct2D per-pixel alpha; fallback to GDI if it fails
-        let d2d_result = render_d2d_with_hints(
+        let d2d_result = render_d2d(
             mem_dc.handle(),
             width,
             height,
             pad,
-            text,
-            hints,
+            content,
+            look,
             &self.font_family,
             self.font_px,
         );
@@ -279,36 +466,82 @@ impl Overlay {
             );
 
             // Fallback to GDI rendering
-            let font = create_font(&self.font_family, self.font_px);
-            let _scoped_font = ScopedFont::new(mem_dc.handle(), font);
-
-            unsafe {
-                SetBkMode(mem_dc.handle(), TRANSPARENT);
-                let white = COLORREF(0x00FFFFFF);
-                let _ = SetTextColor(mem_dc.handle(), white);
-                let mut rc = RECT {
-                    left: pad,
-                    top: pad,
-                    right: width - pad,
-                    bottom: height - pad,
-                };
-                let mut wtext: Vec<u16> = if hints.is_empty() {
-                    text.encode_utf16().collect()
-                } else {
-                    format!("{text} {hints}").encode_utf16().collect()
-                };
-                let _ = DrawTextW(
-                    mem_dc.handle(),
-                    &mut wtext,
-                    &mut rc,
-                    DT_LEFT | DT_VCENTER | DT_SINGLELINE | DT_NOPREFIX,
-                );
+            unsafe { SetBkMode(mem_dc.handle(), TRANSPARENT) };
+            match content {
+                Content::Line { runs, hints, rtl } => {
+                    let font = create_font(&self.font_family, self.font_px);
+                    let _scoped_font = ScopedFont::new(mem_dc.handle(), font);
+                    let mut rc = RECT {
+                        left: pad,
+                        top: pad,
+                        right: width - pad,
+                        bottom: height - pad,
+                    };
+                    // GDI draws one style; show the text without markup.
+                    let text: String = runs.iter().map(|r| r.text.as_str()).collect();
+                    let mut wtext: Vec<u16> =
+                        join_hints(&text, hints).text.encode_utf16().collect();
+                    unsafe {
+                        let _ = SetTextColor(
+                            mem_dc.handle(),
+                            COLORREF(look.theme.foreground.rgb().to_colorref()),
+                        );
+                        let _ = DrawTextW(
+                            mem_dc.handle(),
+                            &mut wtext,
+                            &mut rc,
+                            gdi_flow(*rtl, look.align) | DT_VCENTER | DT_SINGLELINE | DT_NOPREFIX,
+                        );
+                    }
+                }
+                Content::Lines {
+                    layout,
+                    description_opacity,
+                    rtl,
+                    ..
+                } => {
+                    // Lines are already placed; only the reading order is
+                    // left. Hints always read left to right. GDI draws one
+                    // style, so markup is not shown.
+                    for line in &layout.lines {
+                        let reading = if *rtl && line.block != Block::Hints {
+                            DT_RTLREADING
+                        } else {
+                            DRAW_TEXT_FORMAT(0)
+                        };
+                        let font = create_font(&self.font_family, line.size_px.round() as i32);
+                        let _scoped_font = ScopedFont::new(mem_dc.handle(), font);
+                        // No per-pixel alpha here: fade the description
+                        // towards the background instead.
+                        let opacity = match line.block {
+                            Block::Title => 1.0,
+                            Block::Description | Block::Hints => *description_opacity,
+                        };
+                        let color = blend(look.theme.background, look.theme.foreground, opacity);
+                        let mut rc = RECT {
+                            left: pad + line.x as i32,
+                            top: pad + line.y as i32,
+                            right: width - pad,
+                            bottom: height - pad,
+                        };
+                        let mut wtext: Vec<u16> = line.text.encode_utf16().collect();
+                        unsafe {
+                            let _ = SetTextColor(mem_dc.handle(), COLORREF(color));
+                            let _ = DrawTextW(
+                                mem_dc.handle(),
+                                &mut wtext,
+                                &mut rc,
+                                reading | DT_LEFT | DT_TOP | DT_SINGLELINE | DT_NOPREFIX,
+                            );
+                        }
+                    }
+                }
             }
-            // Font is automatically cleaned up by ScopedFont
+            // Fonts are automatically cleaned up by ScopedFont
         }
 
         // Apply a rounded window region to clip hit-testing and visuals
-        let radius = (self.font_px / 2).clamp(6, 20);
+        let radius = look.radius(self.font_px).round() as i32;
         let hrgn = unsafe { CreateRoundRectRgn(0, 0, width, height, radius * 2, radius * 2) };
         unsafe {
             let _ = SetWindowRgn(self.hwnd, hrgn, true);
@@ -322,7 +555,14 @@ impl Overlay {
             cy: height,
         };
         let alpha_format = if d2d_ok { 1u8 } else { 0u8 };
-        let src_const = if d2d_ok { 255u8 } else { 200u8 };
+        // Without per-pixel alpha the text fades with the background, so keep
+        // the GDI fallback readable.
+        let window_alpha = if d2d_ok {
+            1.0
+        } else {
+            (f32::from(bg.a) / 255.0).max(200.0 / 255.0)
+        };
+        let src_const = (255.0 * window_alpha * look.opacity.clamp(0.0, 1.0)).round() as u8;
         let blend = BLENDFUNCTION {
             BlendOp: 0u8,
             BlendFlags: 0u8,
@@ -365,7 +605,38 @@ impl Overlay {
         Ok(())
     }
 
-    fn measure_text_with_hints(&self, text: &str, hints: &str) -> Result<(i32, i32)> {
+    /// The runs of `text`, cut so that the line and its hints stay within
+    /// `fit` and the work area.
+    fn fit_runs(
+        &self,
+        text: &str,
+        hints: &str,
+        fit: &Fit,
+        work: &RECT,
+        pad: i32,
+    ) -> Result<Vec<Run>> {
+        let runs = markup::parse(text);
+        let plain: String = runs.iter().map(|r| r.text.as_str()).collect();
+        let measure = DWriteMeasure::new(&self.font_family)?;
+        let work_w = (work.right - work.left).max(0) as u32;
+        let mut max_w =
+            fit.max_width_px.map_or(work_w, |w| w.min(work_w)) as f32 - (pad * 2) as f32;
+        if !hints.is_empty() {
+            let small = hints_size(self.font_px);
+            max_w -= measure.width(" ", self.font_px as f32) + measure.width(hints, small);
+        }
+        // Measured without styles; bold text may come out a little wider.
+        let fits = |s: &str| {
+            fit.max_chars.is_none_or(|max| within_chars(s, max))
+                && measure.width(s, self.font_px as f32) <= max_w
+        };
+        Ok(match elide(&plain, fit.ellipsis, fits) {
+            Some(cut) => markup::cut_runs(&runs, cut),
+            None => runs,
+        })
+    }
+
+    fn measure_text_with_hints(&self, runs: &[Run], hints: &str, rtl: bool) -> Result<(i32, i32)> {
         // Use DirectWrite for accurate measurement (apply smaller font to hints)
         let factory = get_dwrite_factory()?;
         let family_u16 = to_utf16(&self.font_family);
@@ -379,20 +650,17 @@ impl Overlay {
                 self.font_px as f32,
                 PCWSTR(windows::core::w!("en-US").as_wide().as_ptr()),
             )?;
-            let combined = if hints.is_empty() {
-                text.to_string()
-            } else {
-                format!("{text} {hints}")
-            };
-            let s = to_utf16(&combined);
+            set_flow(&tf, rtl, TextAlign::Start)?;
+            let text: String = runs.iter().map(|r| r.text.as_str()).collect();
+            let joined = join_hints(&text, hints);
+            let s = to_utf16(&joined.text);
             let layout = factory.CreateTextLayout(&s[..s.len() - 1], &tf, 4096.0, 4096.0)?;
+            apply_runs(&layout, runs, joined.label_start, None, 1.0)?;
             if !hints.is_empty() {
-                let hint_start = text.encode_utf16().count() + 1; // +1 for the space
-                let hint_len = hints.encode_utf16().count();
-                let small = (self.font_px as f32 * 0.7).max(8.0);
+                let small = hints_size(self.font_px);
                 let range = DWRITE_TEXT_RANGE {
-                    startPosition: hint_start as u32,
-                    length: hint_len as u32,
+                    startPosition: joined.hints_start,
+                    length: joined.hints_len,
                 };
                 let _ = layout.SetFontSize(small, range);
             }
@@ -405,6 +673,204 @@ impl Overlay {
     }
 }
 
+/// Apply the styles of `runs`, which start at UTF-16 offset `start` of the
+/// text, to `layout`. Colors need a render target for their brushes and are
+/// skipped without one, which is fine for measuring; they are drawn at
+/// `opacity`.
+fn apply_runs(
+    layout: &IDWriteTextLayout,
+    runs: &[Run],
+    mut start: u32,
+    target: Option<&ID2D1RenderTarget>,
+    opacity: f32,
+) -> Result<()> {
+    for run in runs {
+        let length = run.text.encode_utf16().count() as u32;
+        let range = DWRITE_TEXT_RANGE {
+            startPosition: start,
+            length,
+        };
+        unsafe {
+            if run.style.bold {
+                layout.SetFontWeight(DWRITE_FONT_WEIGHT_BOLD, range)?;
+            }
+            if run.style.italic {
+                layout.SetFontStyle(DWRITE_FONT_STYLE_ITALIC, range)?;
+            }
+            if let (Some(color), Some(rt)) = (run.style.color, target) {
+                let [r, g, b] = color.to_f32();
+                let brush = rt.CreateSolidColorBrush(
+                    &D2D1_COLOR_F {
+                        r,
+                        g,
+                        b,
+                        a: opacity,
+                    },
+                    None,
+                )?;
+                layout.SetDrawingEffect(&brush.cast::<IUnknown>()?, range)?;
+            }
+        }
+        start += length;
+    }
+    Ok(())
+}
+
+/// Size of the hotkey hints next to text of `font_px`.
+fn hints_size(font_px: i32) -> f32 {
+    (font_px as f32 * 0.7).max(8.0)
+}
+
+/// `color` with its alpha scaled by `opacity`.
+fn d2d_color(color: Rgba, opacity: f32) -> D2D1_COLOR_F {
+    let [r, g, b, a] = color.to_f32();
+    D2D1_COLOR_F {
+        r,
+        g,
+        b,
+        a: a * opacity,
+    }
+}
+
+/// `COLORREF` value of `fg` drawn at `opacity` over `bg`.
+fn blend(bg: Rgba, fg: Rgba, opacity: f32) -> u32 {
+    let mix = |b: u8, f: u8| (f32::from(b) + (f32::from(f) - f32::from(b)) * opacity).round() as u8;
+    Rgb {
+        r: mix(bg.r, fg.r),
+        g: mix(bg.g, fg.g),
+        b: mix(bg.b, fg.b),
+    }
+    .to_colorref()
+}
+
+/// The primary monitor's work area.
+fn work_area() -> RECT {
+    let mut work: RECT = RECT::default();
+    unsafe {
+        let _ = SystemParametersInfoW(
+            SPI_GETWORKAREA,
+            0,
+            Some(&mut work as *mut _ as *mut c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        );
+    };
+    work
+}
+
+/// Top-left corner for a box `w_pad` wide, centred on `anchor_ratio` of the
+/// work area width and kept inside it.
+fn anchored_origin(work: &RECT, w_pad: i32, margin_px: i32, anchor_ratio: f32) -> (i32, i32) {
+    let work_w = work.right - work.left;
+    let anchor_x = work.left as f32 + (work_w as f32 * anchor_ratio);
+    let mut x = anchor_x.round() as i32 - w_pad / 2;
+    if x < work.left {
+        x = work.left;
+    }
+    if x + w_pad > work.right {
+        x = work.right - w_pad;
+    }
+    (x, work.top + margin_px)
+}
+
+fn text_format(
+    factory: &IDWriteFactory,
+    family: &[u16],
+    size_px: f32,
+) -> Result<IDWriteTextFormat> {
+    unsafe {
+        let tf = factory.CreateTextFormat(
+            PCWSTR(family.as_ptr()),
+            None,
+            DWRITE_FONT_WEIGHT_NORMAL,
+            DWRITE_FONT_STYLE_NORMAL,
+            DWRITE_FONT_STRETCH_NORMAL,
+            size_px,
+            PCWSTR(windows::core::w!("en-US").as_wide().as_ptr()),
+        )?;
+        tf.SetWordWrapping(DWRITE_WORD_WRAPPING_NO_WRAP)?;
+        Ok(tf)
+    }
+}
+
+/// Reading direction and alignment of text laid out with `tf`.
+fn set_flow(tf: &IDWriteTextFormat, rtl: bool, align: TextAlign) -> Result<()> {
+    let direction = if rtl {
+        DWRITE_READING_DIRECTION_RIGHT_TO_LEFT
+    } else {
+        DWRITE_READING_DIRECTION_LEFT_TO_RIGHT
+    };
+    // Leading and trailing follow the reading direction, like `TextAlign`.
+    let alignment = match align {
+        TextAlign::Start => DWRITE_TEXT_ALIGNMENT_LEADING,
+        TextAlign::Center => DWRITE_TEXT_ALIGNMENT_CENTER,
+        TextAlign::End => DWRITE_TEXT_ALIGNMENT_TRAILING,
+    };
+    unsafe {
+        tf.SetReadingDirection(direction)?;
+        tf.SetTextAlignment(alignment)?;
+    }
+    Ok(())
+}
+
+/// `DrawTextW` flags for the same: GDI alignment is by side, not direction.
+fn gdi_flow(rtl: bool, align: TextAlign) -> DRAW_TEXT_FORMAT {
+    let side = match (align, rtl) {
+        (TextAlign::Center, _) => DT_CENTER,
+        (TextAlign::Start, false) | (TextAlign::End, true) => DT_LEFT,
+        (TextAlign::Start, true) | (TextAlign::End, false) => DT_RIGHT,
+    };
+    if rtl { side | DT_RTLREADING } else { side }
+}
+
+/// `TextMeasure` backed by DirectWrite, for the layout model in `core`.
+struct DWriteMeasure {
+    factory: &'static IDWriteFactory,
+    family: Vec<u16>,
+}
+
+impl DWriteMeasure {
+    fn new(family: &str) -> Result<Self> {
+        Ok(Self {
+            factory: get_dwrite_factory()?,
+            family: to_utf16(family),
+        })
+    }
+
+    /// Width of the text of `runs` in their styles.
+    fn styled_width(&self, runs: &[Run], size_px: f32) -> f32 {
+        let text: String = runs.iter().map(|r| r.text.as_str()).collect();
+        self.metrics(&text, size_px, runs)
+            .map_or(0.0, |m| m.widthIncludingTrailingWhitespace)
+    }
+
+    /// Metrics of `text`, styled by `runs` if there are any.
+    fn metrics(&self, text: &str, size_px: f32, runs: &[Run]) -> Result<DWRITE_TEXT_METRICS> {
+        let tf = text_format(self.factory, &self.family, size_px)?;
+        let s = to_utf16(text);
+        unsafe {
+            let layout =
+                self.factory
+                    .CreateTextLayout(&s[..s.len() - 1], &tf, 100_000.0, 100_000.0)?;
+            apply_runs(&layout, runs, 0, None, 1.0)?;
+            let mut m = DWRITE_TEXT_METRICS::default();
+            layout.GetMetrics(&mut m)?;
+            Ok(m)
+        }
+    }
+}
+
+impl TextMeasure for DWriteMeasure {
+    fn width(&self, text: &str, size_px: f32) -> f32 {
+        self.metrics(text, size_px, &[])
+            .map_or(0.0, |m| m.widthIncludingTrailingWhitespace)
+    }
+
+    fn line_height(&self, size_px: f32) -> f32 {
+        self.metrics("Ag", size_px, &[])
+            .map_or(size_px * 1.3, |m| m.height)
+    }
+}
+
 fn create_font(face: &str, px: i32) -> HFONT {
     let height = -px; // negative height means character height in logical units
     let wface = to_utf16(face);
@@ -445,13 +911,13 @@ fn get_d2d_factory() -> Result<&'static ID2D1Factory> {
 }
 
 #[allow(clippy::too_many_arguments)]
-fn render_d2d_with_hints(
+fn render_d2d(
     hdc: HDC,
     width: i32,
     height: i32,
     pad: i32,
-    text: &str,
-    hints: &str,
+    content: &Content,
+    look: &Look,
     font: &str,
     font_px: i32,
 ) -> Result<()> {
@@ -486,15 +952,9 @@ fn render_d2d_with_hints(
         }));
 
         let base: ID2D1RenderTarget = rt.cast()?;
-        let bg = base.CreateSolidColorBrush(
-            &D2D1_COLOR_F {
-                r: 0.0,
-                g: 0.0,
-                b: 0.0,
-                a: 0.35,
-            },
-            None,
-        )?;
+        let theme = &look.theme;
+        let bg = base.CreateSolidColorBrush(&d2d_color(theme.background, 1.0), None)?;
+        let radius = look.radius(font_px);
         let rounded = D2D1_ROUNDED_RECT {
             rect: D2D_RECT_F {
                 left: 0.0,
@@ -502,12 +962,88 @@ fn render_d2d_with_hints(
                 right: width as f32,
                 bottom: height as f32,
             },
-            radiusX: (font_px as f32 * 0.5).clamp(6.0, 20.0),
-            radiusY: (font_px as f32 * 0.5).clamp(6.0, 20.0),
+            radiusX: radius,
+            radiusY: radius,
         };
         base.FillRoundedRectangle(&rounded, &bg);
+        if theme.border_px > 0 && theme.border.a > 0 {
+            // Strokes are centred on the outline; keep them inside the window.
+            let stroke = theme.border_px as f32;
+            let half = stroke / 2.0;
+            let inset = D2D1_ROUNDED_RECT {
+                rect: D2D_RECT_F {
+                    left: half,
+                    top: half,
+                    right: width as f32 - half,
+                    bottom: height as f32 - half,
+                },
+                radiusX: (radius - half).max(0.0),
+                radiusY: (radius - half).max(0.0),
+            };
+            let border = base.CreateSolidColorBrush(&d2d_color(theme.border, 1.0), None)?;
+            base.DrawRoundedRectangle(&inset, &border, stroke, None::<&ID2D1StrokeStyle>);
+        }
 
         let dwrite = get_dwrite_factory()?;
+        match content {
+            Content::Line { runs, hints, rtl } => draw_d2d_line(
+                &base, dwrite, width, height, pad, runs, hints, *rtl, look, font, font_px,
+            )?,
+            Content::Lines {
+                layout,
+                runs,
+                description_opacity,
+                rtl,
+            } => {
+                let family = to_utf16(font);
+                for (line, runs) in layout.lines.iter().zip(runs.iter()) {
+                    let opacity = match line.block {
+                        Block::Title => 1.0,
+                        Block::Description | Block::Hints => *description_opacity,
+                    };
+                    let brush =
+                        base.CreateSolidColorBrush(&d2d_color(theme.foreground, opacity), None)?;
+                    let tf = text_format(dwrite, &family, line.size_px)?;
+                    // `line.x` already aligns the line; the layout only orders
+                    // it. Hints always read left to right.
+                    set_flow(&tf, *rtl && line.block != Block::Hints, TextAlign::Start)?;
+                    let s16 = to_utf16(&line.text);
+                    let text_layout = dwrite.CreateTextLayout(
+                        &s16[..s16.len() - 1],
+                        &tf,
+                        line.width + 1.0,
+                        (height - pad) as f32,
+                    )?;
+                    apply_runs(&text_layout, runs, 0, Some(&base), opacity)?;
+                    let origin = D2D_POINT_2F {
+                        x: pad as f32 + line.x,
+                        y: pad as f32 + line.y,
+                    };
+                    base.DrawTextLayout(origin, &text_layout, &brush, D2D1_DRAW_TEXT_OPTIONS_NONE);
+                }
+            }
+        }
+
+        base.EndDraw(None, None)?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_d2d_line(
+    base: &ID2D1RenderTarget,
+    dwrite: &IDWriteFactory,
+    width: i32,
+    height: i32,
+    pad: i32,
+    runs: &[Run],
+    hints: &str,
+    rtl: bool,
+    look: &Look,
+    font: &str,
+    font_px: i32,
+) -> Result<()> {
+    unsafe {
         let font_u16 = to_utf16(font);
         let tf = dwrite.CreateTextFormat(
             PCWSTR(font_u16.as_ptr()),
@@ -518,44 +1054,33 @@ fn render_d2d_with_hints(
             font_px as f32,
             PCWSTR(windows::core::w!("en-US").as_wide().as_ptr()),
         )?;
-        let combined = if hints.is_empty() {
-            text.to_string()
-        } else {
-            format!("{text} {hints}")
-        };
-        let s16 = to_utf16(&combined);
+        tf.SetWordWrapping(DWRITE_WORD_WRAPPING_NO_WRAP)?;
+        set_flow(&tf, rtl, look.align)?;
+        let text: String = runs.iter().map(|r| r.text.as_str()).collect();
+        let joined = join_hints(&text, hints);
+        let s16 = to_utf16(&joined.text);
+        // The box is the text area, so alignment has room to act on.
         let layout = dwrite.CreateTextLayout(
             &s16[..s16.len() - 1],
             &tf,
-            (width - pad) as f32,
+            (width - pad * 2).max(1) as f32,
             (height - pad) as f32,
         )?;
+        apply_runs(&layout, runs, joined.label_start, Some(base), 1.0)?;
         if !hints.is_empty() {
-            let hint_start = text.encode_utf16().count() + 1;
-            let hint_len = hints.encode_utf16().count();
-            let small = (font_px as f32 * 0.7).max(8.0);
+            let small = hints_size(font_px);
             let range = DWRITE_TEXT_RANGE {
-                startPosition: hint_start as u32,
-                length: hint_len as u32,
+                startPosition: joined.hints_start,
+                length: joined.hints_len,
             };
             let _ = layout.SetFontSize(small, range);
         }
-        let fg = base.CreateSolidColorBrush(
-            &D2D1_COLOR_F {
-                r: 1.0,
-                g: 1.0,
-                b: 1.0,
-                a: 1.0,
-            },
-            None,
-        )?;
+        let fg = base.CreateSolidColorBrush(&d2d_color(look.theme.foreground, 1.0), None)?;
         let origin = D2D_POINT_2F {
             x: pad as f32,
             y: pad as f32,
         };
         base.DrawTextLayout(origin, &layout, &fg, D2D1_DRAW_TEXT_OPTIONS_NONE);
-
-        base.EndDraw(None, None)?;
     }
     Ok(())
 }
@@ -0,0 +1,705 @@
+//! Templates for the overlay line.
+//!
+//! A template is text with placeholders in braces:
+//!
+//! - `{title}`, `{description}`: the desktop's label.
+//! - `{index}`, `{count}`: the desktop's position (from 1) and the number of
+//!   desktops.
+//! - `{os_name}`: the name given to the desktop in Windows.
+//! - `{date}`, `{time}`: the local date (`2024-05-31`) and time (`14:05`);
+//!   `{time_seconds}` adds seconds (`14:05:09`).
+//! - `{countdown}`: time left until the desktop's deadline (`2h 10m`); blank
+//!   without a deadline or once it has passed.
+//! - `{hostname}`, `{user}`: the computer and user name.
+//!
+//! `{title|Desktop}` falls back to the text after `|` when the value is blank.
+//! `{#description} : {description}{/description}` renders its body only when
+//! `description` is not blank, `{^description}…{/description}` only when it
+//! is. `{{` and `}}` stand for literal braces.
+//!
+//! `Template::next_change` tells when the rendered text will next differ, so
+//! the overlay only redraws when something visible changes.
+
+use crate::i18n::tr;
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+/// The line shown when neither the desktop nor the appearance sets one. This
+/// is what the config stores; it is shown as `default_template`, with the
+/// fallback title in the current language.
+pub const DEFAULT_TEMPLATE: &str = "{title|Desktop}{#description} : {description}{/description}";
+
+/// `DEFAULT_TEMPLATE` with the fallback title translated.
+pub fn default_template() -> String {
+    default_template_with(tr("template.untitled"))
+}
+
+fn default_template_with(untitled: &str) -> String {
+    // A fallback ends at the first brace, so the text must not contain any.
+    let untitled = untitled.replace(['{', '}'], "");
+    format!("{{title|{untitled}}}{{#description}} : {{description}}{{/description}}")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Description,
+    Index,
+    Count,
+    OsName,
+    Date,
+    Time,
+    TimeSeconds,
+    Countdown,
+    Hostname,
+    User,
+}
+
+/// A parsed template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field {
+        field: Field,
+        fallback: Option<String>,
+    },
+    Section {
+        field: Field,
+        inverted: bool,
+        body: Vec<Part>,
+    },
+}
+
+/// An open section: its field, whether it is inverted, and its column.
+type Opening = (Field, bool, usize);
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("column {column}: {message}")]
+pub struct TemplateError {
+    /// 1-based character position of the problem.
+    pub column: usize,
+    pub message: String,
+}
+
+/// Local wall-clock time for `{date}` and `{time}`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LocalTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub millisecond: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("expected a local time like \"2024-05-31 18:00\", got {0:?}")]
+pub struct BadLocalTime(pub String);
+
+/// Values for the placeholders. Unknown values render as empty text.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub title: String,
+    pub description: String,
+    /// Position of the desktop, starting at 1.
+    pub index: Option<usize>,
+    pub count: Option<usize>,
+    pub os_name: String,
+    pub now: Option<LocalTime>,
+    /// The desktop's deadline, for `{countdown}`.
+    pub deadline: Option<LocalTime>,
+    pub hostname: String,
+    pub user: String,
+}
+
+const FIELDS: [(&str, Field); 11] = [
+    ("title", Field::Title),
+    ("description", Field::Description),
+    ("index", Field::Index),
+    ("count", Field::Count),
+    ("os_name", Field::OsName),
+    ("date", Field::Date),
+    ("time", Field::Time),
+    ("time_seconds", Field::TimeSeconds),
+    ("countdown", Field::Countdown),
+    ("hostname", Field::Hostname),
+    ("user", Field::User),
+];
+
+impl Field {
+    pub fn name(self) -> &'static str {
+        FIELDS
+            .iter()
+            .find(|(_, f)| *f == self)
+            .map_or("", |(n, _)| n)
+    }
+
+    fn parse(name: &str, column: usize) -> Result<Self, TemplateError> {
+        let name = name.trim();
+        FIELDS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, f)| *f)
+            .ok_or_else(|| TemplateError {
+                column,
+                message: format!("unknown placeholder {{{name}}}"),
+            })
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Context {
+    /// A context with the computer and user name taken from the environment.
+    pub fn from_env() -> Self {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|n| std::env::var(n).ok())
+                .unwrap_or_default()
+        };
+        Self {
+            hostname: var(&["COMPUTERNAME", "HOSTNAME"]),
+            user: var(&["USERNAME", "USER"]),
+            ..Default::default()
+        }
+    }
+
+    pub fn value(&self, field: Field) -> Cow<'_, str> {
+        let number = |n: Option<usize>| n.map_or(Cow::Borrowed(""), |n| n.to_string().into());
+        match field {
+            Field::Title => Cow::Borrowed(&self.title),
+            Field::Description => Cow::Borrowed(&self.description),
+            Field::Index => number(self.index),
+            Field::Count => number(self.count),
+            Field::OsName => Cow::Borrowed(&self.os_name),
+            Field::Date => self.now.map_or(Cow::Borrowed(""), |t| {
+                format!("{:04}-{:02}-{:02}", t.year, t.month, t.day).into()
+            }),
+            Field::Time => self.now.map_or(Cow::Borrowed(""), |t| {
+                format!("{:02}:{:02}", t.hour, t.minute).into()
+            }),
+            Field::TimeSeconds => self.now.map_or(Cow::Borrowed(""), |t| {
+                format!("{:02}:{:02}:{:02}", t.hour, t.minute, t.second).into()
+            }),
+            Field::Countdown => match self.remaining() {
+                Some(secs) if secs > 0 => countdown(secs as u64).into(),
+                _ => Cow::Borrowed(""),
+            },
+            Field::Hostname => Cow::Borrowed(&self.hostname),
+            Field::User => Cow::Borrowed(&self.user),
+        }
+    }
+
+    /// Whole seconds from `now` to `deadline`; negative once it has passed.
+    pub fn remaining(&self) -> Option<i64> {
+        Some(self.deadline?.seconds() - self.now?.seconds())
+    }
+
+    fn is_blank(&self, field: Field) -> bool {
+        self.value(field).trim().is_empty()
+    }
+}
+
+impl LocalTime {
+    /// Seconds since 1970-01-01 00:00 on the same wall clock, ignoring
+    /// milliseconds. Only differences between two values are meaningful.
+    pub fn seconds(&self) -> i64 {
+        // Days from civil, after Howard Hinnant's algorithm.
+        let (m, d) = (i64::from(self.month), i64::from(self.day));
+        let y = i64::from(self.year) - i64::from(m <= 2);
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+        days * 86_400
+            + i64::from(self.hour) * 3600
+            + i64::from(self.minute) * 60
+            + i64::from(self.second)
+    }
+}
+
+/// Accepts `YYYY-MM-DD HH:MM` with optional `:SS`, and `T` instead of the space.
+impl FromStr for LocalTime {
+    type Err = BadLocalTime;
+
+    fn from_str(s: &str) -> Result<Self, BadLocalTime> {
+        let bad = || BadLocalTime(s.to_string());
+        let (date, time) = s.trim().split_once([' ', 'T']).ok_or_else(bad)?;
+        let num = |part: Option<&str>, len: usize| -> Result<u16, BadLocalTime> {
+            part.filter(|p| p.len() == len && p.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|p| p.parse().ok())
+                .ok_or_else(bad)
+        };
+        let mut d = date.split('-');
+        let mut t = time.split(':');
+        let (year, month, day) = (num(d.next(), 4)?, num(d.next(), 2)?, num(d.next(), 2)?);
+        let (hour, minute) = (num(t.next(), 2)?, num(t.next(), 2)?);
+        let second = match t.next() {
+            Some(p) => num(Some(p), 2)?,
+            None => 0,
+        };
+        let days_in_month = match month {
+            2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+        if d.next().is_some()
+            || t.next().is_some()
+            || !(1..=12).contains(&month)
+            || !(1..=days_in_month).contains(&day)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return Err(bad());
+        }
+        Ok(LocalTime {
+            year,
+            month: month as u8,
+            day: day as u8,
+            hour: hour as u8,
+            minute: minute as u8,
+            second: second as u8,
+            millisecond: 0,
+        })
+    }
+}
+
+/// `3d 4h`, `2h 10m`, `45m` or `30s`, rounded down.
+pub fn countdown(secs: u64) -> String {
+    let (d, h, m) = (secs / 86_400, secs / 3600 % 24, secs / 60 % 60);
+    match secs {
+        86_400.. => format!("{d}d {h}h"),
+        3600.. => format!("{}h {m}m", secs / 3600),
+        60.. => format!("{m}m"),
+        _ => format!("{secs}s"),
+    }
+}
+
+/// Seconds until `countdown(secs)` shows something else.
+fn countdown_step(secs: u64) -> u64 {
+    let unit = match secs {
+        86_400.. => 3600,
+        60.. => 60,
+        _ => 1,
+    };
+    secs % unit + 1
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(src: &str) -> Result<Self, TemplateError> {
+        // The template itself, then each open section with the parts collected so far.
+        let mut stack: Vec<(Option<Opening>, Vec<Part>)> = vec![(None, Vec::new())];
+        let mut text = String::new();
+        let mut chars = src.chars().zip(1..).peekable();
+        let err = |column, message: String| Err(TemplateError { column, message });
+        while let Some((c, column)) = chars.next() {
+            match c {
+                '{' | '}' if chars.peek().is_some_and(|(n, _)| *n == c) => {
+                    chars.next();
+                    text.push(c);
+                }
+                '}' => {
+                    return err(
+                        column,
+                        "unmatched '}'; write '}}' for a literal brace".into(),
+                    );
+                }
+                '{' => {
+                    let mut tag = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(('}', _)) => break,
+                            Some((ch, _)) if ch != '{' => tag.push(ch),
+                            _ => {
+                                return err(
+                                    column,
+                                    "unclosed '{'; write '{{' for a literal brace".into(),
+                                );
+                            }
+                        }
+                    }
+                    let parts = &mut stack.last_mut().expect("root").1;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    if let Some(name) = tag.strip_prefix('#').or_else(|| tag.strip_prefix('^')) {
+                        let field = Field::parse(name, column)?;
+                        stack.push((Some((field, tag.starts_with('^'), column)), Vec::new()));
+                    } else if let Some(name) = tag.strip_prefix('/') {
+                        let field = Field::parse(name, column)?;
+                        let (Some((open, inverted, _)), body) = stack.pop().expect("root") else {
+                            return err(column, format!("{{/{field}}} closes no section"));
+                        };
+                        if open != field {
+                            return err(column, format!("{{/{field}}} closes {{#{open}}}"));
+                        }
+                        let parts = &mut stack.last_mut().expect("root").1;
+                        parts.push(Part::Section {
+                            field,
+                            inverted,
+                            body,
+                        });
+                    } else {
+                        let (name, fallback) = match tag.split_once('|') {
+                            Some((n, f)) => (n, Some(f.to_string())),
+                            None => (tag.as_str(), None),
+                        };
+                        let field = Field::parse(name, column)?;
+                        parts.push(Part::Field { field, fallback });
+                    }
+                }
+                _ => text.push(c),
+            }
+        }
+        let (open, mut parts) = stack.pop().expect("root");
+        if let Some((field, _, column)) = open {
+            return err(column, format!("section {{#{field}}} is not closed"));
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+}
+
+impl Template {
+    pub fn render(&self, ctx: &Context) -> String {
+        let mut out = String::new();
+        render_parts(&self.parts, ctx, &mut out);
+        out
+    }
+
+    /// How long until the rendered text changes because time has passed, or
+    /// `None` if it only changes when the context does.
+    pub fn next_change(&self, ctx: &Context) -> Option<Duration> {
+        let now = ctx.now?;
+        let secs_of_day = u64::from(now.hour) * 3600 + u64::from(now.minute) * 60;
+        let secs_of_day = secs_of_day + u64::from(now.second);
+        let remaining = ctx.remaining().filter(|r| *r > 0);
+        let steps = [
+            (Field::TimeSeconds, Some(1)),
+            (Field::Time, Some(60 - u64::from(now.second))),
+            (Field::Date, Some(86_400 - secs_of_day)),
+            (
+                Field::Countdown,
+                remaining.map(|r| countdown_step(r as u64)),
+            ),
+        ];
+        let secs = steps
+            .into_iter()
+            .filter(|(field, _)| self.uses(*field))
+            .filter_map(|(_, step)| step)
+            .min()?;
+        Some(Duration::from_millis(
+            secs * 1000 - u64::from(now.millisecond),
+        ))
+    }
+
+    /// Whether the output depends on `field`.
+    pub fn uses(&self, field: Field) -> bool {
+        fn walk(parts: &[Part], field: Field) -> bool {
+            parts.iter().any(|p| match p {
+                Part::Text(_) => false,
+                Part::Field { field: f, .. } => *f == field,
+                Part::Section { field: f, body, .. } => *f == field || walk(body, field),
+            })
+        }
+        walk(&self.parts, field)
+    }
+}
+
+fn render_parts(parts: &[Part], ctx: &Context, out: &mut String) {
+    for part in parts {
+        match part {
+            Part::Text(t) => out.push_str(t),
+            Part::Field { field, fallback } => match fallback {
+                Some(f) if ctx.is_blank(*field) => out.push_str(f),
+                _ => out.push_str(&ctx.value(*field)),
+            },
+            Part::Section {
+                field,
+                inverted,
+                body,
+            } => {
+                if ctx.is_blank(*field) == *inverted {
+                    render_parts(body, ctx, out);
+                }
+            }
+        }
+    }
+}
+
+/// Parse `template`, falling back to the default if it is invalid. The
+/// default is rendered as `default_template`.
+pub fn parse_or_default(template: &str) -> Template {
+    if template == DEFAULT_TEMPLATE {
+        return parse_default();
+    }
+    template.parse().unwrap_or_else(|e| {
+        tracing::warn!(error=%e, template, "invalid line template; using the default");
+        parse_default()
+    })
+}
+
+fn parse_default() -> Template {
+    default_template()
+        .parse()
+        .unwrap_or_else(|_| DEFAULT_TEMPLATE.parse().expect("default template"))
+}
+
+/// Render `template` with `parse_or_default`.
+pub fn render_line(template: &str, ctx: &Context) -> String {
+    parse_or_default(template).render(ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(title: &str, description: &str) -> Context {
+        Context {
+            title: title.into(),
+            description: description.into(),
+            ..Default::default()
+        }
+    }
+
+    fn render(template: &str, ctx: &Context) -> String {
+        template.parse::<Template>().unwrap().render(ctx)
+    }
+
+    #[test]
+    fn default_template_matches_the_classic_line() {
+        assert_eq!(
+            render_line(DEFAULT_TEMPLATE, &ctx("Work", "Tickets")),
+            "Work : Tickets"
+        );
+        assert_eq!(
+            render_line(DEFAULT_TEMPLATE, &ctx("", "desc")),
+            "Desktop : desc"
+        );
+        assert_eq!(render_line(DEFAULT_TEMPLATE, &ctx("   ", "")), "Desktop");
+        assert_eq!(render_line(DEFAULT_TEMPLATE, &ctx("Work", "  ")), "Work");
+    }
+
+    #[test]
+    fn default_template_takes_a_translated_fallback() {
+        assert_eq!(default_template_with("Desktop"), DEFAULT_TEMPLATE);
+        assert_eq!(
+            render(&default_template_with("Bureau"), &ctx("", "desc")),
+            "Bureau : desc"
+        );
+        assert_eq!(render(&default_template_with("{Odd}"), &ctx("", "")), "Odd");
+    }
+
+    #[test]
+    fn renders_every_placeholder() {
+        let c = Context {
+            index: Some(2),
+            count: Some(4),
+            os_name: "Mail".into(),
+            now: Some(LocalTime {
+                year: 2024,
+                month: 5,
+                day: 31,
+                hour: 9,
+                minute: 5,
+                second: 59,
+                millisecond: 0,
+            }),
+            hostname: "PC-7".into(),
+            user: "sam".into(),
+            ..ctx("Work", "Tickets")
+        };
+        assert_eq!(
+            render(
+                "{index}/{count} {os_name}: {title} ({description}) {date} {time} {user}@{hostname}",
+                &c
+            ),
+            "2/4 Mail: Work (Tickets) 2024-05-31 09:05 sam@PC-7"
+        );
+    }
+
+    #[test]
+    fn unknown_values_render_empty() {
+        assert_eq!(
+            render("[{index}/{count}|{date}|{time}]", &Context::default()),
+            "[/||]"
+        );
+        assert_eq!(render("{index|?}", &Context::default()), "?");
+    }
+
+    #[test]
+    fn sections_and_inverted_sections() {
+        let t = "{title}{#os_name} [{os_name}]{/os_name}{^os_name} (unnamed){/os_name}";
+        let mut c = ctx("Work", "");
+        assert_eq!(render(t, &c), "Work (unnamed)");
+        c.os_name = "Mail".into();
+        assert_eq!(render(t, &c), "Work [Mail]");
+
+        let nested = "{#title}<{title}{#description}: {description}{/description}>{/title}";
+        assert_eq!(render(nested, &ctx("A", "B")), "<A: B>");
+        assert_eq!(render(nested, &ctx("A", "")), "<A>");
+        assert_eq!(render(nested, &ctx("", "B")), "");
+    }
+
+    #[test]
+    fn escaped_braces_are_literal() {
+        assert_eq!(render("{{{title}}}", &ctx("x", "")), "{x}");
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_problem() {
+        let err = |t: &str| t.parse::<Template>().unwrap_err();
+        assert_eq!(
+            err("ab {titel}"),
+            TemplateError {
+                column: 4,
+                message: "unknown placeholder {titel}".into()
+            }
+        );
+        assert_eq!(err("{title").column, 1);
+        assert_eq!(err("x }").column, 3);
+        assert_eq!(
+            err("{#title}{/description}").message,
+            "{/description} closes {#title}"
+        );
+        assert_eq!(err("{/title}").message, "{/title} closes no section");
+        assert_eq!(err("  {^title}x").message, "section {#title} is not closed");
+        assert_eq!(err("  {^title}x").column, 3);
+    }
+
+    #[test]
+    fn invalid_templates_fall_back_to_the_default() {
+        assert_eq!(render_line("{nope}", &ctx("Work", "")), "Work");
+    }
+
+    #[test]
+    fn reports_used_fields() {
+        let t: Template = "{#description}{time}{/description}".parse().unwrap();
+        assert!(t.uses(Field::Time) && t.uses(Field::Description));
+        assert!(!t.uses(Field::Date));
+    }
+
+    fn at(s: &str) -> LocalTime {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_local_times() {
+        assert_eq!(
+            at("2024-02-29T18:05:09"),
+            LocalTime {
+                year: 2024,
+                month: 2,
+                day: 29,
+                hour: 18,
+                minute: 5,
+                second: 9,
+                millisecond: 0
+            }
+        );
+        assert_eq!(at(" 2024-05-31 18:00 ").second, 0);
+        for bad in [
+            "2023-02-29 10:00",
+            "2024-5-31 10:00",
+            "2024-05-31",
+            "2024-05-31 24:00",
+            "tomorrow",
+        ] {
+            assert!(bad.parse::<LocalTime>().is_err(), "{bad}");
+        }
+        assert_eq!(
+            at("2025-01-01 00:00").seconds() - at("2024-12-31 23:59:30").seconds(),
+            30
+        );
+        assert_eq!(
+            at("2024-03-01 00:00").seconds() - at("2024-02-28 00:00").seconds(),
+            2 * 86_400
+        );
+    }
+
+    #[test]
+    fn countdown_shows_the_two_largest_units() {
+        assert_eq!(countdown(3 * 86_400 + 4 * 3600 + 59), "3d 4h");
+        assert_eq!(countdown(2 * 3600 + 10 * 60 + 5), "2h 10m");
+        assert_eq!(countdown(45 * 60), "45m");
+        assert_eq!(countdown(30), "30s");
+
+        let mut c = Context {
+            now: Some(at("2024-05-31 15:49:50")),
+            deadline: Some(at("2024-05-31 18:00")),
+            ..Default::default()
+        };
+        let t = "{#countdown}Release freeze in {countdown}{/countdown}";
+        assert_eq!(render(t, &c), "Release freeze in 2h 10m");
+        c.now = Some(at("2024-05-31 18:00"));
+        assert_eq!(render(t, &c), "");
+    }
+
+    #[test]
+    fn next_change_follows_the_visible_fields() {
+        let mut now = at("2024-05-31 23:58:20");
+        now.millisecond = 250;
+        let c = Context {
+            now: Some(now),
+            deadline: Some(at("2024-06-01 01:00:00")),
+            ..Default::default()
+        };
+        let next = |t: &str| t.parse::<Template>().unwrap().next_change(&c);
+        assert_eq!(next("{title}"), None);
+        assert_eq!(next("{time_seconds}"), Some(Duration::from_millis(750)));
+        assert_eq!(next("{time}"), Some(Duration::from_millis(39_750)));
+        assert_eq!(next("{date}"), Some(Duration::from_millis(99_750)));
+        // 1h 1m 40s left: "1h 1m" until 1h 0m 59s.
+        assert_eq!(
+            next("{#countdown}x{/countdown}"),
+            Some(Duration::from_millis(40_750))
+        );
+        assert_eq!(
+            next("{date} {countdown}"),
+            Some(Duration::from_millis(40_750))
+        );
+        assert_eq!(
+            "{time}"
+                .parse::<Template>()
+                .unwrap()
+                .next_change(&Context::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn countdown_steps_land_on_the_next_value() {
+        for secs in [30, 60, 61, 3599, 3600, 3661, 86_400, 90_061] {
+            let step = countdown_step(secs);
+            assert_eq!(countdown(secs - step + 1), countdown(secs), "{secs}");
+            assert_ne!(countdown(secs - step), countdown(secs), "{secs}");
+        }
+    }
+
+    #[test]
+    fn context_from_env_reads_names() {
+        let c = Context::from_env();
+        assert!(c.title.is_empty() && c.index.is_none());
+    }
+}
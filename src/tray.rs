@@ -1,3 +1,4 @@
+use crate::i18n::tr;
 use crate::utils::to_utf16;
 use anyhow::{Result, anyhow};
 use std::mem::size_of;
@@ -21,6 +22,50 @@ pub const CMD_OPEN_CONFIG: u16 = 1004;
 pub const CMD_EXIT: u16 = 1005;
 pub const CMD_ABOUT: u16 = 1006;
 pub const CMD_RUN_AT_STARTUP: u16 = 1007;
+pub const CMD_RESTORE_BACKUP: u16 = 1008;
+pub const CMD_DIAGNOSTICS: u16 = 1009;
+/// Profile entries: `CMD_PROFILE_FIRST` selects the default labels and
+/// `CMD_PROFILE_FIRST + 1 + i` the i-th named profile.
+pub const CMD_PROFILE_FIRST: u16 = 1100;
+pub const MAX_PROFILES: usize = 99;
+/// `CMD_ARCHIVE_FIRST + i` re-attaches the i-th archived label.
+pub const CMD_ARCHIVE_FIRST: u16 = 1200;
+pub const MAX_ARCHIVED: usize = 99;
+
+/// Profiles to list in the menu.
+#[derive(Debug, Clone, Default)]
+pub struct ProfileMenu {
+    /// Named profiles in menu order.
+    pub names: Vec<String>,
+    /// Index into `names` of the active profile; `None` for the default.
+    pub active: Option<usize>,
+}
+
+impl ProfileMenu {
+    /// The profile a menu command selects: `Some(None)` for the default
+    /// labels, `Some(Some(name))` for a named profile.
+    pub fn command_target(&self, cmd: u16) -> Option<Option<&str>> {
+        let idx = cmd.checked_sub(CMD_PROFILE_FIRST)? as usize;
+        match idx {
+            0 => Some(None),
+            i => self.names.get(i - 1).map(|n| Some(n.as_str())),
+        }
+    }
+}
+
+/// Archived labels to offer for the current desktop, in archive order.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveMenu {
+    pub entries: Vec<String>,
+}
+
+impl ArchiveMenu {
+    /// The archive index a menu command re-attaches.
+    pub fn command_target(&self, cmd: u16) -> Option<usize> {
+        let idx = cmd.checked_sub(CMD_ARCHIVE_FIRST)? as usize;
+        (idx < self.entries.len().min(MAX_ARCHIVED)).then_some(idx)
+    }
+}
 
 pub struct Tray {
     pub nid: NOTIFYICONDATAW,
@@ -92,40 +137,91 @@ impl Tray {
         }
     }
 
-    pub fn show_menu(&self, hwnd: HWND) -> Result<()> {
+    pub fn show_menu(
+        &self,
+        hwnd: HWND,
+        profiles: &ProfileMenu,
+        archive: &ArchiveMenu,
+    ) -> Result<()> {
         unsafe {
             let hmenu = CreatePopupMenu()?;
-            AppendMenuW(
+            append(
                 hmenu,
                 MF_STRING,
                 CMD_EDIT_TITLE as usize,
-                PCWSTR(windows::core::w!("Edit Title").as_wide().as_ptr()),
+                "action.edit_title",
             )?;
-            AppendMenuW(
+            append(
                 hmenu,
                 MF_STRING,
                 CMD_EDIT_DESC as usize,
-                PCWSTR(windows::core::w!("Edit Description").as_wide().as_ptr()),
+                "action.edit_description",
             )?;
+            if !profiles.names.is_empty() {
+                let sub = CreatePopupMenu()?;
+                let default = profiles.active.is_none();
+                append(
+                    sub,
+                    MF_STRING | if default { MF_CHECKED } else { MF_UNCHECKED },
+                    CMD_PROFILE_FIRST as usize,
+                    "menu.default_profile",
+                )?;
+                for (i, name) in profiles.names.iter().take(MAX_PROFILES).enumerate() {
+                    let wname = to_utf16(name);
+                    let checked = profiles.active == Some(i);
+                    AppendMenuW(
+                        sub,
+                        MF_STRING | if checked { MF_CHECKED } else { MF_UNCHECKED },
+                        CMD_PROFILE_FIRST as usize + 1 + i,
+                        PCWSTR(wname.as_ptr()),
+                    )?;
+                }
+                append(hmenu, MF_POPUP, sub.0 as usize, "menu.profile")?;
+            }
+            if !archive.entries.is_empty() {
+                let sub = CreatePopupMenu()?;
+                for (i, entry) in archive.entries.iter().take(MAX_ARCHIVED).enumerate() {
+                    let wentry = to_utf16(entry);
+                    AppendMenuW(
+                        sub,
+                        MF_STRING,
+                        CMD_ARCHIVE_FIRST as usize + i,
+                        PCWSTR(wentry.as_ptr()),
+                    )?;
+                }
+                append(hmenu, MF_POPUP, sub.0 as usize, "menu.reattach")?;
+            }
             AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null())?;
-            AppendMenuW(
+            append(
                 hmenu,
                 MF_STRING,
                 CMD_TOGGLE as usize,
-                PCWSTR(windows::core::w!("Toggle Overlay").as_wide().as_ptr()),
+                "action.toggle_overlay",
             )?;
-            AppendMenuW(
+            append(
                 hmenu,
                 MF_STRING,
                 CMD_OPEN_CONFIG as usize,
-                PCWSTR(windows::core::w!("Open Config").as_wide().as_ptr()),
+                "menu.open_config",
+            )?;
+            append(
+                hmenu,
+                MF_STRING,
+                CMD_RESTORE_BACKUP as usize,
+                "menu.restore_backup",
+            )?;
+            append(
+                hmenu,
+                MF_STRING,
+                CMD_DIAGNOSTICS as usize,
+                "menu.diagnostics",
             )?;
             AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null())?;
-            AppendMenuW(
+            append(
                 hmenu,
                 MF_STRING,
                 CMD_RUN_AT_STARTUP as usize,
-                PCWSTR(windows::core::w!("Run at startup").as_wide().as_ptr()),
+                "menu.run_at_startup",
             )?;
             // Reflect current autorun state
             let enabled = crate::autorun::get_run_at_login();
@@ -134,18 +230,8 @@ impl Tray {
                 CMD_RUN_AT_STARTUP as u32,
                 (MF_BYCOMMAND | if enabled { MF_CHECKED } else { MF_UNCHECKED }).0,
             );
-            AppendMenuW(
-                hmenu,
-                MF_STRING,
-                CMD_ABOUT as usize,
-                PCWSTR(windows::core::w!("About...").as_wide().as_ptr()),
-            )?;
-            AppendMenuW(
-                hmenu,
-                MF_STRING,
-                CMD_EXIT as usize,
-                PCWSTR(windows::core::w!("Exit").as_wide().as_ptr()),
-            )?;
+            append(hmenu, MF_STRING, CMD_ABOUT as usize, "menu.about")?;
+            append(hmenu, MF_STRING, CMD_EXIT as usize, "menu.exit")?;
 
             let mut pt = POINT::default();
             let _ = GetCursorPos(&mut pt);
@@ -157,11 +243,15 @@ impl Tray {
     }
 
     // Static helpers to avoid borrowing AppState across re-entrant shell calls
-    pub fn show_popup_menu(hwnd: HWND) -> Result<()> {
+    pub fn show_popup_menu(
+        hwnd: HWND,
+        profiles: &ProfileMenu,
+        archive: &ArchiveMenu,
+    ) -> Result<()> {
         Self {
             nid: unsafe { std::mem::zeroed() },
         }
-        .show_menu(hwnd)
+        .show_menu(hwnd, profiles, archive)
     }
 
     pub fn balloon_for(hwnd: HWND, title: &str, text: &str) -> Result<()> {
@@ -183,8 +273,7 @@ impl Tray {
                 nid.uFlags = NIF_MESSAGE | NIF_TIP | NIF_ICON | NIF_INFO;
                 nid.uCallbackMessage = TRAY_MSG;
                 nid.hIcon = Self::load_app_icon();
-                let tip = "Desktop Labeler";
-                let wtip = to_utf16(tip);
+                let wtip = to_utf16(tr("app.name"));
                 let lt2 = wtip.len().min(nid.szTip.len());
                 nid.szTip[..lt2].copy_from_slice(&wtip[..lt2]);
                 let _ = Shell_NotifyIconW(NIM_ADD, &nid);
@@ -193,7 +282,25 @@ impl Tray {
         Ok(())
     }
 
-    pub fn re_add_for(hwnd: HWND) -> Result<()> {
+    /// Replace the tooltip, e.g. after the hotkeys changed.
+    pub fn set_tip_for(hwnd: HWND, tip: &str) -> Result<()> {
+        unsafe {
+            let mut nid: NOTIFYICONDATAW = std::mem::zeroed();
+            nid.cbSize = size_of::<NOTIFYICONDATAW>() as u32;
+            nid.hWnd = hwnd;
+            nid.uID = TRAY_UID;
+            nid.uFlags = NIF_TIP;
+            let wtip = to_utf16(tip);
+            let lt = wtip.len().min(nid.szTip.len());
+            nid.szTip[..lt].copy_from_slice(&wtip[..lt]);
+            if !Shell_NotifyIconW(NIM_MODIFY, &nid).as_bool() {
+                return Err(anyhow!("Shell_NotifyIconW(NIM_MODIFY) failed"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn re_add_for(hwnd: HWND, tip: &str) -> Result<()> {
         unsafe {
             let mut nid: NOTIFYICONDATAW = std::mem::zeroed();
             nid.cbSize = size_of::<NOTIFYICONDATAW>() as u32;
@@ -202,7 +309,6 @@ impl Tray {
             nid.uFlags = NIF_MESSAGE | NIF_TIP | NIF_ICON;
             nid.uCallbackMessage = TRAY_MSG;
             nid.hIcon = Self::load_app_icon();
-            let tip = "Desktop Labeler";
             let wtip = to_utf16(tip);
             let lt = wtip.len().min(nid.szTip.len());
             nid.szTip[..lt].copy_from_slice(&wtip[..lt]);
@@ -211,3 +317,10 @@ impl Tray {
         Ok(())
     }
 }
+
+/// Append a menu item labelled with the current language's text for `key`.
+fn append(menu: HMENU, flags: MENU_ITEM_FLAGS, id: usize, key: &'static str) -> Result<()> {
+    let text = to_utf16(tr(key));
+    unsafe { AppendMenuW(menu, flags, id, PCWSTR(text.as_ptr()))? };
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::i18n::tr;
 use crate::utils::to_utf16;
 use core::ffi::c_void;
 use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
@@ -283,10 +284,11 @@ extern "system" fn dlg_wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LR
                 .unwrap();
                 // Limit text length to 200 chars
                 let _ = SendMessageW(edit, EM_LIMITTEXT, WPARAM_T(200), LPARAM_T(0));
+                let ok_text = to_utf16(tr("dialog.ok"));
                 let _ok = CreateWindowExW(
                     WINDOW_EX_STYLE(0),
                     PCWSTR(windows::core::w!("BUTTON").as_wide().as_ptr()),
-                    PCWSTR(windows::core::w!("OK").as_wide().as_ptr()),
+                    PCWSTR(ok_text.as_ptr()),
                     WINDOW_STYLE(WS_CHILD.0 | WS_VISIBLE.0 | WS_TABSTOP.0 | (BS_PUSHBUTTON as u32)),
                     0,
                     0,
@@ -298,10 +300,11 @@ extern "system" fn dlg_wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LR
                     None,
                 )
                 .unwrap();
+                let cancel_text = to_utf16(tr("dialog.cancel"));
                 let _cancel = CreateWindowExW(
                     WINDOW_EX_STYLE(0),
                     PCWSTR(windows::core::w!("BUTTON").as_wide().as_ptr()),
-                    PCWSTR(windows::core::w!("Cancel").as_wide().as_ptr()),
+                    PCWSTR(cancel_text.as_ptr()),
                     WINDOW_STYLE(WS_CHILD.0 | WS_VISIBLE.0 | WS_TABSTOP.0),
                     0,
                     0,
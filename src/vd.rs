@@ -1,3 +1,5 @@
+use crate::config::DesktopId;
+use crate::config::identity::LiveDesktop;
 use core::ffi::c_void;
 use std::sync::mpsc;
 use std::thread;
@@ -6,15 +8,36 @@ use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
 use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
 use winvd::{DesktopEvent, DesktopEventThread, listen_desktop_events};
 
-pub fn get_current_desktop_guid() -> String {
+pub fn get_current_desktop_id() -> DesktopId {
     #[cfg(windows)]
     {
-        if let Ok(d) = winvd::get_current_desktop() {
-            return format!("{d:?}");
+        if let Ok(guid) = winvd::get_current_desktop().and_then(|d| d.get_id()) {
+            return DesktopId::from_u128(guid.to_u128());
         }
     }
     // Fallback if API unavailable
-    "default".to_string()
+    DesktopId::FALLBACK
+}
+
+/// All virtual desktops with their names, in Task View order.
+pub fn live_desktops() -> Vec<LiveDesktop> {
+    winvd::get_desktops()
+        .map(|desktops| {
+            desktops
+                .iter()
+                .filter_map(|d| {
+                    let id = DesktopId::from_u128(d.get_id().ok()?.to_u128());
+                    let name = d.get_name().unwrap_or_default();
+                    Some(LiveDesktop { id, name })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Ids of all virtual desktops, in Task View order.
+pub fn desktop_order() -> Vec<DesktopId> {
+    live_desktops().into_iter().map(|d| d.id).collect()
 }
 
 pub fn start_vd_poller(hwnd: HWND, msg: u32) {
@@ -22,9 +45,9 @@ pub fn start_vd_poller(hwnd: HWND, msg: u32) {
     // Cast to usize to satisfy Send, then reconstruct on use.
     let hwnd_raw = hwnd.0 as usize;
     thread::spawn(move || {
-        let mut last = super::vd::get_current_desktop_guid();
+        let mut last = super::vd::get_current_desktop_id();
         loop {
-            let now = super::vd::get_current_desktop_guid();
+            let now = super::vd::get_current_desktop_id();
             if now != last {
                 let target = HWND(hwnd_raw as *mut c_void);
                 unsafe {
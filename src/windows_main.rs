@@ -2,6 +2,7 @@
 
 use anyhow::Result;
 use std::cell::RefCell;
+use std::collections::HashSet;
 use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
 use windows::Win32::Graphics::Gdi::{
     GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromWindow,
@@ -11,12 +12,18 @@ use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::System::RemoteDesktop::{
     NOTIFY_FOR_THIS_SESSION, WTSRegisterSessionNotification, WTSUnRegisterSessionNotification,
 };
+use windows::Win32::System::SystemInformation::GetLocalTime;
 use windows::Win32::UI::WindowsAndMessaging::*;
 
 use mddskmgr::autorun;
-use mddskmgr::config::{self, Config, Paths};
+use mddskmgr::chord;
+use mddskmgr::config::history::{self, History};
+use mddskmgr::config::identity::{self, MatchPolicy, Rebind};
+use mddskmgr::config::{self, Config, DesktopId, OverlayLayout, Paths};
 use mddskmgr::hotkeys::{self, HK_EDIT_DESC, HK_EDIT_TITLE, HK_TOGGLE};
-use mddskmgr::overlay::Overlay;
+use mddskmgr::i18n::{self, tr, tr_with};
+use mddskmgr::overlay::{Fit, Look, Overlay, TwoLines};
+use mddskmgr::template::{self, Field};
 use mddskmgr::tray;
 use mddskmgr::tray::{
     CMD_EDIT_DESC, CMD_EDIT_TITLE, CMD_EXIT, CMD_OPEN_CONFIG, CMD_TOGGLE, TRAY_MSG, Tray,
@@ -24,12 +31,15 @@ use mddskmgr::tray::{
 use mddskmgr::ui;
 use mddskmgr::vd;
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc as std_mpsc;
+use std::time::Duration;
 use windows::Win32::UI::Shell::ShellExecuteW;
 use windows::core::PCWSTR;
 
 const WM_VD_SWITCHED: u32 = WM_APP + 2;
 const WM_CFG_CHANGED: u32 = WM_APP + 3;
+const WM_OFFER_MATCHES: u32 = WM_APP + 4;
 const WM_WTSSESSION_CHANGE: u32 = 0x02B1;
 const WTS_SESSION_LOCK: u32 = 0x7;
 const WTS_SESSION_UNLOCK: u32 = 0x8;
@@ -38,6 +48,7 @@ const WTS_SESSION_UNLOCK: u32 = 0x8;
 const TIMER_VD_POLLER: usize = 1;
 const TIMER_FULLSCREEN_CHECK: usize = 2;
 const TIMER_TOPMOST_REASSERT: usize = 3;
+const TIMER_REDRAW: usize = 4;
 
 thread_local! {
     static APP: RefCell<Option<AppState>> = const { RefCell::new(None) };
@@ -47,8 +58,9 @@ struct AppState {
     hwnd: HWND,
     cfg: Config,
     cfg_paths: Paths,
+    history: History,
     overlay: Overlay,
-    current_guid: String,
+    current_desktop: DesktopId,
     visible: bool,
     tray: Tray,
     taskbar_created_msg: u32,
@@ -56,22 +68,86 @@ struct AppState {
     hide_for_accessibility: bool,
     hide_for_fullscreen: bool,
     anchor_index: u8, // 0=1/4,1=1/2,2=3/4
+    /// Desktop matches waiting for the user's confirmation.
+    pending_matches: Vec<Rebind>,
+    /// Matches shown in the open prompt, until the user answers it.
+    offered_matches: Vec<Rebind>,
+    /// Matches the user declined this session, as (from, to).
+    declined_matches: HashSet<(DesktopId, DesktopId)>,
+    /// The config file had problems when it was last loaded, so `cfg` may
+    /// hold defaults in place of the user's values.
+    cfg_has_problems: bool,
 }
 
-fn compute_line(cfg: &Config, guid: &str) -> (String, i32) {
-    let label = cfg.desktops.get(guid).cloned().unwrap_or_default();
-    let title = if label.title.trim().is_empty() {
-        "Desktop".to_string()
+/// What the overlay shows for a desktop.
+struct RenderedLine {
+    /// The template's output.
+    text: String,
+    /// Two-line layout: the description shown below `text`.
+    detail: Option<String>,
+    /// How long until `text` changes by itself.
+    next_change: Option<Duration>,
+}
+
+fn compute_line(cfg: &Config, desktop: DesktopId) -> (String, i32) {
+    let line = render_line(cfg, desktop);
+    (line.text, cfg.appearance_for(desktop).margin_px)
+}
+
+/// Render the template for `desktop`. In the two-line layout the description
+/// goes to the second line, so the template sees it as empty.
+fn render_line(cfg: &Config, desktop: DesktopId) -> RenderedLine {
+    let template = template::parse_or_default(&cfg.line_template(desktop));
+    let label = cfg.labels().get(&desktop).cloned().unwrap_or_default();
+    let two_lines = cfg.appearance_for(desktop).layout == OverlayLayout::TwoLines;
+    let (description, detail) = if two_lines {
+        (String::new(), Some(label.description))
     } else {
-        label.title
+        (label.description, None)
     };
-    let desc = label.description;
-    let line = if desc.trim().is_empty() {
-        title
-    } else {
-        format!("{title} : {desc}")
+    let mut ctx = template::Context {
+        title: label.title,
+        description,
+        deadline: label.deadline.and_then(|d| d.parse().ok()),
+        ..template::Context::from_env()
     };
-    (line, cfg.appearance.margin_px)
+    // Only ask Windows for what the template shows.
+    if [Field::Index, Field::Count, Field::OsName]
+        .into_iter()
+        .any(|f| template.uses(f))
+    {
+        let live = vd::live_desktops();
+        ctx.count = Some(live.len());
+        if let Some(i) = live.iter().position(|d| d.id == desktop) {
+            ctx.index = Some(i + 1);
+            ctx.os_name = live[i].name.clone();
+        }
+    }
+    if [
+        Field::Date,
+        Field::Time,
+        Field::TimeSeconds,
+        Field::Countdown,
+    ]
+    .into_iter()
+    .any(|f| template.uses(f))
+    {
+        let t = unsafe { GetLocalTime() };
+        ctx.now = Some(template::LocalTime {
+            year: t.wYear,
+            month: t.wMonth as u8,
+            day: t.wDay as u8,
+            hour: t.wHour as u8,
+            minute: t.wMinute as u8,
+            second: t.wSecond as u8,
+            millisecond: t.wMilliseconds,
+        });
+    }
+    RenderedLine {
+        text: template.render(&ctx),
+        detail,
+        next_change: template.next_change(&ctx),
+    }
 }
 
 fn anchor_ratio_from_index(idx: u8) -> f32 {
@@ -82,18 +158,97 @@ fn anchor_ratio_from_index(idx: u8) -> f32 {
     }
 }
 
-fn draw_overlay_line(overlay: &Overlay, cfg: &Config, guid: &str) {
-    let (line, margin) = compute_line(cfg, guid);
-    let hints = "(Ctrl+Alt+T,D,O,L)";
-    tracing::debug!(guid=%guid, line=%line, "update_overlay_text");
-    let ratio = APP.with(|slot| {
+fn draw_overlay_line(overlay: &Overlay, cfg: &Config, desktop: DesktopId) {
+    let line = render_line(cfg, desktop);
+    let appearance = cfg.appearance_for(desktop);
+    let overlay = overlay.with_font(&appearance.font_family, appearance.font_size_dip);
+    let margin = appearance.margin_px;
+    let hints = overlay_hints(cfg, desktop);
+    let hints = hints.as_str();
+    tracing::debug!(desktop=%desktop, line=%line.text, "update_overlay_text");
+    let (ratio, hwnd) = APP.with(|slot| {
         if let Some(app) = &*slot.borrow() {
-            anchor_ratio_from_index(app.anchor_index)
+            (anchor_ratio_from_index(app.anchor_index), Some(app.hwnd))
         } else {
-            0.5
+            (0.5, None)
         }
     });
-    let _ = overlay.draw_line_top_anchor_with_hints(&line, hints, margin, ratio);
+    let ratio = appearance.position.map_or(ratio, |p| p.anchor_ratio());
+    let fit = Fit {
+        max_width_px: Some(appearance.max_width_px).filter(|w| *w > 0),
+        max_chars: Some(appearance.max_chars as usize).filter(|n| *n > 0),
+        ellipsis: appearance.ellipsis,
+    };
+    let look = Look {
+        theme: appearance.theme_colors(),
+        opacity: appearance.opacity_percent as f32 / 100.0,
+        corner_radius_px: appearance.corner_radius_px,
+        padding_px: appearance.padding_px,
+        direction: appearance.direction,
+        align: appearance.align,
+    };
+    let _ = match &line.detail {
+        None => {
+            overlay.draw_line_top_anchor_with_hints(&line.text, hints, &fit, &look, margin, ratio)
+        }
+        Some(detail) => {
+            let opts = TwoLines {
+                description_percent: appearance.description_size_percent,
+                description_opacity: appearance.description_opacity_percent as f32 / 100.0,
+                max_lines: appearance.max_lines as usize,
+            };
+            overlay.draw_two_lines_top_anchor(
+                &line.text, detail, hints, &opts, &fit, &look, margin, ratio,
+            )
+        }
+    };
+    if let Some(hwnd) = hwnd {
+        schedule_redraw(hwnd, line.next_change);
+    }
+}
+
+/// The hotkey hint shown after the overlay text, empty when hidden.
+fn overlay_hints(cfg: &Config, desktop: DesktopId) -> String {
+    if cfg.appearance_for(desktop).show_hints {
+        chord::overlay_hint(&cfg.hotkeys)
+    } else {
+        String::new()
+    }
+}
+
+fn tray_tip(cfg: &Config) -> String {
+    chord::tooltip(tr("app.name"), &cfg.hotkeys)
+}
+
+fn about_text(cfg: &Config) -> String {
+    let hotkeys: Vec<String> = chord::help_lines(&cfg.hotkeys)
+        .iter()
+        .map(|line| format!("  {line}"))
+        .collect();
+    tr_with("about.text", &[("hotkeys", &hotkeys.join("\r\n"))])
+}
+
+/// Use the configured language, or the system's, for everything shown from
+/// now on.
+pub fn apply_language(cfg: &Config) {
+    let locale = i18n::resolve(cfg.language.as_deref(), i18n::system_tag().as_deref());
+    tracing::debug!(locale = locale.tag(), "language");
+    i18n::set_locale(locale);
+}
+
+/// Redraw once the line changes by itself (clock, countdown), and not before.
+fn schedule_redraw(hwnd: HWND, after: Option<Duration>) {
+    unsafe {
+        match after {
+            Some(d) => {
+                let ms = d.as_millis().clamp(1, u128::from(u32::MAX)) as u32;
+                SetTimer(hwnd, TIMER_REDRAW, ms, None);
+            }
+            None => {
+                let _ = KillTimer(hwnd, TIMER_REDRAW);
+            }
+        }
+    }
 }
 
 fn is_high_contrast() -> bool {
@@ -198,21 +353,305 @@ fn refresh_visibility_now() {
     }
 }
 
+/// Rebuild the overlay after the effective appearance may have changed.
+fn apply_appearance(app: &mut AppState) {
+    let a = app.cfg.effective_appearance();
+    match Overlay::new(app.hwnd, &a.font_family, a.font_size_dip) {
+        Ok(ov) => app.overlay = ov,
+        Err(e) => tracing::warn!(error=%e, "Failed to apply appearance"),
+    }
+}
+
+/// Profiles for the tray menu, in the order `Config::cycle_profile` visits
+/// them.
+fn profile_menu() -> tray::ProfileMenu {
+    APP.with(|slot| {
+        let borrowed = slot.borrow();
+        let Some(app) = &*borrowed else {
+            return tray::ProfileMenu::default();
+        };
+        let names: Vec<String> = app.cfg.profiles.keys().cloned().collect();
+        let active = (app.cfg.active_profile.as_ref())
+            .and_then(|active| names.iter().position(|n| n == active));
+        tray::ProfileMenu { names, active }
+    })
+}
+
+/// Archived labels for the tray menu, as "title (profile, GUID)".
+fn archive_menu() -> tray::ArchiveMenu {
+    APP.with(|slot| {
+        let borrowed = slot.borrow();
+        let Some(app) = &*borrowed else {
+            return tray::ArchiveMenu::default();
+        };
+        let entries = app
+            .cfg
+            .archived
+            .labels
+            .iter()
+            .map(|a| {
+                let profile = config::profile::display_name(a.profile.as_deref());
+                format!("{} ({profile}, {})", a.label.title, a.desktop_id)
+            })
+            .collect();
+        tray::ArchiveMenu { entries }
+    })
+}
+
+/// Why a change stays in memory only, with the name of the config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NotSaved {
+    /// The config file has problems: saving would replace the user's broken
+    /// values with the defaults they were reset to.
+    Problems(String),
+    /// Writing the file failed.
+    Failed(String),
+}
+
+impl NotSaved {
+    /// Balloon text for `text`, or for the label when `None`, not being saved.
+    fn describe(&self, text: Option<&str>) -> String {
+        let (key, file) = match (self, text.is_some()) {
+            (NotSaved::Problems(file), true) => ("msg.not_saved", file),
+            (NotSaved::Problems(file), false) => ("msg.label_not_saved", file),
+            (NotSaved::Failed(file), true) => ("msg.save_failed", file),
+            (NotSaved::Failed(file), false) => ("msg.label_save_failed", file),
+        };
+        tr_with(key, &[("file", file), ("text", text.unwrap_or_default())])
+    }
+}
+
+/// Write `app.cfg` to the config file, unless the file has problems.
+fn save_config(app: &AppState, what: &str) -> Result<(), NotSaved> {
+    let file = || {
+        let name = app.cfg_paths.cfg_file.file_name().unwrap_or_default();
+        name.to_string_lossy().into_owned()
+    };
+    if app.cfg_has_problems {
+        tracing::info!("{what}: not saving while the config file has problems");
+        return Err(NotSaved::Problems(file()));
+    }
+    mddskmgr::config::save_atomic(&app.cfg, &app.cfg_paths).map_err(|e| {
+        tracing::warn!(error=?e, "{what}: save failed");
+        NotSaved::Failed(file())
+    })
+}
+
+/// Bring the labels in line with the live desktops: follow desktops whose
+/// GUID changed (or queue the match for confirmation), record where labelled
+/// desktops are, and mark or archive labels of deleted desktops. Saves if
+/// anything changed (see `save_config`).
+fn reconcile_desktops(app: &mut AppState) {
+    let live = vd::live_desktops();
+    let mut changed = false;
+    for m in identity::plan(&app.cfg, &live) {
+        if app.cfg.desktop_matching == MatchPolicy::Auto && m.confident {
+            tracing::info!(from=%m.from, to=%m.to, evidence=m.evidence.describe(), "labels follow a desktop with a new GUID");
+            app.cfg.rebind(&m);
+            changed = true;
+        } else if !app.declined_matches.contains(&(m.from, m.to))
+            && !app.pending_matches.contains(&m)
+            && !app.offered_matches.contains(&m)
+        {
+            app.pending_matches.push(m);
+        }
+    }
+    changed |= app.cfg.record_identities(&live);
+    let ids: Vec<DesktopId> = live.iter().map(|d| d.id).collect();
+    let r = app
+        .cfg
+        .reconcile(&ids, history::now(), config::archive::GRACE_PERIOD_SECS);
+    if r.changed() {
+        tracing::info!(?r, "reconciled labels with the desktop list");
+        changed = true;
+    }
+    if changed {
+        let _ = save_config(app, "reconcile");
+    }
+    if !app.pending_matches.is_empty() {
+        unsafe {
+            let _ = PostMessageW(app.hwnd, WM_OFFER_MATCHES, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+/// Ask whether to move labels to desktops that look like labelled desktops
+/// with a new GUID. Only one prompt is open at a time: matches found while it
+/// is open wait for the answer.
+fn offer_matches(hwnd: HWND) {
+    let mut offer: Option<(Vec<Rebind>, String)> = None;
+    APP.with(|slot| {
+        if let Some(app) = &mut *slot.borrow_mut() {
+            if !app.offered_matches.is_empty() || app.pending_matches.is_empty() {
+                return;
+            }
+            let matches = std::mem::take(&mut app.pending_matches);
+            app.offered_matches = matches.clone();
+            let order = vd::desktop_order();
+            let lines: Vec<String> = matches
+                .iter()
+                .map(|m| {
+                    let label = app.cfg.labels().get(&m.from);
+                    let title = label.map_or_else(|| m.from.to_string(), |l| l.title.clone());
+                    let at = order.iter().position(|id| *id == m.to).map_or(0, |i| i + 1);
+                    let line = tr_with(
+                        "msg.offer_match",
+                        &[
+                            ("title", &title),
+                            ("number", &at.to_string()),
+                            ("evidence", tr(m.evidence.message_key())),
+                        ],
+                    );
+                    format!("  {line}")
+                })
+                .collect();
+            let text = tr_with("msg.offer_matches", &[("matches", &lines.join("\r\n"))]);
+            offer = Some((matches, text));
+        }
+    });
+    let Some((matches, text)) = offer else {
+        return;
+    };
+    let wtext: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    let wcaption: Vec<u16> = tr("app.name")
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    let answer = unsafe {
+        MessageBoxW(
+            hwnd,
+            PCWSTR(wtext.as_ptr()),
+            PCWSTR(wcaption.as_ptr()),
+            MB_YESNO | MB_ICONQUESTION,
+        )
+    };
+    let mut snap: Option<(Overlay, Config, DesktopId)> = None;
+    let mut not_saved = None;
+    APP.with(|slot| {
+        if let Some(app) = &mut *slot.borrow_mut() {
+            app.offered_matches.clear();
+            if !app.pending_matches.is_empty() {
+                unsafe {
+                    let _ = PostMessageW(app.hwnd, WM_OFFER_MATCHES, WPARAM(0), LPARAM(0));
+                }
+            }
+            if answer != IDYES {
+                app.declined_matches.extend(matches.iter().map(|m| (m.from, m.to)));
+                return;
+            }
+            for m in &matches {
+                tracing::info!(from=%m.from, to=%m.to, evidence=m.evidence.describe(), "labels moved to a desktop with a new GUID");
+                app.cfg.rebind(m);
+            }
+            // Matches queued meanwhile for the same desktops are settled now.
+            app.pending_matches
+                .retain(|p| !matches.iter().any(|m| m.from == p.from || m.to == p.to));
+            app.cfg.record_identities(&vd::live_desktops());
+            not_saved = save_config(app, "offer_matches").err();
+            snap = Some((app.overlay.clone(), app.cfg.clone(), app.current_desktop));
+        }
+    });
+    if let Some((ov, cfg_clone, gid)) = snap {
+        draw_overlay_line(&ov, &cfg_clone, gid);
+    }
+    if let Some(why) = not_saved {
+        let _ = mddskmgr::tray::Tray::balloon_for(hwnd, tr("balloon.config"), &why.describe(None));
+    }
+}
+
+/// Give an archived label to the current desktop.
+fn reattach_archived(index: usize) {
+    let mut snap: Option<(Overlay, Config, DesktopId, HWND)> = None;
+    let mut outcome = String::new();
+    APP.with(|slot| {
+        if let Some(app) = &mut *slot.borrow_mut() {
+            outcome = match app.cfg.reattach(index, app.current_desktop) {
+                Ok(entry) => match save_config(app, "reattach") {
+                    Ok(()) => entry.label.title,
+                    Err(why) => why.describe(Some(&entry.label.title)),
+                },
+                Err(e) => {
+                    tracing::warn!(error=%e, "reattach failed");
+                    tr(e.message_key()).to_string()
+                }
+            };
+            snap = Some((
+                app.overlay.clone(),
+                app.cfg.clone(),
+                app.current_desktop,
+                app.hwnd,
+            ));
+        }
+    });
+    if let Some((ov, cfg_clone, gid, hwnd)) = snap {
+        draw_overlay_line(&ov, &cfg_clone, gid);
+        refresh_visibility_now();
+        let _ = mddskmgr::tray::Tray::balloon_for(hwnd, tr("balloon.reattach"), &outcome);
+    }
+}
+
+enum ProfileSwitch {
+    Next,
+    /// A named profile, or `None` for the default labels.
+    To(Option<String>),
+}
+
+/// Switch profiles, persist the choice and redraw with the new labels and
+/// appearance.
+fn switch_profile(switch: ProfileSwitch) {
+    let mut snap: Option<(Overlay, Config, DesktopId, HWND)> = None;
+    let mut not_saved = None;
+    APP.with(|slot| {
+        if let Some(app) = &mut *slot.borrow_mut() {
+            match switch {
+                ProfileSwitch::Next => {
+                    app.cfg.cycle_profile();
+                }
+                ProfileSwitch::To(name) => {
+                    if let Err(e) = app.cfg.set_active_profile(name.as_deref()) {
+                        tracing::warn!(error=%e, "switch_profile");
+                        return;
+                    }
+                }
+            }
+            not_saved = save_config(app, "switch_profile").err();
+            apply_appearance(app);
+            snap = Some((
+                app.overlay.clone(),
+                app.cfg.clone(),
+                app.current_desktop,
+                app.hwnd,
+            ));
+        }
+    });
+    if let Some((ov, cfg_clone, gid, hwnd)) = snap {
+        tracing::info!(profile=%cfg_clone.active_profile_name(), "Switched profile");
+        draw_overlay_line(&ov, &cfg_clone, gid);
+        refresh_visibility_now();
+        let name = cfg_clone.active_profile_display_name();
+        let text = match not_saved {
+            Some(why) => why.describe(Some(name)),
+            None => name.to_string(),
+        };
+        let _ = mddskmgr::tray::Tray::balloon_for(hwnd, tr("balloon.profile"), &text);
+    }
+}
+
 fn quick_edit(edit_title: bool) {
     // Snapshot state without holding a mutable borrow during the modal UI.
     let snapshot = APP.with(|slot| {
         if let Some(app) = &*slot.borrow() {
-            let key = app.current_guid.clone();
-            let label = app.cfg.desktops.get(&key).cloned().unwrap_or_default();
+            let key = app.current_desktop;
+            let label = app.cfg.labels().get(&key).cloned().unwrap_or_default();
             let caption = if edit_title {
-                "Edit Desktop Title"
+                tr("edit.title_caption")
             } else {
-                "Edit Desktop Description"
+                tr("edit.description_caption")
             };
             let hint = if edit_title {
-                "Change the title"
+                tr("edit.title_hint")
             } else {
-                "Change the description"
+                tr("edit.description_hint")
             };
             let initial = if edit_title {
                 label.title
@@ -232,32 +671,94 @@ fn quick_edit(edit_title: bool) {
     });
 
     if let Some((hwnd, key, caption, hint, initial)) = snapshot {
-        tracing::debug!(caption=%caption, guid=%key, initial=%initial, "quick_edit start");
+        tracing::debug!(caption=%caption, desktop=%key, initial=%initial, "quick_edit start");
         if let Some(newtext) = ui::prompt_text(hwnd, &caption, &hint, &initial) {
             tracing::debug!(text=%newtext, "quick_edit: new text");
-            let mut snap: Option<(Overlay, Config, String)> = None;
+            let mut snap: Option<(Overlay, Config, DesktopId)> = None;
+            let mut not_saved = None;
             APP.with(|slot| {
                 if let Some(app) = &mut *slot.borrow_mut() {
-                    let entry = app.cfg.desktops.entry(key).or_default();
+                    let mut label = app.cfg.labels().get(&key).cloned().unwrap_or_default();
                     if edit_title {
-                        entry.title = newtext;
+                        label.title = newtext;
                     } else {
-                        entry.description = newtext;
+                        label.description = newtext;
                     }
-                    let _ = mddskmgr::config::save_atomic(&app.cfg, &app.cfg_paths);
-                    tracing::debug!(?app.cfg_paths.cfg_file, "quick_edit: saved config");
-                    snap = Some((
-                        app.overlay.clone(),
-                        app.cfg.clone(),
-                        app.current_guid.clone(),
-                    ));
+                    if !app.history.edit(&mut app.cfg, key, label, history::now()) {
+                        return;
+                    }
+                    save_history(app);
+                    match save_config(app, "quick_edit") {
+                        Ok(()) => {
+                            tracing::debug!(?app.cfg_paths.cfg_file, "quick_edit: saved config")
+                        }
+                        Err(why) => not_saved = Some(why),
+                    }
+                    snap = Some((app.overlay.clone(), app.cfg.clone(), app.current_desktop));
                 }
             });
             if let Some((ov, cfg_clone, gid)) = snap {
-                draw_overlay_line(&ov, &cfg_clone, &gid);
+                draw_overlay_line(&ov, &cfg_clone, gid);
                 refresh_visibility_now();
             }
+            if let Some(why) = not_saved {
+                let _ = mddskmgr::tray::Tray::balloon_for(
+                    hwnd,
+                    tr("balloon.config"),
+                    &why.describe(None),
+                );
+            }
+        }
+    }
+}
+
+/// Undo or redo the last label edit on the current desktop.
+fn step_history(redo: bool) {
+    let mut snap: Option<(Overlay, Config, DesktopId, HWND)> = None;
+    let mut stepped = false;
+    let mut not_saved = None;
+    APP.with(|slot| {
+        if let Some(app) = &mut *slot.borrow_mut() {
+            let key = app.current_desktop;
+            stepped = if redo {
+                app.history.redo(&mut app.cfg, key, history::now())
+            } else {
+                app.history.undo(&mut app.cfg, key, history::now())
+            };
+            if stepped {
+                save_history(app);
+                not_saved = save_config(app, "step_history").err();
+            }
+            snap = Some((app.overlay.clone(), app.cfg.clone(), key, app.hwnd));
+        }
+    });
+    let Some((ov, cfg_clone, gid, hwnd)) = snap else {
+        return;
+    };
+    let caption = tr(if redo { "balloon.redo" } else { "balloon.undo" });
+    let text = if !stepped {
+        tr(if redo {
+            "msg.nothing_to_redo"
+        } else {
+            "msg.nothing_to_undo"
+        })
+        .to_string()
+    } else {
+        draw_overlay_line(&ov, &cfg_clone, gid);
+        refresh_visibility_now();
+        let line = mddskmgr::markup::plain(&compute_line(&cfg_clone, gid).0);
+        match not_saved {
+            Some(why) => why.describe(Some(&line)),
+            None => line,
         }
+    };
+    let _ = mddskmgr::tray::Tray::balloon_for(hwnd, caption, &text);
+}
+
+/// History is a convenience: a failed write is logged, not reported.
+fn save_history(app: &AppState) {
+    if let Err(e) = app.history.save(&app.cfg_paths) {
+        tracing::warn!(error=?e, "saving label history failed");
     }
 }
 
@@ -265,7 +766,7 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
     match msg {
         WM_CREATE => {
             APP.with(|slot| {
-                let (cfg, paths) = match config::load_or_default() {
+                let (cfg, paths, problems) = match config::load_with_problems() {
                     Ok(v) => v,
                     Err(e) => {
                         tracing::error!(error=%e, "Fatal: config load failed");
@@ -273,7 +774,8 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
                         return;
                     }
                 };
-                let overlay = match Overlay::new(hwnd, &cfg.appearance.font_family, cfg.appearance.font_size_dip) {
+                let appearance = cfg.effective_appearance();
+                let overlay = match Overlay::new(hwnd, &appearance.font_family, appearance.font_size_dip) {
                     Ok(v) => v,
                     Err(e) => {
                         tracing::error!(error=%e, "Fatal: overlay creation failed");
@@ -282,7 +784,7 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
                     }
                 };
                 let taskbar_created_msg = unsafe { RegisterWindowMessageW(PCWSTR(windows::core::w!("TaskbarCreated").as_wide().as_ptr())) };
-                let tray = match Tray::new(hwnd, "Desktop Labeler") {
+                let tray = match Tray::new(hwnd, &tray_tip(&cfg)) {
                     Ok(v) => v,
                     Err(e) => {
                         tracing::error!(error=%e, "Fatal: tray icon creation failed");
@@ -291,11 +793,15 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
                     }
                 };
 
+                if let Some(text) = config::validate::summarize(&paths.cfg_file, &problems) {
+                    let _ = mddskmgr::tray::Tray::balloon_for(hwnd, tr("balloon.config"), &text);
+                }
+
                 // Register hotkeys (warn on duplicates)
                 let hk = &cfg.hotkeys;
                 if mddskmgr::hotkeys::has_duplicates(hk) {
                     // Show a friendly tray balloon (without holding a RefCell borrow).
-                    let _ = mddskmgr::tray::Tray::balloon_for(hwnd, "Hotkeys", "Duplicate hotkeys detected; adjust labels.json");
+                    let _ = mddskmgr::tray::Tray::balloon_for(hwnd, tr("balloon.hotkeys"), tr("msg.duplicate_hotkeys"));
                 }
                 if !hotkeys::register(hwnd, hk.edit_title.ctrl, hk.edit_title.alt, hk.edit_title.shift, &hk.edit_title.key, HK_EDIT_TITLE).unwrap_or(false) {
                     tracing::warn!(key=%hk.edit_title.key, "Failed to register edit_title hotkey");
@@ -309,16 +815,26 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
                 if !hotkeys::register(hwnd, hk.snap_position.ctrl, hk.snap_position.alt, hk.snap_position.shift, &hk.snap_position.key, hotkeys::HK_SNAP).unwrap_or(false) {
                     tracing::warn!(key=%hk.snap_position.key, "Failed to register snap_position hotkey");
                 }
+                if !hotkeys::register(hwnd, hk.cycle_profile.ctrl, hk.cycle_profile.alt, hk.cycle_profile.shift, &hk.cycle_profile.key, hotkeys::HK_PROFILE).unwrap_or(false) {
+                    tracing::warn!(key=%hk.cycle_profile.key, "Failed to register cycle_profile hotkey");
+                }
+                if !hotkeys::register(hwnd, hk.undo_label.ctrl, hk.undo_label.alt, hk.undo_label.shift, &hk.undo_label.key, hotkeys::HK_UNDO).unwrap_or(false) {
+                    tracing::warn!(key=%hk.undo_label.key, "Failed to register undo_label hotkey");
+                }
+                if !hotkeys::register(hwnd, hk.redo_label.ctrl, hk.redo_label.alt, hk.redo_label.shift, &hk.redo_label.key, hotkeys::HK_REDO).unwrap_or(false) {
+                    tracing::warn!(key=%hk.redo_label.key, "Failed to register redo_label hotkey");
+                }
 
-                let current_guid = vd::get_current_desktop_guid();
+                let current_desktop = vd::get_current_desktop_id();
                 let vd_thread = mddskmgr::vd::start_vd_events(hwnd, WM_VD_SWITCHED);
-                let app = AppState { hwnd, cfg, cfg_paths: paths, overlay, current_guid, visible: true, tray, taskbar_created_msg, vd_thread, hide_for_accessibility: false, hide_for_fullscreen: false, anchor_index: 1 };
+                let mut app = AppState { hwnd, cfg, history: History::load(&paths), cfg_paths: paths, overlay, current_desktop, visible: true, tray, taskbar_created_msg, vd_thread, hide_for_accessibility: false, hide_for_fullscreen: false, anchor_index: 1, pending_matches: Vec::new(), offered_matches: Vec::new(), declined_matches: HashSet::new(), cfg_has_problems: !problems.is_empty() };
+                reconcile_desktops(&mut app);
                 // Draw initial line before storing
                 let ov = app.overlay.clone();
                 let cfg_clone = app.cfg.clone();
-                let gid = app.current_guid.clone();
+                let gid = app.current_desktop;
                 *slot.borrow_mut() = Some(app);
-                draw_overlay_line(&ov, &cfg_clone, &gid);
+                draw_overlay_line(&ov, &cfg_clone, gid);
                 start_runtime_services(hwnd);
             });
             LRESULT(0)
@@ -334,11 +850,13 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
         } =>
         {
             // Re-add the tray icon without keeping a RefCell borrow during Shell calls.
-            let _ = mddskmgr::tray::Tray::re_add_for(hwnd);
+            let tip = APP.with(|slot| slot.borrow().as_ref().map(|app| tray_tip(&app.cfg)));
+            let tip = tip.unwrap_or_else(|| "Desktop Labeler".into());
+            let _ = mddskmgr::tray::Tray::re_add_for(hwnd, &tip);
             LRESULT(0)
         }
         WM_RBUTTONUP | WM_CONTEXTMENU => {
-            let _ = mddskmgr::tray::Tray::show_popup_menu(hwnd);
+            let _ = mddskmgr::tray::Tray::show_popup_menu(hwnd, &profile_menu(), &archive_menu());
             LRESULT(0)
         }
         WM_SETCURSOR => {
@@ -349,40 +867,59 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
         }
         WM_VD_SWITCHED => {
             // Update current GUID, then draw outside of the borrow to avoid re-entrancy
-            let mut snapshot: Option<(Overlay, Config, String)> = None;
+            let mut snapshot: Option<(Overlay, Config, DesktopId)> = None;
             APP.with(|slot| {
                 if let Some(app) = &mut *slot.borrow_mut() {
-                    let id = vd::get_current_desktop_guid();
-                    if id != app.current_guid {
-                        app.current_guid = id.clone();
+                    let id = vd::get_current_desktop_id();
+                    if id != app.current_desktop {
+                        app.current_desktop = id;
+                        // Desktops are usually created or removed around a switch.
+                        reconcile_desktops(app);
                     }
-                    snapshot = Some((
-                        app.overlay.clone(),
-                        app.cfg.clone(),
-                        app.current_guid.clone(),
-                    ));
+                    snapshot = Some((app.overlay.clone(), app.cfg.clone(), app.current_desktop));
                 }
             });
             if let Some((ov, cfg_clone, gid)) = snapshot {
-                draw_overlay_line(&ov, &cfg_clone, &gid);
+                draw_overlay_line(&ov, &cfg_clone, gid);
             }
             LRESULT(0)
         }
+        WM_OFFER_MATCHES => {
+            offer_matches(hwnd);
+            LRESULT(0)
+        }
         WM_CFG_CHANGED => {
             // Reload config and apply labels/hotkeys; show any balloon outside borrow.
             let mut need_balloon = false;
-            let mut snapshot: Option<(Overlay, Config, String, HWND)> = None;
+            let mut cfg_problems: Option<String> = None;
+            let mut snapshot: Option<(Overlay, Config, DesktopId, HWND)> = None;
             APP.with(|slot| {
                 let mut borrow = slot.borrow_mut();
-                if let (Some(app), Ok((new_cfg, _))) =
-                    (&mut *borrow, mddskmgr::config::load_or_default())
+                if let (Some(app), Ok((new_cfg, new_paths, problems))) =
+                    (&mut *borrow, mddskmgr::config::load_with_problems())
                 {
+                    cfg_problems = config::validate::summarize(&new_paths.cfg_file, &problems);
+                    app.cfg_has_problems = !problems.is_empty();
+                    // Keep the running config if the file can't be read at all.
+                    if problems.iter().any(|p| p.is_unreadable()) {
+                        return;
+                    }
                     app.cfg = new_cfg;
+                    apply_language(&app.cfg);
+                    if new_paths.cfg_dir != app.cfg_paths.cfg_dir {
+                        app.history = History::load(&new_paths);
+                    }
+                    // A new labels.toml replaces labels.json from now on.
+                    app.cfg_paths = new_paths;
+                    apply_appearance(app);
                     // Re-register hotkeys
                     mddskmgr::hotkeys::unregister(app.hwnd, HK_EDIT_TITLE);
                     mddskmgr::hotkeys::unregister(app.hwnd, HK_EDIT_DESC);
                     mddskmgr::hotkeys::unregister(app.hwnd, HK_TOGGLE);
                     mddskmgr::hotkeys::unregister(app.hwnd, hotkeys::HK_SNAP);
+                    mddskmgr::hotkeys::unregister(app.hwnd, hotkeys::HK_PROFILE);
+                    mddskmgr::hotkeys::unregister(app.hwnd, hotkeys::HK_UNDO);
+                    mddskmgr::hotkeys::unregister(app.hwnd, hotkeys::HK_REDO);
                     let hk = &app.cfg.hotkeys;
                     let ok1 = mddskmgr::hotkeys::register(
                         app.hwnd,
@@ -420,52 +957,84 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
                         hotkeys::HK_SNAP,
                     )
                     .unwrap_or(false);
-                    if !(ok1 && ok2 && ok3 && ok4) {
+                    let ok5 = mddskmgr::hotkeys::register(
+                        app.hwnd,
+                        hk.cycle_profile.ctrl,
+                        hk.cycle_profile.alt,
+                        hk.cycle_profile.shift,
+                        &hk.cycle_profile.key,
+                        hotkeys::HK_PROFILE,
+                    )
+                    .unwrap_or(false);
+                    let ok6 = mddskmgr::hotkeys::register(
+                        app.hwnd,
+                        hk.undo_label.ctrl,
+                        hk.undo_label.alt,
+                        hk.undo_label.shift,
+                        &hk.undo_label.key,
+                        hotkeys::HK_UNDO,
+                    )
+                    .unwrap_or(false);
+                    let ok7 = mddskmgr::hotkeys::register(
+                        app.hwnd,
+                        hk.redo_label.ctrl,
+                        hk.redo_label.alt,
+                        hk.redo_label.shift,
+                        &hk.redo_label.key,
+                        hotkeys::HK_REDO,
+                    )
+                    .unwrap_or(false);
+                    if !(ok1 && ok2 && ok3 && ok4 && ok5 && ok6 && ok7) {
                         need_balloon = true;
                     }
                     snapshot = Some((
                         app.overlay.clone(),
                         app.cfg.clone(),
-                        app.current_guid.clone(),
+                        app.current_desktop,
                         app.hwnd,
                     ));
                 }
             });
             if let Some((ov, cfg_clone, gid, _)) = snapshot {
-                draw_overlay_line(&ov, &cfg_clone, &gid);
+                draw_overlay_line(&ov, &cfg_clone, gid);
+                let _ = mddskmgr::tray::Tray::set_tip_for(hwnd, &tray_tip(&cfg_clone));
             }
-            if need_balloon {
+            if let Some(text) = cfg_problems {
+                let _ = mddskmgr::tray::Tray::balloon_for(hwnd, tr("balloon.config"), &text);
+            } else if need_balloon {
                 let _ = mddskmgr::tray::Tray::balloon_for(
                     hwnd,
-                    "Hotkeys",
-                    "Some hotkeys failed to register. Adjust in labels.json",
+                    tr("balloon.hotkeys"),
+                    tr("msg.hotkeys_failed"),
                 );
             }
             LRESULT(0)
         }
         WM_TIMER => {
-            if w.0 == TIMER_VD_POLLER {
-                let mut snapshot: Option<(Overlay, Config, String)> = None;
+            if w.0 == TIMER_VD_POLLER || w.0 == TIMER_REDRAW {
+                // The poller only redraws on a desktop switch; time-based
+                // changes come from the TIMER_REDRAW set by draw_overlay_line.
+                let mut snapshot: Option<(Overlay, Config, DesktopId)> = None;
                 APP.with(|slot| {
                     if let Some(app) = &mut *slot.borrow_mut() {
-                        let id = vd::get_current_desktop_guid();
-                        if id != app.current_guid {
-                            app.current_guid = id.clone();
+                        let id = vd::get_current_desktop_id();
+                        let switched = id != app.current_desktop;
+                        if switched {
+                            app.current_desktop = id;
+                        }
+                        if switched || w.0 == TIMER_REDRAW {
+                            snapshot =
+                                Some((app.overlay.clone(), app.cfg.clone(), app.current_desktop));
                         }
-                        snapshot = Some((
-                            app.overlay.clone(),
-                            app.cfg.clone(),
-                            app.current_guid.clone(),
-                        ));
                     }
                 });
                 if let Some((ov, cfg_clone, gid)) = snapshot {
-                    draw_overlay_line(&ov, &cfg_clone, &gid);
+                    draw_overlay_line(&ov, &cfg_clone, gid);
                 }
             } else if w.0 == TIMER_FULLSCREEN_CHECK {
                 APP.with(|slot| {
                     if let Some(app) = &mut *slot.borrow_mut() {
-                        let hide = if app.cfg.appearance.hide_on_fullscreen {
+                        let hide = if app.cfg.effective_appearance().hide_on_fullscreen {
                             is_foreground_fullscreen(app)
                         } else {
                             false
@@ -548,7 +1117,7 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
                     need_refresh = true;
                 }
                 hotkeys::HK_SNAP => {
-                    let mut snap: Option<(Overlay, Config, String, f32)> = None;
+                    let mut snap: Option<(Overlay, Config, DesktopId, f32)> = None;
                     APP.with(|slot| {
                         if let Some(app) = &mut *slot.borrow_mut() {
                             app.anchor_index = (app.anchor_index + 1) % 3;
@@ -556,15 +1125,18 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
                             snap = Some((
                                 app.overlay.clone(),
                                 app.cfg.clone(),
-                                app.current_guid.clone(),
+                                app.current_desktop,
                                 ratio,
                             ));
                         }
                     });
                     if let Some((ov, cfg_clone, gid, _ratio)) = snap {
-                        draw_overlay_line(&ov, &cfg_clone, &gid);
+                        draw_overlay_line(&ov, &cfg_clone, gid);
                     }
                 }
+                hotkeys::HK_PROFILE => switch_profile(ProfileSwitch::Next),
+                hotkeys::HK_UNDO => step_history(false),
+                hotkeys::HK_REDO => step_history(true),
                 _ => {}
             }
             if need_refresh {
@@ -576,7 +1148,11 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
             let l = l.0 as u32;
             match l {
                 WM_CONTEXTMENU | WM_RBUTTONUP => {
-                    let _ = mddskmgr::tray::Tray::show_popup_menu(hwnd);
+                    let _ = mddskmgr::tray::Tray::show_popup_menu(
+                        hwnd,
+                        &profile_menu(),
+                        &archive_menu(),
+                    );
                 }
                 WM_LBUTTONDBLCLK => {
                     APP.with(|slot| {
@@ -617,20 +1193,54 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
                             .map(|app| app.cfg_paths.cfg_file.to_string_lossy().to_string())
                     });
                     if let Some(path) = path {
-                        let wpath: Vec<u16> =
-                            path.encode_utf16().chain(std::iter::once(0)).collect();
-                        unsafe {
-                            let _ = ShellExecuteW(
-                                None,
-                                PCWSTR(windows::core::w!("open").as_wide().as_ptr()),
-                                PCWSTR(wpath.as_ptr()),
-                                None,
-                                None,
-                                SW_SHOWNORMAL,
-                            );
+                        shell_open(&path);
+                    }
+                }
+                tray::CMD_DIAGNOSTICS => {
+                    // Dump where every effective setting comes from and open it.
+                    let paths =
+                        APP.with(|slot| slot.borrow().as_ref().map(|app| app.cfg_paths.clone()));
+                    if let Some(paths) = paths {
+                        let dump =
+                            config::layers::diagnostics(&paths, &config::LayerSources::current());
+                        let out = paths.log_dir.join("config-diagnostics.txt");
+                        let written = std::fs::create_dir_all(&paths.log_dir)
+                            .and_then(|_| std::fs::write(&out, dump));
+                        match written {
+                            Ok(()) => shell_open(&out.to_string_lossy()),
+                            Err(e) => {
+                                tracing::warn!(error=%e, "write diagnostics failed");
+                                let _ = mddskmgr::tray::Tray::balloon_for(
+                                    hwnd,
+                                    tr("balloon.config"),
+                                    tr("msg.diagnostics_failed"),
+                                );
+                            }
                         }
                     }
                 }
+                tray::CMD_RESTORE_BACKUP => {
+                    // The config watcher picks up the restored file and reloads it.
+                    let paths =
+                        APP.with(|slot| slot.borrow().as_ref().map(|app| app.cfg_paths.clone()));
+                    if let Some(paths) = paths {
+                        let text = match config::backup::restore_latest(&paths) {
+                            Ok((backup, _)) => tr_with(
+                                "msg.restored",
+                                &[(
+                                    "file",
+                                    &backup.file_name().unwrap_or_default().to_string_lossy(),
+                                )],
+                            ),
+                            Err(e) => {
+                                tracing::warn!(error=?e, "restore backup failed");
+                                tr("msg.no_backup").to_string()
+                            }
+                        };
+                        let _ =
+                            mddskmgr::tray::Tray::balloon_for(hwnd, tr("balloon.config"), &text);
+                    }
+                }
                 CMD_EXIT => {
                     // Trigger orderly teardown to avoid hangs: destroy window -> WM_DESTROY posts quit.
                     unsafe {
@@ -641,14 +1251,40 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
                     let cur = autorun::get_run_at_login();
                     let _ = autorun::set_run_at_login(!cur);
                 }
-                tray::CMD_ABOUT => unsafe {
-                    let _ = MessageBoxW(
+                tray::CMD_ABOUT => {
+                    let text =
+                        APP.with(|slot| slot.borrow().as_ref().map(|app| about_text(&app.cfg)));
+                    let text: Vec<u16> = text
+                        .unwrap_or_default()
+                        .encode_utf16()
+                        .chain(std::iter::once(0))
+                        .collect();
+                    let caption: Vec<u16> = tr("about.caption")
+                        .encode_utf16()
+                        .chain(std::iter::once(0))
+                        .collect();
+                    unsafe {
+                        let _ = MessageBoxW(
                             hwnd,
-                            PCWSTR(windows::core::w!("Desktop Labeler\r\n\r\nShows a per-desktop title overlay on the primary monitor.\r\n\r\nHotkeys:\r\n  Ctrl+Alt+T  Edit Title\r\n  Ctrl+Alt+D  Edit Description\r\n  Ctrl+Alt+O  Toggle Overlay\r\n  Ctrl+Alt+L  Snap Position").as_wide().as_ptr()),
-                            PCWSTR(windows::core::w!("About Desktop Labeler").as_wide().as_ptr()),
+                            PCWSTR(text.as_ptr()),
+                            PCWSTR(caption.as_ptr()),
                             MB_OK | MB_ICONINFORMATION,
                         );
-                },
+                    }
+                }
+                cmd if cmd >= tray::CMD_ARCHIVE_FIRST => {
+                    if let Some(index) = archive_menu().command_target(cmd) {
+                        reattach_archived(index);
+                    }
+                }
+                cmd if cmd >= tray::CMD_PROFILE_FIRST => {
+                    let target = profile_menu()
+                        .command_target(cmd)
+                        .map(|name| name.map(str::to_string));
+                    if let Some(name) = target {
+                        switch_profile(ProfileSwitch::To(name));
+                    }
+                }
                 _ => {}
             }
             LRESULT(0)
@@ -661,11 +1297,15 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
                         let _ = KillTimer(hwnd, TIMER_VD_POLLER);
                         let _ = KillTimer(hwnd, TIMER_FULLSCREEN_CHECK);
                         let _ = KillTimer(hwnd, TIMER_TOPMOST_REASSERT);
+                        let _ = KillTimer(hwnd, TIMER_REDRAW);
                     }
                     mddskmgr::hotkeys::unregister(app.hwnd, HK_EDIT_TITLE);
                     mddskmgr::hotkeys::unregister(app.hwnd, HK_EDIT_DESC);
                     mddskmgr::hotkeys::unregister(app.hwnd, HK_TOGGLE);
                     mddskmgr::hotkeys::unregister(app.hwnd, hotkeys::HK_SNAP);
+                    mddskmgr::hotkeys::unregister(app.hwnd, hotkeys::HK_PROFILE);
+                    mddskmgr::hotkeys::unregister(app.hwnd, hotkeys::HK_UNDO);
+                    mddskmgr::hotkeys::unregister(app.hwnd, hotkeys::HK_REDO);
                     // Remove tray icon to prevent ghost icons after exit
                     app.tray.remove_icon();
                     // Drop virtual desktop event thread if present
@@ -684,10 +1324,95 @@ extern "system" fn wndproc(hwnd: HWND, msg: u32, w: WPARAM, l: LPARAM) -> LRESUL
     }
 }
 
+/// `--convert-config <json|toml>`: convert the config file in place and
+/// report the outcome in a message box.
+pub fn convert_config(to: &str) -> Result<()> {
+    let outcome = to
+        .parse::<config::Format>()
+        .and_then(|to| config::format::convert(&config::project_paths()?, to));
+    match outcome {
+        Ok(paths) => report(
+            &tr_with(
+                "cli.converted",
+                &[("file", &paths.cfg_file.display().to_string())],
+            ),
+            true,
+        ),
+        Err(e) => report(
+            &tr_with("cli.convert_failed", &[("error", &format!("{e:#}"))]),
+            false,
+        ),
+    }
+    Ok(())
+}
+
+/// `--export` / `--import`: exchange labels with a CSV file or JSON bundle
+/// and report the outcome in a message box.
+pub fn exchange_labels(cmd: Result<config::exchange::Command>) -> Result<()> {
+    let outcome = cmd.and_then(|cmd| {
+        config::exchange::run(&cmd, &config::project_paths()?, &vd::desktop_order())
+    });
+    match outcome {
+        Ok(text) => report(&text, true),
+        Err(e) => report(
+            &tr_with("cli.exchange_failed", &[("error", &format!("{e:#}"))]),
+            false,
+        ),
+    }
+    Ok(())
+}
+
+/// Log `text` and show it in a message box; used by the command-line actions.
+fn report(text: &str, ok: bool) {
+    tracing::info!(%text, ok, "command");
+    let flags = if ok {
+        MB_OK | MB_ICONINFORMATION
+    } else {
+        MB_OK | MB_ICONERROR
+    };
+    let wtext: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    let wcaption: Vec<u16> = tr("app.name")
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    unsafe {
+        let _ = MessageBoxW(
+            None,
+            PCWSTR(wtext.as_ptr()),
+            PCWSTR(wcaption.as_ptr()),
+            flags,
+        );
+    }
+}
+
+fn shell_open(path: &str) {
+    let wpath: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        let _ = ShellExecuteW(
+            None,
+            PCWSTR(windows::core::w!("open").as_wide().as_ptr()),
+            PCWSTR(wpath.as_ptr()),
+            None,
+            None,
+            SW_SHOWNORMAL,
+        );
+    }
+}
+
+/// Title of the overlay window; one instance per config location may run.
+fn instance_title() -> Vec<u16> {
+    config::location::instance_name("Desktop Labeler")
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect()
+}
+
 fn single_instance_guard() -> bool {
     unsafe {
         let class_name = windows::core::w!("DesktopOverlayWndClass");
-        let h = FindWindowW(class_name, None).unwrap_or(HWND(std::ptr::null_mut()));
+        let title = instance_title();
+        let h =
+            FindWindowW(class_name, PCWSTR(title.as_ptr())).unwrap_or(HWND(std::ptr::null_mut()));
         h.0.is_null()
     }
 }
@@ -729,12 +1454,25 @@ fn start_runtime_services(hwnd: HWND) {
         if let Some(cfg_path) = cfg_path_opt {
             let (tx, rx) = std_mpsc::channel::<()>();
             std::thread::spawn(move || {
-                let (watch_tx, watch_rx) = std_mpsc::channel();
+                // Watch the directory so that creating or removing
+                // labels.toml next to labels.json is noticed too.
+                let Some(dir) = cfg_path.parent().map(Path::to_path_buf) else {
+                    return;
+                };
+                let (watch_tx, watch_rx) = std_mpsc::channel::<notify::Result<notify::Event>>();
                 let mut watcher: RecommendedWatcher =
                     Watcher::new(watch_tx, notify::Config::default()).expect("watcher");
-                let _ = watcher.watch(&cfg_path, RecursiveMode::NonRecursive);
-                while let Ok(_ev) = watch_rx.recv() {
-                    let _ = tx.send(());
+                let _ = watcher.watch(&dir, RecursiveMode::NonRecursive);
+                let is_config = |p: &Path| {
+                    p == cfg_path
+                        || p.file_name().is_some_and(|n| {
+                            n == config::format::JSON_FILE || n == config::format::TOML_FILE
+                        })
+                };
+                while let Ok(ev) = watch_rx.recv() {
+                    if ev.is_ok_and(|ev| ev.paths.iter().map(PathBuf::as_path).any(is_config)) {
+                        let _ = tx.send(());
+                    }
                 }
             });
             let hwnd_copy = hwnd.0 as usize;
@@ -779,12 +1517,13 @@ pub fn main() -> Result<()> {
         };
         RegisterClassW(&wc);
 
+        let title = instance_title();
         let hwnd = CreateWindowExW(
             WINDOW_EX_STYLE(
                 (WS_EX_TOOLWINDOW | WS_EX_LAYERED | WS_EX_TOPMOST | WS_EX_NOACTIVATE).0,
             ),
             class_name,
-            windows::core::w!(""),
+            PCWSTR(title.as_ptr()),
             WS_POPUP,
             0,
             0,
@@ -814,17 +1553,20 @@ mod tests {
     use super::*;
     use std::panic::catch_unwind;
 
+    const G1: DesktopId = DesktopId::from_u128(0xD178F97B_2525_4ED7_B219_6BA2AA6BE296);
+
     #[test]
     fn compute_line_with_both_fields() {
         let mut cfg = Config::default();
         cfg.desktops.insert(
-            "g1".into(),
+            G1,
             mddskmgr::config::DesktopLabel {
                 title: "Work".into(),
                 description: "Tickets".into(),
+                ..Default::default()
             },
         );
-        let (line, margin) = compute_line(&cfg, "g1");
+        let (line, margin) = compute_line(&cfg, G1);
         assert_eq!(line, "Work : Tickets");
         assert_eq!(margin, cfg.appearance.margin_px);
     }
@@ -833,13 +1575,14 @@ mod tests {
     fn compute_line_empty_title_uses_default() {
         let mut cfg = Config::default();
         cfg.desktops.insert(
-            "g1".into(),
+            G1,
             mddskmgr::config::DesktopLabel {
                 title: "".into(),
                 description: "desc".into(),
+                ..Default::default()
             },
         );
-        let (line, _) = compute_line(&cfg, "g1");
+        let (line, _) = compute_line(&cfg, G1);
         assert_eq!(line, "Desktop : desc");
     }
 
@@ -847,20 +1590,86 @@ mod tests {
     fn compute_line_whitespace_title_uses_default() {
         let mut cfg = Config::default();
         cfg.desktops.insert(
-            "g1".into(),
+            G1,
             mddskmgr::config::DesktopLabel {
                 title: "   ".into(),
                 description: "".into(),
+                ..Default::default()
             },
         );
-        let (line, _) = compute_line(&cfg, "g1");
+        let (line, _) = compute_line(&cfg, G1);
         assert_eq!(line, "Desktop");
     }
 
+    #[test]
+    fn compute_line_uses_the_desktop_template() {
+        let mut cfg = Config::default();
+        cfg.appearance.line_template = "[{title}]".into();
+        cfg.desktops.insert(
+            G1,
+            mddskmgr::config::DesktopLabel {
+                title: "Work".into(),
+                description: "Tickets".into(),
+                template: Some("{description} ({title})".into()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(compute_line(&cfg, G1).0, "Tickets (Work)");
+        assert_eq!(compute_line(&cfg, DesktopId::from_u128(42)).0, "[]");
+    }
+
+    #[test]
+    fn two_line_layout_moves_the_description_below() {
+        let mut cfg = Config::default();
+        cfg.appearance.layout = OverlayLayout::TwoLines;
+        cfg.desktops.insert(
+            G1,
+            mddskmgr::config::DesktopLabel {
+                title: "Work".into(),
+                description: "Tickets".into(),
+                ..Default::default()
+            },
+        );
+        let line = render_line(&cfg, G1);
+        assert_eq!(line.text, "Work");
+        assert_eq!(line.detail.as_deref(), Some("Tickets"));
+    }
+
+    #[test]
+    fn desktop_appearance_applies_to_its_desktop_only() {
+        let mut cfg = Config::default();
+        let mut label = mddskmgr::config::DesktopLabel {
+            title: "Prod".into(),
+            description: "Careful".into(),
+            ..Default::default()
+        };
+        label.appearance.margin_px = Some(20);
+        label.appearance.layout = Some(OverlayLayout::TwoLines);
+        cfg.desktops.insert(G1, label);
+        let (_, margin) = compute_line(&cfg, G1);
+        assert_eq!(margin, 20);
+        assert_eq!(render_line(&cfg, G1).detail.as_deref(), Some("Careful"));
+        let (_, margin) = compute_line(&cfg, DesktopId::from_u128(42));
+        assert_eq!(margin, cfg.appearance.margin_px);
+    }
+
+    #[test]
+    fn hints_and_about_follow_the_hotkeys() {
+        let mut cfg = Config::default();
+        cfg.hotkeys.edit_title.key = "E".into();
+        assert_eq!(overlay_hints(&cfg, G1), "(Ctrl+Alt+E,D,O,L,P)");
+        assert!(about_text(&cfg).contains("\r\n  Ctrl+Alt+E  Edit Title\r\n"));
+        assert!(tray_tip(&cfg).contains("Ctrl+Alt+E"));
+        let mut label = mddskmgr::config::DesktopLabel::default();
+        label.appearance.show_hints = Some(false);
+        cfg.desktops.insert(G1, label);
+        assert_eq!(overlay_hints(&cfg, G1), "");
+    }
+
     #[test]
     fn compute_line_unknown_guid_uses_defaults() {
         let cfg = Config::default();
-        let (line, _) = compute_line(&cfg, "nonexistent");
+        let (line, _) = compute_line(&cfg, DesktopId::from_u128(42));
         assert_eq!(line, "Desktop");
     }
 
@@ -891,9 +1700,10 @@ mod tests {
             let app = super::AppState {
                 hwnd: HWND(std::ptr::null_mut()),
                 cfg,
+                history: History::load(&paths),
                 cfg_paths: paths,
                 overlay,
-                current_guid: "default".into(),
+                current_desktop: DesktopId::FALLBACK,
                 visible: true,
                 tray,
                 taskbar_created_msg: 0,
@@ -901,6 +1711,8 @@ mod tests {
                 hide_for_accessibility: false,
                 hide_for_fullscreen: false,
                 anchor_index: 1,
+                pending_matches: Vec::new(),
+                declined_matches: HashSet::new(),
             };
             *slot.borrow_mut() = Some(app);
         });
@@ -937,9 +1749,10 @@ mod tests {
                         let app = AppState {
                             hwnd,
                             cfg,
+                            history: History::load(&paths),
                             cfg_paths: paths,
                             overlay,
-                            current_guid: "default".into(),
+                            current_desktop: DesktopId::FALLBACK,
                             visible: true,
                             tray,
                             taskbar_created_msg: 0,
@@ -947,6 +1760,8 @@ mod tests {
                             hide_for_accessibility: false,
                             hide_for_fullscreen: false,
                             anchor_index: 1,
+                            pending_matches: Vec::new(),
+                            declined_matches: HashSet::new(),
                         };
                         *slot.borrow_mut() = Some(app);
                     });
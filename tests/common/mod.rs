@@ -0,0 +1,26 @@
+//! Helpers shared by the integration tests. Each test file compiles its own
+//! copy, so helpers one of them does not use are allowed to go unused.
+#![allow(dead_code)]
+
+use mddskmgr::config::{DesktopLabel, Paths};
+use std::fs;
+use std::path::Path;
+
+/// Config and log paths under `base`, with the config folder created.
+pub fn temp_paths(base: &Path) -> Paths {
+    let cfg_dir = base.join("cfg");
+    fs::create_dir_all(&cfg_dir).unwrap();
+    Paths {
+        cfg_file: cfg_dir.join("labels.json"),
+        cfg_dir,
+        log_dir: base.join("log"),
+    }
+}
+
+pub fn label(title: &str, description: &str) -> DesktopLabel {
+    DesktopLabel {
+        title: title.into(),
+        description: description.into(),
+        ..Default::default()
+    }
+}
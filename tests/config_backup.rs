@@ -0,0 +1,122 @@
+use mddskmgr::config::backup::{self, KEEP_BACKUPS};
+use mddskmgr::config::{Config, DesktopId, DesktopLabel, load_from, save_atomic};
+use pretty_assertions::assert_eq;
+use std::fs;
+
+mod common;
+use common::temp_paths;
+
+const ID: DesktopId = DesktopId::from_u128(0xD178F97B_2525_4ED7_B219_6BA2AA6BE296);
+
+fn labelled(title: &str) -> Config {
+    let mut cfg = Config::default();
+    cfg.desktops.insert(
+        ID,
+        DesktopLabel {
+            title: title.into(),
+            ..Default::default()
+        },
+    );
+    cfg
+}
+
+#[test]
+fn save_backs_up_previous_file() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    save_atomic(&labelled("one"), &paths).unwrap();
+    assert!(backup::list_backups(&paths).is_empty());
+
+    save_atomic(&labelled("two"), &paths).unwrap();
+    let backups = backup::list_backups(&paths);
+    assert_eq!(backups.len(), 1);
+    let saved: Config = serde_json::from_str(&fs::read_to_string(&backups[0]).unwrap()).unwrap();
+    assert_eq!(saved.desktops[&ID].title, "one");
+}
+
+#[test]
+fn identical_saves_do_not_rotate_history() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    save_atomic(&labelled("one"), &paths).unwrap();
+    for _ in 0..3 {
+        save_atomic(&labelled("one"), &paths).unwrap();
+    }
+    assert_eq!(backup::list_backups(&paths).len(), 1);
+}
+
+#[test]
+fn keeps_at_most_n_backups() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    for i in 0..KEEP_BACKUPS + 5 {
+        save_atomic(&labelled(&format!("v{i}")), &paths).unwrap();
+        // Backup names have millisecond resolution.
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    let backups = backup::list_backups(&paths);
+    assert_eq!(backups.len(), KEEP_BACKUPS);
+    let newest: Config = serde_json::from_str(&fs::read_to_string(&backups[0]).unwrap()).unwrap();
+    assert_eq!(newest.desktops[&ID].title, format!("v{}", KEEP_BACKUPS + 3));
+}
+
+#[test]
+fn corrupt_file_falls_back_to_newest_valid_backup() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    save_atomic(&labelled("good"), &paths).unwrap();
+    save_atomic(&labelled("better"), &paths).unwrap();
+    fs::write(&paths.cfg_file, "{ broken").unwrap();
+
+    let (cfg, problems) = load_from(&paths);
+    assert_eq!(problems.len(), 1);
+    assert_eq!(cfg.desktops[&ID].title, "good");
+    // The corrupt file is left for the user to inspect or restore over.
+    assert_eq!(fs::read_to_string(&paths.cfg_file).unwrap(), "{ broken");
+}
+
+#[test]
+fn restore_latest_replaces_corrupt_file_and_keeps_it() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    save_atomic(&labelled("good"), &paths).unwrap();
+    save_atomic(&labelled("better"), &paths).unwrap();
+    fs::write(&paths.cfg_file, "{ broken").unwrap();
+
+    let (_, cfg) = backup::restore_latest(&paths).expect("restore");
+    assert_eq!(cfg.desktops[&ID].title, "good");
+    let (reloaded, problems) = load_from(&paths);
+    assert!(problems.is_empty());
+    assert_eq!(reloaded.desktops[&ID].title, "good");
+    // The broken file itself was backed up before being replaced.
+    let kept = backup::list_backups(&paths)
+        .iter()
+        .any(|p| fs::read_to_string(p).unwrap() == "{ broken");
+    assert!(kept);
+}
+
+#[test]
+fn restoring_twice_walks_back_through_backups() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    for title in ["one", "two", "three"] {
+        save_atomic(&labelled(title), &paths).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+
+    let (_, cfg) = backup::restore_latest(&paths).expect("first restore");
+    assert_eq!(cfg.desktops[&ID].title, "two");
+    let (_, cfg) = backup::restore_latest(&paths).expect("second restore");
+    assert_eq!(cfg.desktops[&ID].title, "one");
+    assert!(backup::restore_latest(&paths).is_err());
+    let (reloaded, _) = load_from(&paths);
+    assert_eq!(reloaded.desktops[&ID].title, "one");
+}
+
+#[test]
+fn restore_latest_without_backups_fails() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    save_atomic(&Config::default(), &paths).unwrap();
+    assert!(backup::restore_latest(&paths).is_err());
+}
@@ -0,0 +1,187 @@
+use mddskmgr::config::exchange::{self, Command, MergeStrategy};
+use mddskmgr::config::location::paths_for;
+use mddskmgr::config::{Config, DesktopId, backup, load_from, save_atomic};
+use std::fs;
+
+mod common;
+use common::label;
+
+const HOME_1: DesktopId = DesktopId::from_u128(0x11);
+const HOME_2: DesktopId = DesktopId::from_u128(0x12);
+const WORK_1: DesktopId = DesktopId::from_u128(0x21);
+const WORK_2: DesktopId = DesktopId::from_u128(0x22);
+
+#[test]
+fn bundle_moves_labels_between_machines_by_index() {
+    let home = tempfile::tempdir().expect("tmpdir");
+    let home_paths = paths_for(home.path());
+    let mut cfg = Config::default();
+    cfg.desktops.insert(HOME_1, label("Mail", "inbox zero"));
+    cfg.desktops.insert(HOME_2, label("Code", ""));
+    cfg.appearance.font_size_dip = 22;
+    save_atomic(&cfg, &home_paths).expect("save");
+
+    let bundle = home.path().join("export.json");
+    let msg = exchange::run(
+        &Command::Export {
+            path: bundle.clone(),
+            with_settings: true,
+        },
+        &home_paths,
+        &[HOME_1, HOME_2],
+    )
+    .expect("export");
+    assert!(msg.contains("2 label(s)"), "{msg}");
+
+    let work = tempfile::tempdir().expect("tmpdir");
+    let work_paths = paths_for(work.path());
+    let mut existing = Config::default();
+    existing.desktops.insert(WORK_2, label("Meetings", ""));
+    save_atomic(&existing, &work_paths).expect("save");
+
+    let msg = exchange::run(
+        &Command::Import {
+            path: bundle,
+            strategy: MergeStrategy::ByIndex,
+        },
+        &work_paths,
+        &[WORK_1, WORK_2],
+    )
+    .expect("import");
+    assert!(msg.contains("1 added, 1 updated"), "{msg}");
+
+    let (imported, problems) = load_from(&work_paths);
+    assert!(problems.is_empty(), "{problems:?}");
+    assert_eq!(imported.desktops[&WORK_1].title, "Mail");
+    assert_eq!(imported.desktops[&WORK_1].description, "inbox zero");
+    assert_eq!(imported.desktops[&WORK_2].title, "Code");
+    assert!(!imported.desktops.contains_key(&HOME_1));
+    assert_eq!(imported.appearance.font_size_dip, 22);
+    assert!(!backup::list_backups(&work_paths).is_empty());
+}
+
+#[test]
+fn csv_export_imports_back_without_settings() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = paths_for(td.path());
+    let mut cfg = Config::default();
+    cfg.desktops
+        .insert(HOME_1, label("Mail, chat", "line \"quoted\""));
+    save_atomic(&cfg, &paths).expect("save");
+
+    let csv = td.path().join("labels.csv");
+    let export = |with_settings| Command::Export {
+        path: csv.clone(),
+        with_settings,
+    };
+    assert!(exchange::run(&export(true), &paths, &[]).is_err());
+    exchange::run(&export(false), &paths, &[]).expect("export");
+    let text = fs::read_to_string(&csv).unwrap();
+    assert!(
+        text.starts_with("desktop_id,index,title,description\n"),
+        "{text}"
+    );
+
+    // Keep-existing leaves the edited label alone and adds the new row.
+    let edited =
+        text.replace("Mail, chat", "Edited") + "{00000000-0000-0000-0000-000000000012},,Code,\n";
+    fs::write(&csv, edited).unwrap();
+    let msg = exchange::run(
+        &Command::Import {
+            path: csv,
+            strategy: MergeStrategy::KeepExisting,
+        },
+        &paths,
+        &[],
+    )
+    .expect("import");
+    assert!(
+        msg.contains("1 added, 0 updated, 0 unchanged, 1 kept"),
+        "{msg}"
+    );
+    let (after, _) = load_from(&paths);
+    assert_eq!(after.desktops[&HOME_1].title, "Mail, chat");
+    assert_eq!(after.desktops[&HOME_2].title, "Code");
+}
+
+#[test]
+fn import_refuses_a_config_with_problems() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = paths_for(td.path());
+    fs::write(
+        &paths.cfg_file,
+        r#"{ "appearance": { "font_size_dip": 0 }, "version": 2 }"#,
+    )
+    .unwrap();
+    let bundle = td.path().join("in.json");
+    fs::write(
+        &bundle,
+        r#"{ "format": "mddsklbl-labels", "version": 1, "desktops": [] }"#,
+    )
+    .unwrap();
+    let err = exchange::run(
+        &Command::Import {
+            path: bundle,
+            strategy: MergeStrategy::Overwrite,
+        },
+        &paths,
+        &[],
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("font"), "{err}");
+    assert!(
+        fs::read_to_string(&paths.cfg_file)
+            .unwrap()
+            .contains("\"font_size_dip\": 0")
+    );
+}
+
+#[test]
+fn import_refuses_settings_that_do_not_validate() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = paths_for(td.path());
+    let mut cfg = Config::default();
+    cfg.desktops.insert(HOME_1, label("Mail", ""));
+    save_atomic(&cfg, &paths).expect("save");
+
+    let mut bundle = exchange::bundle(&Config::default(), &[], true);
+    bundle.appearance.as_mut().unwrap().font_size_dip = 0;
+    bundle.hotkeys.as_mut().unwrap().toggle_overlay.key = "".into();
+    let file = td.path().join("in.json");
+    fs::write(&file, exchange::to_json(&bundle)).unwrap();
+    let err = exchange::run(
+        &Command::Import {
+            path: file,
+            strategy: MergeStrategy::Overwrite,
+        },
+        &paths,
+        &[],
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("cannot be imported"), "{err}");
+    let (after, problems) = load_from(&paths);
+    assert!(problems.is_empty(), "{problems:?}");
+    assert_eq!(after.appearance.font_size_dip, 16);
+}
+
+#[test]
+fn settings_export_the_top_level_appearance() {
+    let mut cfg = Config::default();
+    cfg.appearance.font_size_dip = 20;
+    cfg.profiles.insert(
+        "Home".into(),
+        serde_json::from_str(r#"{ "appearance": { "font_size_dip": 30 } }"#).unwrap(),
+    );
+    cfg.set_active_profile(Some("Home")).unwrap();
+    let bundle = exchange::bundle(&cfg, &[], true);
+    assert_eq!(bundle.appearance.unwrap().font_size_dip, 20);
+
+    let mut other = Config::default();
+    exchange::import(
+        &mut other,
+        &exchange::bundle(&cfg, &[], true),
+        MergeStrategy::Overwrite,
+        &[],
+    );
+    assert_eq!(other.appearance.font_size_dip, 20);
+}
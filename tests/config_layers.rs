@@ -0,0 +1,196 @@
+use mddskmgr::config::layers::diagnostics;
+use mddskmgr::config::{
+    ConfigError, DesktopId, DesktopLabel, Layer, LayerSources, load_layered, save_layered,
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::fs;
+
+mod common;
+use common::temp_paths;
+
+fn sources(base: &std::path::Path, machine: Value, env: &[(&str, &str)]) -> LayerSources {
+    let machine_file = base.join("defaults.json");
+    fs::write(&machine_file, machine.to_string()).unwrap();
+    LayerSources {
+        machine_file: Some(machine_file),
+        env: env
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    }
+}
+
+fn read_json(path: &std::path::Path) -> Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn machine_defaults_apply_until_the_user_overrides_them() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let src = sources(
+        td.path(),
+        json!({
+            "appearance": { "font_family": "Consolas", "font_size_dip": 18 },
+            "hotkeys": { "edit_title": { "ctrl": true, "alt": true, "shift": true, "key": "E" } }
+        }),
+        &[],
+    );
+    fs::write(
+        &paths.cfg_file,
+        r#"{ "appearance": { "font_size_dip": 22 }, "version": 2 }"#,
+    )
+    .unwrap();
+
+    let (cfg, problems) = load_layered(&paths, &src.read());
+    assert!(problems.is_empty());
+    assert_eq!(cfg.appearance.font_family, "Consolas");
+    assert_eq!(cfg.appearance.font_size_dip, 22);
+    assert_eq!(cfg.hotkeys.edit_title.key, "E");
+    assert_eq!(cfg.hotkeys.toggle_overlay.key, "O");
+}
+
+#[test]
+fn saving_keeps_machine_and_env_values_out_of_the_user_file() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let src = sources(
+        td.path(),
+        json!({ "appearance": { "font_family": "Consolas" } }),
+        &[("MDDSKLBL__APPEARANCE__MARGIN_PX", "24")],
+    );
+    let layers = src.read();
+
+    // First run: no user file yet.
+    let (mut cfg, problems) = load_layered(&paths, &layers);
+    assert!(problems.is_empty());
+    assert_eq!(cfg.appearance.margin_px, 24);
+
+    let id: DesktopId = "D178F97B-2525-4ED7-B219-6BA2AA6BE296".parse().unwrap();
+    cfg.desktops.insert(
+        id,
+        DesktopLabel {
+            title: "Work".into(),
+            ..Default::default()
+        },
+    );
+    save_layered(&cfg, &paths, &layers).expect("save");
+
+    let saved = read_json(&paths.cfg_file);
+    assert_eq!(saved["desktops"][id.to_string()]["title"], "Work");
+    assert!(saved["appearance"].get("font_family").is_none());
+    assert!(saved["appearance"].get("margin_px").is_none());
+
+    // A later change by IT still reaches this user.
+    let src = sources(
+        td.path(),
+        json!({ "appearance": { "font_family": "Cascadia Mono" } }),
+        &[],
+    );
+    let (cfg, _) = load_layered(&paths, &src.read());
+    assert_eq!(cfg.appearance.font_family, "Cascadia Mono");
+    assert_eq!(cfg.appearance.margin_px, 8);
+    assert_eq!(cfg.desktops[&id].title, "Work");
+}
+
+#[test]
+fn bad_overrides_are_dropped_and_the_user_file_kept() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let src = sources(
+        td.path(),
+        json!({ "appearance": { "margin_px": "wide" } }),
+        &[
+            ("MDDSKLBL__HOTKEYS__EDIT_TITLE__KEY", "7"),
+            ("MDDSKLBL__APPEARANCE__FONT_SIZE_DIP", "big"),
+        ],
+    );
+    let user = r#"{ "desktops": { "D178F97B-2525-4ED7-B219-6BA2AA6BE296": { "title": "Work", "description": "" } }, "version": 2 }"#;
+    fs::write(&paths.cfg_file, user).unwrap();
+
+    let (cfg, problems) = load_layered(&paths, &src.read());
+    assert_eq!(cfg.hotkeys.edit_title.key, "7");
+    assert_eq!(cfg.appearance.font_size_dip, 16);
+    assert_eq!(cfg.appearance.margin_px, 8);
+    assert_eq!(cfg.desktops.len(), 1);
+    let layers: Vec<_> = problems
+        .iter()
+        .map(|p| match p {
+            ConfigError::Override { layer, path, .. } => (*layer, path.as_str()),
+            other => panic!("unexpected problem {other}"),
+        })
+        .collect();
+    assert_eq!(
+        layers,
+        [
+            (Layer::Environment, "appearance.font_size_dip"),
+            (Layer::Machine, "appearance.margin_px")
+        ]
+    );
+    assert_eq!(fs::read_to_string(&paths.cfg_file).unwrap(), user);
+}
+
+#[test]
+fn broken_machine_file_is_ignored() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let machine_file = td.path().join("defaults.json");
+    fs::write(&machine_file, "{ nope").unwrap();
+    let src = LayerSources {
+        machine_file: Some(machine_file),
+        env: Vec::new(),
+    };
+    let (cfg, problems) = load_layered(&paths, &src.read());
+    assert!(problems.is_empty());
+    assert_eq!(cfg.appearance.font_family, "Segoe UI");
+}
+
+#[test]
+fn provenance_reports_the_winning_layer() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let src = sources(
+        td.path(),
+        json!({ "appearance": { "font_size_dip": 18, "margin_px": 2 } }),
+        &[("MDDSKLBL__APPEARANCE__MARGIN_PX", "6")],
+    );
+    let user = json!({ "appearance": { "font_size_dip": 20 } });
+    let (_, prov) = src.read().apply(Some(&user));
+    assert_eq!(
+        prov.layer_of("appearance.font_family"),
+        Some(Layer::Default)
+    );
+    assert_eq!(prov.layer_of("appearance.font_size_dip"), Some(Layer::User));
+    assert_eq!(
+        prov.layer_of("appearance.margin_px"),
+        Some(Layer::Environment)
+    );
+
+    fs::write(&paths.cfg_file, user.to_string()).unwrap();
+    let dump = diagnostics(&paths, &src);
+    assert!(
+        dump.contains("appearance.font_size_dip = 20  [user]"),
+        "{dump}"
+    );
+    assert!(
+        dump.contains("appearance.margin_px = 6  [environment]"),
+        "{dump}"
+    );
+    assert!(dump.contains("environment overrides: 1"), "{dump}");
+}
+
+#[test]
+fn diagnostics_read_a_toml_user_file() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let mut paths = temp_paths(td.path());
+    paths.cfg_file = paths.cfg_dir.join("labels.toml");
+    fs::write(&paths.cfg_file, "[appearance]\nfont_size_dip = 20\n").unwrap();
+    let src = LayerSources::default();
+    let dump = diagnostics(&paths, &src);
+    assert!(dump.contains("labels.toml (loaded)"), "{dump}");
+    assert!(
+        dump.contains("appearance.font_size_dip = 20  [user]"),
+        "{dump}"
+    );
+}
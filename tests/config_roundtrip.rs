@@ -1,15 +1,24 @@
-use mddskmgr::config::{Appearance, Config, DesktopLabel, Hotkeys, KeyChord, Paths, save_atomic};
+use mddskmgr::config::{
+    Appearance, Config, DesktopId, DesktopLabel, Ellipsis, Hotkeys, KeyChord, OverlayLayout,
+    OverlayPosition, Paths, TextAlign, TextDirection, ThemePreset, load_from, migrate,
+    parse_config, save_atomic,
+};
 use pretty_assertions::assert_eq;
 use std::fs;
 
+mod common;
+use common::temp_paths;
+
 #[test]
 fn save_and_load_roundtrip() {
     let mut cfg = Config::default();
+    let id: DesktopId = "D178F97B-2525-4ED7-B219-6BA2AA6BE296".parse().unwrap();
     cfg.desktops.insert(
-        "guid-1".into(),
+        id,
         DesktopLabel {
             title: "Work".into(),
             description: "Tickets".into(),
+            ..Default::default()
         },
     );
     cfg.hotkeys = Hotkeys {
@@ -18,31 +27,79 @@ fn save_and_load_roundtrip() {
             alt: true,
             shift: false,
             key: "T".into(),
+            extra: Default::default(),
         },
         edit_description: KeyChord {
             ctrl: true,
             alt: true,
             shift: false,
             key: "D".into(),
+            extra: Default::default(),
         },
         toggle_overlay: KeyChord {
             ctrl: true,
             alt: true,
             shift: false,
             key: "O".into(),
+            extra: Default::default(),
         },
         snap_position: KeyChord {
             ctrl: true,
             alt: true,
             shift: false,
             key: "L".into(),
+            extra: Default::default(),
+        },
+        cycle_profile: KeyChord {
+            ctrl: true,
+            alt: true,
+            shift: false,
+            key: "P".into(),
+            extra: Default::default(),
         },
+        undo_label: KeyChord {
+            ctrl: true,
+            alt: true,
+            shift: false,
+            key: "Z".into(),
+            extra: Default::default(),
+        },
+        redo_label: KeyChord {
+            ctrl: true,
+            alt: true,
+            shift: false,
+            key: "Y".into(),
+            extra: Default::default(),
+        },
+        extra: Default::default(),
     };
     cfg.appearance = Appearance {
         font_family: "Segoe UI".into(),
         font_size_dip: 16,
         margin_px: 8,
         hide_on_fullscreen: false,
+        line_template: "{index}. {title|Desktop}".into(),
+        layout: OverlayLayout::TwoLines,
+        description_size_percent: 60,
+        description_opacity_percent: 80,
+        max_width_px: 480,
+        max_lines: 3,
+        max_chars: 40,
+        ellipsis: Ellipsis::Middle,
+        theme: ThemePreset::Solarized,
+        foreground: Some("#fdf6e3".into()),
+        background: None,
+        border_color: None,
+        accent: None,
+        border_px: Some(0),
+        opacity_percent: 90,
+        corner_radius_px: Some(4),
+        padding_px: None,
+        position: Some(OverlayPosition::Right),
+        show_hints: false,
+        direction: TextDirection::Rtl,
+        align: TextAlign::Start,
+        extra: Default::default(),
     };
 
     let td = tempfile::tempdir().expect("tmpdir");
@@ -59,8 +116,23 @@ fn save_and_load_roundtrip() {
     save_atomic(&cfg, &paths).expect("save");
     let data = fs::read_to_string(&paths.cfg_file).expect("read file");
     let parsed: Config = serde_json::from_str(&data).expect("json");
-    assert_eq!(parsed.desktops.get("guid-1").unwrap().title, "Work");
+    assert_eq!(parsed.desktops.get(&id).unwrap().title, "Work");
     assert_eq!(parsed.hotkeys.toggle_overlay.key, "O");
+    assert_eq!(parsed.appearance.layout, OverlayLayout::TwoLines);
+    assert_eq!(parsed.appearance.max_width_px, 480);
+    assert_eq!(parsed.appearance.max_lines, 3);
+    assert_eq!(parsed.appearance.max_chars, 40);
+    assert_eq!(parsed.appearance.ellipsis, Ellipsis::Middle);
+    assert_eq!(parsed.appearance.opacity_percent, 90);
+    let theme = parsed.appearance.theme_colors();
+    let solarized = ThemePreset::Solarized.theme();
+    assert_eq!(theme.foreground.to_string(), "#fdf6e3ff");
+    assert_eq!(theme.background, solarized.background);
+    assert_eq!(theme.border_px, 0);
+    assert_eq!(parsed.appearance.position, Some(OverlayPosition::Right));
+    assert!(!parsed.appearance.show_hints);
+    assert_eq!(parsed.appearance.direction, TextDirection::Rtl);
+    assert_eq!(parsed.appearance.align, TextAlign::Start);
 }
 
 #[test]
@@ -98,6 +170,47 @@ fn version_migration_changes_snap_key_from_s_to_l() {
     // not in save_atomic. Verify the raw data is "S":
     assert_eq!(reloaded.hotkeys.snap_position.key, "S");
     assert!(reloaded.version.is_none());
+
+    let (migrated, problems) = load_from(&paths);
+    assert!(problems.is_empty());
+    assert_eq!(migrated.hotkeys.snap_position.key, "L");
+    assert_eq!(migrated.version, Some(migrate::CURRENT_VERSION));
+    // The migrated result is persisted.
+    let data = fs::read_to_string(&paths.cfg_file).expect("read");
+    let persisted: Config = serde_json::from_str(&data).expect("json");
+    assert_eq!(persisted.hotkeys.snap_position.key, "L");
+    assert_eq!(persisted.version, Some(migrate::CURRENT_VERSION));
+}
+
+#[test]
+fn load_does_not_rewrite_current_config() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let cfg = Config {
+        version: Some(migrate::CURRENT_VERSION),
+        ..Config::default()
+    };
+    // Compact formatting differs from what save_atomic writes, so any rewrite is visible.
+    let compact = serde_json::to_string(&cfg).unwrap();
+    fs::write(&paths.cfg_file, &compact).unwrap();
+
+    let (loaded, problems) = load_from(&paths);
+    assert!(problems.is_empty());
+    assert_eq!(loaded.version, Some(migrate::CURRENT_VERSION));
+    assert_eq!(fs::read_to_string(&paths.cfg_file).unwrap(), compact);
+}
+
+#[test]
+fn load_creates_missing_config_at_current_version() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+
+    let (loaded, problems) = load_from(&paths);
+    assert!(problems.is_empty());
+    assert_eq!(loaded.version, Some(migrate::CURRENT_VERSION));
+    let data = fs::read_to_string(&paths.cfg_file).expect("file created");
+    let persisted: Config = serde_json::from_str(&data).expect("json");
+    assert_eq!(persisted.version, Some(migrate::CURRENT_VERSION));
 }
 
 #[test]
@@ -131,3 +244,211 @@ fn default_config_has_expected_values() {
     assert!(cfg.desktops.is_empty());
     assert!(cfg.version.is_none());
 }
+
+#[test]
+fn legacy_debug_keys_are_normalized_on_load() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let mut doc = serde_json::to_value(Config {
+        version: Some(1),
+        ..Config::default()
+    })
+    .unwrap();
+    doc["desktops"] = serde_json::json!({
+        "Desktop(Guid(D178F97B-2525-4ED7-B219-6BA2AA6BE296))": { "title": "Work", "description": "" },
+        "{0a1b2c3d-0000-1111-2222-333344445555}": { "title": "Chat", "description": "" }
+    });
+    fs::write(&paths.cfg_file, doc.to_string()).unwrap();
+
+    let (cfg, problems) = load_from(&paths);
+    assert!(problems.is_empty());
+    let work: DesktopId = "D178F97B-2525-4ED7-B219-6BA2AA6BE296".parse().unwrap();
+    let chat: DesktopId = "0A1B2C3D-0000-1111-2222-333344445555".parse().unwrap();
+    assert_eq!(cfg.desktops[&work].title, "Work");
+    assert_eq!(cfg.desktops[&chat].title, "Chat");
+    let raw: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&paths.cfg_file).unwrap()).unwrap();
+    assert!(raw["desktops"]["0A1B2C3D-0000-1111-2222-333344445555"].is_object());
+}
+
+#[test]
+fn unknown_fields_survive_load_and_save() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    // A file written by a newer build, with fields this build has never heard of.
+    let mut doc = serde_json::to_value(Config {
+        version: Some(migrate::CURRENT_VERSION),
+        ..Config::default()
+    })
+    .unwrap();
+    doc["sync"] = serde_json::json!({ "enabled": true, "peers": ["laptop"] });
+    doc["hotkeys"]["open_palette"] =
+        serde_json::json!({ "ctrl": true, "alt": true, "shift": false, "key": "P" });
+    doc["appearance"]["corner_radius"] = serde_json::json!(6);
+    doc["hotkeys"]["edit_title"]["win"] = serde_json::json!(true);
+    doc["desktops"] = serde_json::json!({
+        "D178F97B-2525-4ED7-B219-6BA2AA6BE296": {
+            "title": "Work",
+            "description": "",
+            "icon": "briefcase"
+        }
+    });
+    fs::write(&paths.cfg_file, doc.to_string()).unwrap();
+
+    let (mut cfg, problems) = load_from(&paths);
+    assert!(problems.is_empty());
+    assert_eq!(cfg.extra["sync"]["peers"][0], "laptop");
+    assert_eq!(cfg.hotkeys.extra["open_palette"]["key"], "P");
+    assert_eq!(cfg.hotkeys.edit_title.extra["win"], true);
+    assert_eq!(cfg.appearance.extra["corner_radius"], 6);
+    let work: DesktopId = "D178F97B-2525-4ED7-B219-6BA2AA6BE296".parse().unwrap();
+    assert_eq!(cfg.desktops[&work].extra["icon"], "briefcase");
+
+    // An edit by this build keeps everything it did not touch.
+    cfg.appearance.font_size_dip = 20;
+    save_atomic(&cfg, &paths).expect("save");
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&paths.cfg_file).unwrap()).unwrap();
+    let mut expected = doc;
+    expected["appearance"]["font_size_dip"] = serde_json::json!(20);
+    expected["$schema"] = serde_json::json!(mddskmgr::config::schema::SCHEMA_REF);
+    assert_eq!(saved, expected);
+}
+
+#[test]
+fn known_fields_are_not_duplicated_into_extras() {
+    let cfg: Config =
+        serde_json::from_value(serde_json::to_value(Config::default()).unwrap()).expect("json");
+    assert!(cfg.extra.is_empty());
+    assert!(cfg.hotkeys.extra.is_empty());
+    assert!(cfg.hotkeys.edit_title.extra.is_empty());
+    assert!(cfg.appearance.extra.is_empty());
+}
+
+#[test]
+fn comments_and_trailing_commas_survive_an_edit() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let text = r#"{
+  // One desktop per client.
+  "desktops": {
+    "D178F97B-2525-4ED7-B219-6BA2AA6BE296": { "title": "Client A", "description": "" }, // billable
+  },
+  /* Bigger text for the wall display. */
+  "appearance": { "font_size_dip": 24, },
+  "version": 2,
+}
+"#;
+    fs::write(&paths.cfg_file, text).unwrap();
+
+    let (mut cfg, problems) = load_from(&paths);
+    assert!(problems.is_empty(), "{problems:?}");
+    assert_eq!(cfg.appearance.font_size_dip, 24);
+    let id: DesktopId = "D178F97B-2525-4ED7-B219-6BA2AA6BE296".parse().unwrap();
+    assert_eq!(cfg.desktops[&id].title, "Client A");
+
+    cfg.desktops.get_mut(&id).unwrap().title = "Client A (retainer)".into();
+    save_atomic(&cfg, &paths).expect("save");
+
+    let saved = fs::read_to_string(&paths.cfg_file).unwrap();
+    assert!(saved.contains("// One desktop per client."), "{saved}");
+    assert!(saved.contains("// billable"), "{saved}");
+    assert!(
+        saved.contains("/* Bigger text for the wall display. */"),
+        "{saved}"
+    );
+    assert!(saved.contains("\"Client A (retainer)\""), "{saved}");
+    let (reloaded, problems) = load_from(&paths);
+    assert!(problems.is_empty());
+    assert_eq!(reloaded.desktops[&id].title, "Client A (retainer)");
+    assert_eq!(reloaded.appearance.font_size_dip, 24);
+}
+
+#[test]
+fn profiles_round_trip_through_the_file() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let id: DesktopId = "D178F97B-2525-4ED7-B219-6BA2AA6BE296".parse().unwrap();
+    fs::write(
+        &paths.cfg_file,
+        r#"{
+  "desktops": { "D178F97B-2525-4ED7-B219-6BA2AA6BE296": { "title": "Work", "description": "" } },
+  "profiles": {
+    "On-call": {
+      "desktops": { "D178F97B-2525-4ED7-B219-6BA2AA6BE296": { "title": "Pager", "description": "" } },
+      "appearance": { "font_size_dip": 28 }
+    }
+  },
+  "version": 2
+}"#,
+    )
+    .unwrap();
+
+    let (mut cfg, problems) = load_from(&paths);
+    assert!(problems.is_empty(), "{problems:?}");
+    assert_eq!(cfg.labels()[&id].title, "Work");
+    cfg.set_active_profile(Some("On-call")).unwrap();
+    assert_eq!(cfg.effective_appearance().font_size_dip, 28);
+    cfg.labels_mut().get_mut(&id).unwrap().description = "Week 42".into();
+    save_atomic(&cfg, &paths).expect("save");
+
+    let (reloaded, problems) = load_from(&paths);
+    assert!(problems.is_empty(), "{problems:?}");
+    assert_eq!(reloaded.active_profile.as_deref(), Some("On-call"));
+    assert_eq!(reloaded.labels()[&id].description, "Week 42");
+    assert_eq!(reloaded.desktops[&id].description, "");
+    assert_eq!(reloaded.effective_appearance().font_size_dip, 28);
+    assert_eq!(reloaded.appearance.font_size_dip, 16);
+}
+
+#[test]
+fn archived_labels_round_trip_through_the_file() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let gone: DesktopId = "D178F97B-2525-4ED7-B219-6BA2AA6BE296".parse().unwrap();
+    let live: DesktopId = "0A1B2C3D-0000-1111-2222-333344445555".parse().unwrap();
+    let mut cfg = Config::default();
+    for id in [gone, live] {
+        cfg.desktops.insert(
+            id,
+            DesktopLabel {
+                title: id.to_string(),
+                ..Default::default()
+            },
+        );
+    }
+    cfg.reconcile(&[live], 1_000, 0);
+    save_atomic(&cfg, &paths).expect("save");
+
+    let text = fs::read_to_string(&paths.cfg_file).unwrap();
+    assert!(text.contains("\"archived\""), "{text}");
+    let (mut reloaded, problems) = load_from(&paths);
+    assert!(problems.is_empty(), "{problems:?}");
+    assert_eq!(reloaded.archived.labels.len(), 1);
+    assert_eq!(reloaded.archived.labels[0].desktop_id, gone);
+    assert_eq!(reloaded.archived.labels[0].archived_at, 1_000);
+
+    let fresh: DesktopId = "11111111-2222-3333-4444-555555555555".parse().unwrap();
+    reloaded.reattach(0, fresh).unwrap();
+    save_atomic(&reloaded, &paths).expect("save");
+    let text = fs::read_to_string(&paths.cfg_file).unwrap();
+    assert!(!text.contains("\"archived\""), "{text}");
+}
+
+#[test]
+fn labels_and_chords_may_leave_out_fields() {
+    let cfg = parse_config(
+        r#"{
+            "desktops": { "D178F97B-2525-4ED7-B219-6BA2AA6BE296": { "title": "Work" } },
+            "hotkeys": { "edit_title": { "key": "E" } }
+        }"#,
+    )
+    .expect("parse");
+    let work: DesktopId = "D178F97B-2525-4ED7-B219-6BA2AA6BE296".parse().unwrap();
+    assert_eq!(cfg.desktops[&work].description, "");
+    let chord = &cfg.hotkeys.edit_title;
+    assert_eq!(
+        (chord.ctrl, chord.alt, chord.key.as_str()),
+        (true, true, "E")
+    );
+}
@@ -0,0 +1,80 @@
+use mddskmgr::config::schema::{SCHEMA_FILE, SCHEMA_REF, schema_json};
+use mddskmgr::config::{Config, load_from, save_atomic};
+use std::fs;
+use std::path::Path;
+
+mod common;
+use common::temp_paths;
+
+/// The shipped schema must match the types. Run with `UPDATE_SCHEMA=1` to
+/// regenerate it after changing the config structs.
+#[test]
+fn checked_in_schema_is_current() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("schema")
+        .join(SCHEMA_FILE);
+    let generated = schema_json();
+    if std::env::var_os("UPDATE_SCHEMA").is_some() {
+        fs::write(&path, &generated).unwrap();
+    }
+    let checked_in = fs::read_to_string(&path)
+        .unwrap_or_default()
+        .replace("\r\n", "\n");
+    assert!(
+        checked_in == generated,
+        "schema/{SCHEMA_FILE} is stale; rerun the tests with UPDATE_SCHEMA=1"
+    );
+}
+
+#[test]
+fn schema_carries_validation_rules() {
+    let schema: serde_json::Value = serde_json::from_str(&schema_json()).unwrap();
+    let defs = &schema["definitions"];
+    assert_eq!(
+        defs["Appearance"]["properties"]["font_size_dip"]["minimum"],
+        1.0
+    );
+    assert_eq!(
+        defs["Appearance"]["properties"]["margin_px"]["minimum"],
+        0.0
+    );
+    assert!(defs["KeyChord"]["properties"]["key"]["pattern"].is_string());
+    let desktops = &schema["properties"]["desktops"];
+    assert!(desktops["propertyNames"]["pattern"].is_string());
+    assert_eq!(
+        desktops["additionalProperties"]["$ref"],
+        "#/definitions/DesktopLabel"
+    );
+    // Every top-level setting is optional; missing ones take their defaults.
+    assert!(schema.get("required").is_none());
+    assert!(defs["KeyChord"].get("required").is_none());
+    assert!(defs["DesktopLabel"].get("required").is_none());
+    assert_eq!(
+        schema["properties"]["appearance"]["default"]["font_size_dip"],
+        16
+    );
+}
+
+#[test]
+fn saved_file_references_schema_next_to_it() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    save_atomic(&Config::default(), &paths).expect("save");
+
+    let raw: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&paths.cfg_file).unwrap()).unwrap();
+    assert_eq!(raw["$schema"], SCHEMA_REF);
+    assert_eq!(
+        fs::read_to_string(paths.cfg_dir.join(SCHEMA_FILE)).unwrap(),
+        schema_json()
+    );
+
+    // A reference the user chose is left alone.
+    let (mut cfg, problems) = load_from(&paths);
+    assert!(problems.is_empty());
+    cfg.schema = Some("https://example.invalid/labels.schema.json".into());
+    save_atomic(&cfg, &paths).expect("save");
+    let raw: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&paths.cfg_file).unwrap()).unwrap();
+    assert_eq!(raw["$schema"], "https://example.invalid/labels.schema.json");
+}
@@ -0,0 +1,138 @@
+use mddskmgr::config::format::{self, Format};
+use mddskmgr::config::location::paths_for;
+use mddskmgr::config::source::Location;
+use mddskmgr::config::{ConfigError, DesktopId, backup, load_from, save_atomic};
+use pretty_assertions::assert_eq;
+use std::fs;
+
+const ID: DesktopId = DesktopId::from_u128(0xD178F97B_2525_4ED7_B219_6BA2AA6BE296);
+
+const TOML: &str = r#"# Desktops by client
+version = 2
+
+[desktops.D178F97B-2525-4ED7-B219-6BA2AA6BE296]
+title = "Client A" # retainer
+description = ""
+
+[appearance]
+font_size_dip = 24
+"#;
+
+#[test]
+fn labels_toml_takes_precedence() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    fs::write(
+        td.path().join("labels.json"),
+        r#"{ "appearance": { "font_size_dip": 12 }, "version": 2 }"#,
+    )
+    .unwrap();
+    fs::write(td.path().join("labels.toml"), TOML).unwrap();
+
+    let paths = paths_for(td.path());
+    assert_eq!(paths.cfg_file, td.path().join("labels.toml"));
+    let (cfg, problems) = load_from(&paths);
+    assert!(problems.is_empty(), "{problems:?}");
+    assert_eq!(cfg.appearance.font_size_dip, 24);
+    assert_eq!(cfg.desktops[&ID].title, "Client A");
+    assert_eq!(cfg.appearance.font_family, "Segoe UI");
+}
+
+#[test]
+fn saving_toml_keeps_format_and_comments() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    fs::write(td.path().join("labels.toml"), TOML).unwrap();
+    let paths = paths_for(td.path());
+
+    let (mut cfg, _) = load_from(&paths);
+    cfg.desktops.get_mut(&ID).unwrap().title = "Client B".into();
+    save_atomic(&cfg, &paths).expect("save");
+
+    let saved = fs::read_to_string(&paths.cfg_file).unwrap();
+    assert!(saved.starts_with("# Desktops by client\n"), "{saved}");
+    assert!(saved.contains("title = \"Client B\" # retainer"), "{saved}");
+    assert!(!saved.contains("$schema"), "{saved}");
+    assert!(!td.path().join("labels.json").exists());
+    assert!(!td.path().join("labels.schema.json").exists());
+
+    let backups = backup::list_backups(&paths);
+    assert_eq!(backups.len(), 1);
+    assert!(backups[0].to_string_lossy().ends_with(".toml"));
+    assert_eq!(fs::read_to_string(&backups[0]).unwrap(), TOML);
+}
+
+#[test]
+fn toml_files_are_migrated() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let old = "[hotkeys.snap_position]\nctrl = true\nalt = true\nshift = false\nkey = \"S\"\n";
+    fs::write(td.path().join("labels.toml"), old).unwrap();
+    let paths = paths_for(td.path());
+
+    let (cfg, problems) = load_from(&paths);
+    assert!(problems.is_empty(), "{problems:?}");
+    assert_eq!(cfg.hotkeys.snap_position.key, "L");
+    let saved = fs::read_to_string(&paths.cfg_file).unwrap();
+    assert!(saved.contains("key = \"L\""), "{saved}");
+    assert!(saved.contains("version = 2"), "{saved}");
+}
+
+#[test]
+fn toml_problems_have_locations() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    fs::write(
+        td.path().join("labels.toml"),
+        "version = 2\n\n[appearance]\nfont_size_dip = 0\n",
+    )
+    .unwrap();
+    let (_, problems) = load_from(&paths_for(td.path()));
+    assert_eq!(
+        problems,
+        vec![ConfigError::ZeroFontSize {
+            path: "appearance.font_size_dip".into(),
+            location: Some(Location {
+                line: 4,
+                column: 17
+            }),
+        }]
+    );
+}
+
+#[test]
+fn conversion_round_trips_between_formats() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    fs::write(td.path().join("labels.toml"), TOML).unwrap();
+    let paths = paths_for(td.path());
+    let (before, _) = load_from(&paths);
+
+    let json = format::convert(&paths, Format::Json).expect("to json");
+    assert_eq!(json.cfg_file, td.path().join("labels.json"));
+    assert!(!td.path().join("labels.toml").exists());
+    assert_eq!(paths_for(td.path()).cfg_file, json.cfg_file);
+    let text = fs::read_to_string(&json.cfg_file).unwrap();
+    assert!(text.contains("\"$schema\""), "{text}");
+    let (as_json, problems) = load_from(&json);
+    assert!(problems.is_empty(), "{problems:?}");
+    assert_eq!(as_json.desktops[&ID].title, before.desktops[&ID].title);
+    assert_eq!(as_json.appearance.font_size_dip, 24);
+
+    let toml = format::convert(&json, Format::Toml).expect("to toml");
+    assert!(!td.path().join("labels.json").exists());
+    let (as_toml, problems) = load_from(&toml);
+    assert!(problems.is_empty(), "{problems:?}");
+    assert_eq!(as_toml.desktops[&ID].title, "Client A");
+    assert_eq!(as_toml.appearance.font_size_dip, 24);
+
+    // The originals are kept as backups.
+    assert_eq!(backup::list_backups(&toml).len(), 1);
+    assert_eq!(backup::list_backups(&json).len(), 1);
+    assert!(format::convert(&toml, Format::Toml).is_err());
+}
+
+#[test]
+fn unreadable_config_is_not_converted() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    fs::write(td.path().join("labels.toml"), "version = \n").unwrap();
+    let paths = paths_for(td.path());
+    assert!(format::convert(&paths, Format::Json).is_err());
+    assert!(td.path().join("labels.toml").exists());
+    assert!(!td.path().join("labels.json").exists());
+}
@@ -0,0 +1,182 @@
+use mddskmgr::config::source::Location;
+use mddskmgr::config::{Config, ConfigError, DesktopId, load_from, save_atomic};
+use pretty_assertions::assert_eq;
+use std::fs;
+
+mod common;
+use common::temp_paths;
+
+const ID: DesktopId = DesktopId::from_u128(0xD178F97B_2525_4ED7_B219_6BA2AA6BE296);
+
+const VALID_WITH_BAD_VALUES: &str = r#"{
+  "desktops": { "D178F97B-2525-4ED7-B219-6BA2AA6BE296": { "title": "Client A", "description": "" } },
+  "hotkeys": {
+    "edit_title": { "ctrl": true, "alt": true, "shift": false, "key": "" },
+    "edit_description": { "ctrl": true, "alt": true, "shift": false, "key": "D" },
+    "toggle_overlay": { "ctrl": true, "alt": true, "shift": false, "key": "F13" },
+    "snap_position": { "ctrl": true, "alt": true, "shift": false, "key": "L" }
+  },
+  "appearance": {
+    "font_family": "Segoe UI",
+    "font_size_dip": 0,
+    "margin_px": -3
+  },
+  "version": 2
+}"#;
+
+#[test]
+fn reports_invalid_values_with_path_and_location() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    fs::write(&paths.cfg_file, VALID_WITH_BAD_VALUES).unwrap();
+
+    let (cfg, problems) = load_from(&paths);
+    assert_eq!(
+        problems,
+        vec![
+            ConfigError::EmptyHotkeyKey {
+                path: "hotkeys.edit_title.key".into(),
+                location: Some(Location {
+                    line: 4,
+                    column: 71
+                }),
+            },
+            ConfigError::UnknownKeyName {
+                path: "hotkeys.toggle_overlay.key".into(),
+                location: Some(Location {
+                    line: 6,
+                    column: 75
+                }),
+                key: "F13".into(),
+            },
+            ConfigError::ZeroFontSize {
+                path: "appearance.font_size_dip".into(),
+                location: Some(Location {
+                    line: 11,
+                    column: 22
+                }),
+            },
+            ConfigError::NegativeMargin {
+                path: "appearance.margin_px".into(),
+                location: Some(Location {
+                    line: 12,
+                    column: 18
+                }),
+                value: -3,
+            },
+        ]
+    );
+    // Labels survive; only the invalid values fall back to defaults.
+    assert_eq!(cfg.desktops.get(&ID).unwrap().title, "Client A");
+    assert_eq!(cfg.hotkeys.edit_title.key, "T");
+    assert_eq!(cfg.hotkeys.toggle_overlay.key, "O");
+    assert_eq!(cfg.appearance.font_size_dip, 16);
+    assert_eq!(cfg.appearance.margin_px, 8);
+    // The file is left as the user wrote it.
+    assert_eq!(
+        fs::read_to_string(&paths.cfg_file).unwrap(),
+        VALID_WITH_BAD_VALUES
+    );
+}
+
+#[test]
+fn syntax_error_reports_line_and_column() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let broken =
+        "{\n  \"desktops\": {\n    \"g1\": { \"title\": \"Work\" \"description\": \"\" }\n  }\n}";
+    fs::write(&paths.cfg_file, broken).unwrap();
+
+    let (cfg, problems) = load_from(&paths);
+    assert!(cfg.desktops.is_empty());
+    match problems.as_slice() {
+        [ConfigError::Syntax { line, .. }] => assert_eq!(*line, 3),
+        other => panic!("unexpected problems: {other:?}"),
+    }
+}
+
+#[test]
+fn type_error_reports_field_path() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let text = VALID_WITH_BAD_VALUES.replace("\"font_size_dip\": 0", "\"font_size_dip\": \"big\"");
+    fs::write(&paths.cfg_file, &text).unwrap();
+
+    let (_, problems) = load_from(&paths);
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].path(), "appearance.font_size_dip");
+    assert_eq!(
+        problems[0].location(),
+        Some(Location {
+            line: 11,
+            column: 22
+        })
+    );
+}
+
+#[test]
+fn unparseable_file_is_never_clobbered() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let broken = "{ \"desktops\": { \"g1\": { \"title\": \"Work\", } }";
+    fs::write(&paths.cfg_file, broken).unwrap();
+
+    let (mut cfg, problems) = load_from(&paths);
+    assert_eq!(problems.len(), 1);
+    assert_eq!(fs::read_to_string(&paths.cfg_file).unwrap(), broken);
+
+    // A later save (e.g. after editing a label) must not replace the file either.
+    cfg.desktops.insert(DesktopId::FALLBACK, Default::default());
+    assert!(save_atomic(&cfg, &paths).is_err());
+    assert_eq!(fs::read_to_string(&paths.cfg_file).unwrap(), broken);
+}
+
+#[test]
+fn non_utf8_file_is_never_clobbered() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    // "Büro" saved in Windows-1252 rather than UTF-8.
+    let ansi = b"{ \"desktops\": { \"g1\": { \"title\": \"B\xfcro\" } } }".to_vec();
+    fs::write(&paths.cfg_file, &ansi).unwrap();
+
+    let (cfg, problems) = load_from(&paths);
+    assert!(matches!(problems[..], [ConfigError::Unreadable { .. }]));
+    assert!(problems[0].is_unreadable());
+    assert_eq!(fs::read(&paths.cfg_file).unwrap(), ansi);
+
+    assert!(save_atomic(&cfg, &paths).is_err());
+    assert_eq!(fs::read(&paths.cfg_file).unwrap(), ansi);
+}
+
+#[test]
+fn valid_file_can_be_saved_over() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    save_atomic(&Config::default(), &paths).expect("initial save");
+    let (mut cfg, problems) = load_from(&paths);
+    assert!(problems.is_empty());
+    cfg.appearance.font_size_dip = 20;
+    save_atomic(&cfg, &paths).expect("second save");
+    let (reloaded, _) = load_from(&paths);
+    assert_eq!(reloaded.appearance.font_size_dip, 20);
+}
+
+#[test]
+fn locations_account_for_comments() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let paths = temp_paths(td.path());
+    let text = "{\n  // size\n  \"appearance\": { /* tiny */ \"font_size_dip\": 0 }\n}";
+    fs::write(&paths.cfg_file, text).unwrap();
+
+    let (_, problems) = load_from(&paths);
+    assert_eq!(
+        problems,
+        vec![ConfigError::ZeroFontSize {
+            path: "appearance.font_size_dip".into(),
+            location: Some(Location {
+                line: 3,
+                column: 47
+            }),
+        }]
+    );
+}
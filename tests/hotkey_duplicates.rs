@@ -9,25 +9,51 @@ fn detects_duplicate_hotkeys() {
             alt: true,
             shift: false,
             key: "T".into(),
+            extra: Default::default(),
         },
         edit_description: KeyChord {
             ctrl: true,
             alt: true,
             shift: false,
             key: "D".into(),
+            extra: Default::default(),
         },
         toggle_overlay: KeyChord {
             ctrl: true,
             alt: true,
             shift: false,
             key: "O".into(),
+            extra: Default::default(),
         },
         snap_position: KeyChord {
             ctrl: true,
             alt: true,
             shift: false,
             key: "S".into(),
+            extra: Default::default(),
         },
+        cycle_profile: KeyChord {
+            ctrl: true,
+            alt: true,
+            shift: false,
+            key: "P".into(),
+            extra: Default::default(),
+        },
+        undo_label: KeyChord {
+            ctrl: true,
+            alt: true,
+            shift: false,
+            key: "Z".into(),
+            extra: Default::default(),
+        },
+        redo_label: KeyChord {
+            ctrl: true,
+            alt: true,
+            shift: false,
+            key: "Y".into(),
+            extra: Default::default(),
+        },
+        extra: Default::default(),
     };
     assert!(!has_duplicates(&hk));
     // Collide description with title